@@ -1,6 +1,9 @@
 use std::convert::TryFrom;
-use criterion::{criterion_group, criterion_main, Criterion, BenchmarkId};
-use project2::{RedBlackTree, AVLTree, BinarySearchTree};
+use criterion::{criterion_group, criterion_main, Criterion, BenchmarkId, BatchSize};
+use project2::{RedBlackTree, LeftLeaningRedBlackTree, AVLTree, RelaxedAVLTree, AATree, WAVLTree, BinarySearchTree};
+use project2::tree::Tree;
+use project2::tree::inspect::TreeBalance;
+use project2::tree::Op;
 
 fn generate_values(limit: u32) -> Vec<u32> {
     let mut values = Vec::with_capacity(usize::try_from(limit).unwrap());
@@ -24,7 +27,23 @@ fn bench_balanced_tree(c: &mut Criterion) {
                     // Insert tree_size elements into tree
                     data.iter().take(*num).for_each(|v| tree.insert(*v));
                     // Search for the first tree_size/10 elements in the tree
-                    data.iter().take(num/10).for_each(|v| { tree.search(&v); });
+                    data.iter().take(num/10).for_each(|v| { tree.search(v); });
+                    tree
+                })
+            }
+        );
+
+        // Benchmark Left Leaning Red Black Tree
+        group.bench_with_input(
+            BenchmarkId::new("Left Leaning Red Black Tree", tree_size),
+            tree_size,
+            |b, num| {
+                b.iter_with_large_drop(|| {
+                    let mut tree = LeftLeaningRedBlackTree::new();
+                    // Insert tree_size elements into tree
+                    data.iter().take(*num).for_each(|v| tree.insert(*v));
+                    // Search for the first tree_size/10 elements in the tree
+                    data.iter().take(num/10).for_each(|v| { tree.search(v); });
                     tree
                 })
             }
@@ -40,7 +59,23 @@ fn bench_balanced_tree(c: &mut Criterion) {
                     // Insert tree_size elements into tree
                     data.iter().take(*num).for_each(|v| tree.insert(*v));
                     // Search for the first tree_size/10 elements in the tree
-                    data.iter().take(num/10).for_each(|v| { tree.search(&v); });
+                    data.iter().take(num/10).for_each(|v| { tree.search(v); });
+                    tree
+                })
+            }
+        );
+
+        // Benchmark AA Tree
+        group.bench_with_input(
+            BenchmarkId::new("AA Tree", tree_size),
+            tree_size,
+            |b, num| {
+                b.iter_with_large_drop(|| {
+                    let mut tree = AATree::new();
+                    // Insert tree_size elements into tree
+                    data.iter().take(*num).for_each(|v| tree.insert(*v));
+                    // Search for the first tree_size/10 elements in the tree
+                    data.iter().take(num/10).for_each(|v| { tree.search(v); });
                     tree
                 })
             }
@@ -58,7 +93,23 @@ fn bench_balanced_tree(c: &mut Criterion) {
                         // Insert tree_size elements into tree
                         data.iter().take(*num).for_each(|v| tree.insert(*v));
                         // Search for the first tree_size/10 elements in the tree
-                        data.iter().take(num/10).for_each(|v| { tree.search(&v); });
+                        data.iter().take(num/10).for_each(|v| { tree.search(v); });
+                        tree
+                    })
+                }
+            );
+
+            // Benchmark Binary Search Tree rebuilt whenever it grows past twice the minimal height
+            group.bench_with_input(
+                BenchmarkId::new("Binary Search Tree (Auto Rebuild)", tree_size),
+                tree_size,
+                |b, num| {
+                    b.iter_with_large_drop(|| {
+                        let mut tree = BinarySearchTree::with_auto_rebuild(2.0);
+                        // Insert tree_size elements into tree
+                        data.iter().take(*num).for_each(|v| tree.insert(*v));
+                        // Search for the first tree_size/10 elements in the tree
+                        data.iter().take(num/10).for_each(|v| { tree.search(v); });
                         tree
                     })
                 }
@@ -69,5 +120,191 @@ fn bench_balanced_tree(c: &mut Criterion) {
     group.finish()
 }
 
-criterion_group!(benches, bench_balanced_tree);
+/// Generates a batch of operations in a scrambled order, inserting
+/// the odd keys and deleting the even keys below 2 * limit
+fn generate_batch(limit: u32) -> Vec<Op<u32>> {
+    let mut batch: Vec<Op<u32>> = (0..limit * 2).map(|v| {
+        if v % 2 == 0 { Op::Delete(v) } else { Op::Insert(v) }
+    }).collect();
+    // Shuffle with a simple linear congruential generator so runs are reproducible
+    let mut seed: u64 = 421;
+    for i in (1..batch.len()).rev() {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        batch.swap(i, usize::try_from(seed >> 33).unwrap() % (i + 1));
+    }
+    batch
+}
+
+fn bench_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Tree Batch Insert + Delete");
+    for tree_size in &[10000, 40000, 70000] {
+        // Start from a tree holding the even keys
+        let base: Vec<u32> = (0..*tree_size * 2).step_by(2).collect();
+        let batch = generate_batch(*tree_size);
+
+        // Benchmark applying operations one at a time
+        group.bench_with_input(
+            BenchmarkId::new("Red Black Tree Repeated", tree_size),
+            tree_size,
+            |b, _| {
+                b.iter_batched_ref(
+                    || {
+                        let mut tree = RedBlackTree::new();
+                        base.iter().for_each(|v| tree.insert(*v));
+                        (tree, batch.clone())
+                    },
+                    |(tree, batch)| {
+                        for op in batch.drain(..) {
+                            match op {
+                                Op::Insert(v) => tree.insert(v),
+                                Op::Delete(v) => { tree.delete(&v); }
+                            }
+                        }
+                    },
+                    BatchSize::LargeInput
+                )
+            }
+        );
+
+        // Benchmark applying operations as a batch
+        group.bench_with_input(
+            BenchmarkId::new("Red Black Tree Batch", tree_size),
+            tree_size,
+            |b, _| {
+                b.iter_batched_ref(
+                    || {
+                        let mut tree = RedBlackTree::new();
+                        base.iter().for_each(|v| tree.insert(*v));
+                        (tree, batch.clone())
+                    },
+                    |(tree, batch)| tree.apply_batch(batch.drain(..)),
+                    BatchSize::LargeInput
+                )
+            }
+        );
+
+        // AA trees cannot be rebuilt, so a merged batch has its nodes appended again
+        group.bench_with_input(
+            BenchmarkId::new("AA Tree Repeated", tree_size),
+            tree_size,
+            |b, _| {
+                b.iter_batched_ref(
+                    || {
+                        let mut tree = AATree::new();
+                        base.iter().for_each(|v| tree.insert(*v));
+                        (tree, batch.clone())
+                    },
+                    |(tree, batch)| {
+                        for op in batch.drain(..) {
+                            match op {
+                                Op::Insert(v) => tree.insert(v),
+                                Op::Delete(v) => { tree.delete(&v); }
+                            }
+                        }
+                    },
+                    BatchSize::LargeInput
+                )
+            }
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("AA Tree Batch", tree_size),
+            tree_size,
+            |b, _| {
+                b.iter_batched_ref(
+                    || {
+                        let mut tree = AATree::new();
+                        base.iter().for_each(|v| tree.insert(*v));
+                        (tree, batch.clone())
+                    },
+                    |(tree, batch)| tree.apply_batch(batch.drain(..)),
+                    BatchSize::LargeInput
+                )
+            }
+        );
+    }
+    group.finish()
+}
+
+/// Inserts the keys into a tree and then deletes every other one
+fn insert_delete<U: TreeBalance>(data: &[u32]) -> Tree<u32, U> {
+    let mut tree = Tree::new();
+    data.iter().for_each(|v| tree.insert(*v));
+    data.iter().step_by(2).for_each(|v| { tree.delete(v); });
+    tree
+}
+
+/// Returns the height of a tree and the number of rotations used to build it
+fn shape_of<U: TreeBalance>(tree: &Tree<u32, U>) -> (usize, usize) {
+    (tree.height(), tree.rotations())
+}
+
+fn bench_delete(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Tree Insert + Delete");
+    let data = generate_batch(70000).into_iter().map(|op| *op.key()).collect::<Vec<u32>>();
+    for tree_size in &[10000, 40000, 70000] {
+        let data = &data[..*tree_size];
+
+        // Report how many rotations each tree needs for the same operations
+        let avl_rotations = insert_delete::<project2::avl::AVLBalance>(data).rotations();
+        let wavl_rotations = insert_delete::<project2::wavl::WAVLBalance>(data).rotations();
+        let rb_rotations = insert_delete::<project2::redblack::RedBlackBalance>(data).rotations();
+        let llrb_rotations = insert_delete::<project2::redblack::LeftLeaningRedBlackBalance>(data).rotations();
+        println!("Rotations for {} keys: AVL Tree {}, WAVL Tree {}, Red Black Tree {}, Left Leaning Red Black Tree {}",
+            tree_size, avl_rotations, wavl_rotations, rb_rotations, llrb_rotations);
+
+        // Report how the height tolerance of a relaxed AVL tree trades height for rotations
+        let relaxed = [
+            (1, shape_of(&insert_delete::<project2::avl::RelaxedAVLBalance<1>>(data))),
+            (2, shape_of(&insert_delete::<project2::avl::RelaxedAVLBalance<2>>(data))),
+            (3, shape_of(&insert_delete::<project2::avl::RelaxedAVLBalance<3>>(data))),
+        ];
+        for (k, (height, rotations)) in relaxed {
+            println!("Relaxed AVL Tree (K = {}) for {} keys: height {}, rotations {}", k, tree_size, height, rotations);
+        }
+
+        // Benchmark AVL Tree
+        group.bench_with_input(
+            BenchmarkId::new("AVL Tree", tree_size),
+            tree_size,
+            |b, _| b.iter_with_large_drop(|| -> AVLTree<u32> { insert_delete(data) })
+        );
+
+        // Benchmark Relaxed AVL Trees
+        group.bench_with_input(
+            BenchmarkId::new("Relaxed AVL Tree (K = 2)", tree_size),
+            tree_size,
+            |b, _| b.iter_with_large_drop(|| -> RelaxedAVLTree<u32, 2> { insert_delete(data) })
+        );
+        group.bench_with_input(
+            BenchmarkId::new("Relaxed AVL Tree (K = 3)", tree_size),
+            tree_size,
+            |b, _| b.iter_with_large_drop(|| -> RelaxedAVLTree<u32, 3> { insert_delete(data) })
+        );
+
+        // Benchmark WAVL Tree
+        group.bench_with_input(
+            BenchmarkId::new("WAVL Tree", tree_size),
+            tree_size,
+            |b, _| b.iter_with_large_drop(|| -> WAVLTree<u32> { insert_delete(data) })
+        );
+
+        // Benchmark Red Black Tree
+        group.bench_with_input(
+            BenchmarkId::new("Red Black Tree", tree_size),
+            tree_size,
+            |b, _| b.iter_with_large_drop(|| -> RedBlackTree<u32> { insert_delete(data) })
+        );
+
+        // Benchmark Left Leaning Red Black Tree
+        group.bench_with_input(
+            BenchmarkId::new("Left Leaning Red Black Tree", tree_size),
+            tree_size,
+            |b, _| b.iter_with_large_drop(|| -> LeftLeaningRedBlackTree<u32> { insert_delete(data) })
+        );
+    }
+    group.finish()
+}
+
+criterion_group!(benches, bench_balanced_tree, bench_batch, bench_delete);
 criterion_main!(benches);
\ No newline at end of file
@@ -0,0 +1,290 @@
+use core::fmt::Debug;
+
+use crate::tree::TreePath;
+use crate::tree::inspect::*;
+
+/// Implementation of a balance for a binary tree
+/// that effectively converts a [Tree] into an AA Tree
+///
+/// Each node stores a level (leaves are at level one).  A left child is always one level
+/// below its parent, while a right child may share its parent's level (a horizontal link)
+/// as long as two horizontal links never follow each other.  The tree is kept balanced
+/// with two operations: skew (rotating a horizontal left link to the right) and
+/// split (rotating two horizontal right links to the left and raising the middle node).
+pub struct AABalance {
+    level: usize
+}
+
+impl TreeBalance for AABalance {
+    type State = usize;
+    type Cache = ();
+
+    fn rebalance_insert<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, path: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
+        // Each node on the insertion path is fixed by its parent, since fixing this node could bring
+        // the inserted node up to be a child of the next node which stops rebalancing.
+        // Raising a node can break the invariants two levels up, so this continues until neither the
+        // child nor this node needed fixing (nothing above can have changed), or to the root
+        // (which is fixed by itself).
+        let fixed = levels_at(&node, &[path.0]).is_some_and(unfit);
+        let mut node = skew_split(node, &[path.0]);
+        if !fixed && !unfit(levels(&node)) {
+            return node.into_position(NodeOffset::Root);
+        }
+        if node.inspect_is_root() {
+            node = skew_split(node, &[]);
+        }
+        node.into_position(NodeOffset::Parent)
+    }
+
+    fn new<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+        AABalance { level: 1 }
+    }
+
+    fn new_root<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+        AABalance { level: 1 }
+    }
+
+    fn rebalance_delete<T: Ord, A: Augment<T>>(mut node: NodeInspector<T, Self, A>, _: TreePath, _: &Self) -> TreePosition<T, Self, A> {
+        // Lower the node (and a horizontal right child) if a child is now too low
+        let (level, left, right) = {
+            let level = |path| node.inspect_child(path).map_or(0, |n| n.inspect_balance(|b| b.level));
+            (node.inspect_balance(|b| b.level), level(TreePath::Left), level(TreePath::Right))
+        };
+        let expected = left.min(right) + 1;
+        if expected < level {
+            node.update_balance(|b| b.level = expected);
+            if right > expected {
+                node.inspect_child(TreePath::Right).unwrap().update_balance(|b| b.level = expected);
+            }
+        }
+
+        // Lowering can leave up to three horizontal links in a row along the right side
+        for paths in [&[][..], &[TreePath::Right], &[TreePath::Right, TreePath::Right]] {
+            node = skew(node, paths);
+        }
+        for paths in [&[][..], &[TreePath::Right]] {
+            node = split(node, paths);
+        }
+        node.into_position(NodeOffset::Parent)
+    }
+
+    fn verify<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Result<(), ValidationError> {
+        let (level, left, right, right_right) = levels(node);
+        if left + 1 != level {
+            Err(ValidationError::Balance("AA left child is not one level below its parent"))
+        } else if right != level && right + 1 != level {
+            Err(ValidationError::Balance("AA right child is not at or one level below its parent"))
+        } else if right_right == level {
+            Err(ValidationError::Balance("AA node has two horizontal links in a row"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn state<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Self::State {
+        node.inspect_balance(|b| b.level)
+    }
+
+    fn adjust_root(&mut self) {
+        // Do nothing
+    }
+}
+
+impl Debug for AABalance {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        write!(f, "{}", self.level)
+    }
+}
+
+/// Returns the levels of a node, its left child, its right child and its right grandchild
+///
+/// Missing nodes are at level zero
+fn levels<'a, T: Ord + 'a, A: Augment<T> + 'a, I: InspectNode<'a, T, AABalance, A>>(node: &'a I) -> (usize, usize, usize, usize) {
+    let left = node.inspect_child(TreePath::Left).map_or(0, |n| n.inspect_balance(|b| b.level));
+    let (right, right_right) = node.inspect_child(TreePath::Right).map_or((0, 0), |n| (
+        n.inspect_balance(|b| b.level),
+        n.inspect_child(TreePath::Right).map_or(0, |n| n.inspect_balance(|b| b.level))
+    ));
+    (node.inspect_balance(|b| b.level), left, right, right_right)
+}
+
+/// Checks if a node with the given levels (see [levels]) needs to be skewed or split
+fn unfit((level, left, _, right_right): (usize, usize, usize, usize)) -> bool {
+    left == level || right_right == level
+}
+
+/// Returns the levels (see [levels]) of the node found by following the paths, if there is one
+fn levels_at<T: Ord, A: Augment<T>>(node: &NodeInspector<T, AABalance, A>, paths: &[TreePath]) -> Option<(usize, usize, usize, usize)> {
+    match *paths {
+        [] => Some(levels(node)),
+        [path] => node.inspect_child(path).map(|n| levels(&n)),
+        [path, next] => node.inspect_child(path).and_then(|n| n.inspect_child(next).map(|n| levels(&n))),
+        _ => unreachable!()
+    }
+}
+
+/// Rotates a horizontal left link of the node found by following the paths to the right
+fn skew<T: Ord, A: Augment<T>>(node: NodeInspector<T, AABalance, A>, paths: &[TreePath]) -> NodeInspector<T, AABalance, A> {
+    match levels_at(&node, paths) {
+        Some((level, left, _, _)) if left == level => node.rotate_at(paths, TreePath::Left),
+        _ => node
+    }
+}
+
+/// Rotates two horizontal right links of the node found by following the paths to the left,
+/// raising the node brought up by a level
+fn split<T: Ord, A: Augment<T>>(node: NodeInspector<T, AABalance, A>, paths: &[TreePath]) -> NodeInspector<T, AABalance, A> {
+    match levels_at(&node, paths) {
+        Some((level, _, _, right_right)) if right_right == level => {
+            let mut node = node.rotate_at(paths, TreePath::Right);
+            match *paths {
+                [] => node.update_balance(|b| b.level += 1),
+                [path] => node.inspect_child(path).unwrap().update_balance(|b| b.level += 1),
+                _ => unreachable!()
+            }
+            node
+        },
+        _ => node
+    }
+}
+
+/// Skews and then splits the node found by following the paths
+fn skew_split<T: Ord, A: Augment<T>>(node: NodeInspector<T, AABalance, A>, paths: &[TreePath]) -> NodeInspector<T, AABalance, A> {
+    split(skew(node, paths), paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AABalance;
+    use crate::Tree;
+
+    #[test]
+    fn insert_unbalanced() {
+        let mut tree: Tree<u32, AABalance> = Tree::new();
+
+        tree.insert(40);
+        tree.insert(50);
+        tree.insert(60);
+        assert_eq!(tree.height(), 2)
+    }
+
+    #[test]
+    fn insert_skewed() {
+        let mut tree: Tree<u32, AABalance> = Tree::new();
+
+        tree.insert(60);
+        tree.insert(50);
+        tree.insert(40);
+        assert_eq!(tree.height(), 2)
+    }
+
+    #[test]
+    fn insert_ascending() {
+        let mut tree: Tree<u32, AABalance> = Tree::new();
+
+        tree.insert(40);
+        tree.insert(50);
+        tree.insert(60);
+        tree.insert(70);
+        tree.insert(80);
+        tree.insert(90);
+        assert_eq!(tree.height(), 4)
+    }
+
+    #[test]
+    fn insert_split_rebalance() {
+        let mut tree: Tree<u32, AABalance> = Tree::new();
+
+        tree.insert(40);
+        tree.insert(50);
+        tree.insert(60);
+        tree.insert(70);
+        tree.insert(90);
+        tree.insert(80);
+        tree.insert(100);
+        tree.insert(110);
+        assert_eq!(tree.height(), 4)
+    }
+
+    #[test]
+    fn delete_leaf() {
+        let mut tree: Tree<u32, AABalance> = Tree::new();
+
+        tree.insert(40);
+        tree.insert(50);
+        tree.insert(60);
+        tree.insert(70);
+        tree.delete(&40);
+        assert_eq!(tree.height(), 2)
+    }
+
+    #[test]
+    fn delete_lower() {
+        let mut tree: Tree<u32, AABalance> = Tree::new();
+
+        tree.insert(40);
+        tree.insert(50);
+        tree.insert(60);
+        tree.insert(70);
+        tree.insert(90);
+        tree.insert(80);
+        tree.delete(&90);
+        tree.delete(&80);
+        assert_eq!(tree.height(), 3);
+        tree.delete(&40);
+        assert_eq!(tree.height(), 2)
+    }
+
+    // Levels are printed as the balance of each node
+    const DESCENDING: &str = "8 { balance: 4, height: 5, leaves: 8, right: 12 { balance: 3, height: 3, leaves: 4, right: 14 { balance: 2, height: 2, leaves: 2, right: 15 { balance: 1, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 13 { balance: 1, height: 1, leaves: 1, right: \"None\", left: \"None\" } }, left: 10 { balance: 2, height: 2, leaves: 2, right: 11 { balance: 1, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 9 { balance: 1, height: 1, leaves: 1, right: \"None\", left: \"None\" } } }, left: 4 { balance: 3, height: 4, leaves: 4, right: 6 { balance: 2, height: 2, leaves: 2, right: 7 { balance: 1, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 5 { balance: 1, height: 1, leaves: 1, right: \"None\", left: \"None\" } }, left: 2 { balance: 2, height: 3, leaves: 2, right: 3 { balance: 1, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 0 { balance: 1, height: 2, leaves: 1, right: 1 { balance: 1, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: \"None\" } } } }";
+
+    #[test]
+    fn insert_descending() {
+        let mut tree: Tree<u32, AABalance> = Tree::new();
+
+        for key in (0..16).rev() {
+            tree.insert(key);
+        }
+        assert_eq!(format!("{:?}", tree), DESCENDING)
+    }
+
+    #[test]
+    fn validate() {
+        let mut tree: Tree<u32, AABalance> = Tree::new();
+
+        let mut seed = 7u32;
+        for _ in 0..2000 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let key = (seed >> 8) % 500;
+            if seed & 3 == 0 {
+                tree.delete(&key);
+            } else {
+                tree.insert(key);
+            }
+            assert_eq!(tree.validate(), Ok(()));
+        }
+        // The height of an AA tree is at most 2 log2(n + 1)
+        assert!(tree.height() <= 2 * (usize::BITS - (tree.len() + 1).leading_zeros()) as usize);
+    }
+
+    #[test]
+    fn validate_reinserted() {
+        // Compacting and converting reinsert the keys without accessing them
+        let mut tree: Tree<u32, AABalance> = Tree::with_lazy_delete(1.0);
+        for key in 0..100 {
+            tree.insert(key);
+        }
+        for key in (0..100).step_by(3) {
+            tree.delete(&key);
+        }
+        tree.compact();
+        assert_eq!(tree.validate(), Ok(()));
+
+        let mut avl: crate::AVLTree<u32> = crate::AVLTree::new();
+        for key in 0..100 {
+            avl.insert(key);
+        }
+        let tree: Tree<u32, AABalance> = avl.into();
+        assert_eq!(tree.validate(), Ok(()));
+    }
+}
@@ -0,0 +1,337 @@
+use core::fmt::{Debug, Display};
+
+use crate::{AVLTree, RedBlackTree};
+use crate::tree::Tree;
+use crate::tree::inspect::ValidationError;
+
+/// Balancing modes that an [AdaptiveTree] switches between
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Mode {
+    /// Balanced as an AVL tree, which keeps the tree shorter for faster lookups
+    AVL,
+    /// Balanced as a red black tree, which rotates less on insertions and deletions
+    RedBlack
+}
+
+/// Binary search tree that switches between AVL and red black balancing to suit its workload
+///
+/// The tree counts its reads ([AdaptiveTree::search]) and writes ([AdaptiveTree::insert] and
+/// [AdaptiveTree::delete]) over a window of recent operations.  At the end of each window, if the
+/// fraction of reads is at least the threshold the tree is balanced as an AVL tree, otherwise as a
+/// red black tree.  Switching converts the tree in O(n) (see [Tree::rebalance_as]), so the window
+/// is at least as long as the number of keys in the tree to keep the cost per operation constant.
+///
+/// ```
+/// use project2::adaptive::{AdaptiveTree, Mode};
+/// let mut tree = AdaptiveTree::new();
+/// for key in 0..1000 {
+///     tree.insert(key);
+/// }
+/// assert_eq!(tree.mode(), Mode::RedBlack);
+///
+/// // A read heavy workload switches the tree to AVL balancing
+/// for key in 0..2000 {
+///     tree.search(&(key % 1000));
+/// }
+/// assert_eq!(tree.mode(), Mode::AVL);
+/// assert_eq!(tree.conversions(), 1);
+/// assert_eq!(tree.len(), 1000);
+/// ```
+pub struct AdaptiveTree<T: Ord> {
+    tree: Balanced<T>,
+    /// Fraction of reads at or above which the tree is balanced as an AVL tree
+    threshold: f64,
+    /// Number of reads in the current window
+    reads: usize,
+    /// Number of writes in the current window
+    writes: usize,
+    /// Number of times the tree has switched modes
+    conversions: usize
+}
+
+/// Tree held by an [AdaptiveTree] in its current mode
+#[allow(clippy::upper_case_acronyms)]
+enum Balanced<T: Ord> {
+    AVL(AVLTree<T>),
+    RedBlack(RedBlackTree<T>)
+}
+
+/// Evaluates the expression with the tree held in either mode
+macro_rules! each {
+    ($balanced:expr, $tree:ident => $apply:expr) => {
+        match $balanced {
+            Balanced::AVL($tree) => $apply,
+            Balanced::RedBlack($tree) => $apply
+        }
+    };
+}
+
+impl <T: Ord> AdaptiveTree<T> {
+    /// Fewest operations counted before the tree decides whether to switch modes
+    const MIN_WINDOW: usize = 256;
+
+    /// Creates a new empty tree that switches to AVL balancing when at least half of its operations are reads
+    ///
+    /// The tree starts out balanced as a red black tree.
+    pub fn new() -> Self {
+        AdaptiveTree::with_threshold(0.5)
+    }
+
+    /// Creates a new empty tree that switches to AVL balancing when at least `threshold` of its operations are reads
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `threshold` is not in the range (0, 1]
+    ///
+    /// ```
+    /// use project2::adaptive::{AdaptiveTree, Mode};
+    /// let mut tree = AdaptiveTree::with_threshold(0.9);
+    /// for key in 0..300 {
+    ///     tree.insert(key);
+    ///     tree.search(&key);
+    ///     tree.search(&key);
+    /// }
+    /// // Two thirds of the operations are reads, which is not enough to switch
+    /// assert_eq!(tree.mode(), Mode::RedBlack);
+    /// ```
+    pub fn with_threshold(threshold: f64) -> Self {
+        let mut tree = AdaptiveTree {
+            tree: Balanced::RedBlack(Tree::new()),
+            threshold: 0.5,
+            reads: 0,
+            writes: 0,
+            conversions: 0
+        };
+        tree.set_threshold(threshold);
+        tree
+    }
+
+    /// Changes the fraction of reads at or above which the tree is balanced as an AVL tree
+    ///
+    /// The new threshold is used from the end of the current window.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `threshold` is not in the range (0, 1]
+    pub fn set_threshold(&mut self, threshold: f64) {
+        assert!(threshold > 0.0 && threshold <= 1.0, "threshold must be in the range (0, 1]");
+        self.threshold = threshold;
+    }
+
+    /// Returns the mode the tree is balanced in
+    pub fn mode(&self) -> Mode {
+        match self.tree {
+            Balanced::AVL(_) => Mode::AVL,
+            Balanced::RedBlack(_) => Mode::RedBlack
+        }
+    }
+
+    /// Returns the number of times the tree has switched modes
+    pub fn conversions(&self) -> usize {
+        self.conversions
+    }
+
+    /// Returns true if the tree has no contents (see [Tree::is_empty])
+    pub fn is_empty(&self) -> bool {
+        each!(&self.tree, tree => tree.is_empty())
+    }
+
+    /// Returns the number of keys in the tree (see [Tree::len])
+    pub fn len(&self) -> usize {
+        each!(&self.tree, tree => tree.len())
+    }
+
+    /// Inserts an element into the tree (see [Tree::insert])
+    ///
+    /// Counts as a write, which may switch the mode of the tree
+    pub fn insert(&mut self, key: T) {
+        each!(&mut self.tree, tree => tree.insert(key));
+        self.record(false);
+    }
+
+    /// Finds if an element exists in the tree (see [Tree::search])
+    ///
+    /// Counts as a read, which may switch the mode of the tree, so the tree is borrowed mutably
+    pub fn search(&mut self, key: &T) -> bool {
+        let found = each!(&self.tree, tree => tree.search(key));
+        self.record(true);
+        found
+    }
+
+    /// Deletes an element from the tree (see [Tree::delete])
+    ///
+    /// Counts as a write, which may switch the mode of the tree
+    pub fn delete(&mut self, key: &T) -> Option<T> {
+        let key = each!(&mut self.tree, tree => tree.delete(key));
+        self.record(false);
+        key
+    }
+
+    /// Returns the height of the tree (see [Tree::height])
+    pub fn height(&self) -> usize {
+        each!(&self.tree, tree => tree.height())
+    }
+
+    /// Returns the number of leaves in the tree (see [Tree::leaves])
+    pub fn leaves(&self) -> usize {
+        each!(&self.tree, tree => tree.leaves())
+    }
+
+    /// Checks that the tree is valid (see [Tree::validate])
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        each!(&self.tree, tree => tree.validate())
+    }
+
+    /// Clears the contents of the tree (see [Tree::clear])
+    ///
+    /// The mode of the tree and the operations counted towards the current window are kept
+    pub fn clear(&mut self) {
+        each!(&mut self.tree, tree => tree.clear())
+    }
+
+    /// Counts an operation, switching modes at the end of a window if the workload calls for it
+    fn record(&mut self, read: bool) {
+        if read {
+            self.reads += 1;
+        } else {
+            self.writes += 1;
+        }
+
+        let total = self.reads + self.writes;
+        if total < Self::MIN_WINDOW.max(self.len()) {
+            return;
+        }
+        let mode = if self.reads as f64 >= self.threshold * total as f64 {
+            Mode::AVL
+        } else {
+            Mode::RedBlack
+        };
+        self.reads = 0;
+        self.writes = 0;
+        if mode != self.mode() {
+            self.switch();
+        }
+    }
+
+    /// Converts the tree to the other mode
+    fn switch(&mut self) {
+        let placeholder = Balanced::RedBlack(Tree::new());
+        self.tree = match core::mem::replace(&mut self.tree, placeholder) {
+            Balanced::AVL(tree) => Balanced::RedBlack(tree.rebalance_as()),
+            Balanced::RedBlack(tree) => Balanced::AVL(tree.rebalance_as())
+        };
+        self.conversions += 1;
+    }
+
+}
+
+impl <T: Ord> Default for AdaptiveTree<T> {
+    fn default() -> Self {
+        AdaptiveTree::new()
+    }
+}
+
+impl <T: Ord + Display> Display for AdaptiveTree<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        each!(&self.tree, tree => Display::fmt(tree, f))
+    }
+}
+
+impl <T: Ord + Debug> Debug for AdaptiveTree<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        each!(&self.tree, tree => Debug::fmt(tree, f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AdaptiveTree, Mode};
+    use crate::RedBlackTree;
+
+    /// Describes the shape of a printed tree, leaving out the state of the balance
+    fn shape(tree: &impl core::fmt::Debug) -> String {
+        format!("{:?}", tree).split("balance: ").enumerate()
+            .map(|(i, part)| if i == 0 { part } else { &part[part.find(", ").unwrap() + 2..] })
+            .collect()
+    }
+
+    #[test]
+    fn matches_typed_trees() {
+        let mut tree = AdaptiveTree::new();
+        let mut redblack = RedBlackTree::new();
+        for key in [40, 50, 60, 70, 90, 80, 100, 110, 30, 20] {
+            tree.insert(key);
+            redblack.insert(key);
+        }
+        assert_eq!(format!("{:?}", tree), format!("{:?}", redblack));
+
+        // The red black shape is also a valid AVL shape, so switching keeps it
+        for key in 0..1000 {
+            tree.search(&key);
+        }
+        assert_eq!(tree.mode(), Mode::AVL);
+        assert_eq!(shape(&tree), shape(&redblack));
+        assert_eq!(tree.validate(), Ok(()));
+
+        // Every AVL shape can be colored red black, so switching back keeps it too
+        for _ in 0..1000 {
+            tree.insert(20);
+        }
+        assert_eq!(tree.mode(), Mode::RedBlack);
+        assert_eq!(shape(&tree), shape(&redblack));
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.conversions(), 2);
+    }
+
+    #[test]
+    fn follows_workload() {
+        let mut tree = AdaptiveTree::with_threshold(0.75);
+
+        // Write heavy
+        for key in 0..2000 {
+            tree.insert(key);
+            tree.search(&key);
+        }
+        assert_eq!(tree.mode(), Mode::RedBlack);
+        assert_eq!(tree.conversions(), 0);
+
+        // Read heavy
+        for key in 0..10000 {
+            tree.search(&(key % 2000));
+        }
+        assert_eq!(tree.mode(), Mode::AVL);
+        assert_eq!(tree.conversions(), 1);
+
+        // Churn
+        for key in 0..2000 {
+            tree.delete(&key);
+            tree.insert(key + 2000);
+        }
+        assert_eq!(tree.mode(), Mode::RedBlack);
+        assert_eq!(tree.conversions(), 2);
+        assert_eq!(tree.len(), 2000);
+        assert!((2000..4000).all(|key| tree.search(&key)));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn window_grows_with_tree() {
+        let mut tree = AdaptiveTree::new();
+        for key in 0..1000 {
+            tree.insert(key);
+        }
+
+        // A burst of reads shorter than the tree does not switch it
+        for key in 0..900 {
+            tree.search(&key);
+        }
+        assert_eq!(tree.mode(), Mode::RedBlack);
+    }
+
+    #[test]
+    #[should_panic(expected = "threshold must be in the range (0, 1]")]
+    fn invalid_threshold() {
+        AdaptiveTree::<u32>::with_threshold(0.0);
+    }
+}
@@ -1,82 +1,159 @@
-use std::fmt::Debug;
+use core::fmt::Debug;
 
-use crate::tree::TreePath;
+use crate::tree::{Tree, TreePath};
+use crate::tree::cache::Height;
 use crate::tree::inspect::*;
 
 /// Implementation of a balance for a binary tree
 /// that effectively converts a [Tree] into into an AVLTree
-pub struct AVLBalance();
-impl TreeBalance for AVLBalance {
+pub type AVLBalance = RelaxedAVLBalance<1>;
 
-    fn rebalance_insert<T: Ord>(node: NodeInspector<T, Self>, path: (TreePath, TreePath)) -> TreePosition<T, Self> {
+/// Implementation of a balance for a binary tree that effectively converts a [Tree]
+/// into an AVL Tree whose subtree heights may differ by up to `K`
+///
+/// Larger tolerances let the tree grow taller than an [AVLBalance] tree in exchange
+/// for fewer rotations, which suits write heavy workloads.
+/// For 70000 keys inserted in a scrambled order then half deleted (see the benchmarks):
+///
+/// | `K` | Height | Rotations |
+/// |-----|--------|-----------|
+/// | 1   | 18     | 62429     |
+/// | 2   | 19     | 27572     |
+/// | 3   | 21     | 15411     |
+pub struct RelaxedAVLBalance<const K: usize>();
+
+impl <const K: usize> RelaxedAVLBalance<K> {
+    /// Fails to compile if K is zero
+    const VALID_K: () = assert!(K > 0, "K must be at least one");
+
+    /// Returns the path to the taller child of the given child of a node
+    fn taller_path<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>, ppath: TreePath) -> TreePath {
+        let pnode = node.inspect_child(ppath).unwrap();
+        let inline_height = pnode.inspect_child(ppath).map_or(0, |x| x.inspect_height());
+        let elbow_height = pnode.inspect_child(ppath.reflect()).map_or(0, |x| x.inspect_height());
+
+        // Optimize selection to prevent unnecessary rotation
+        if inline_height >= elbow_height {
+            ppath
+        } else {
+            ppath.reflect()
+        }
+    }
+}
+
+impl <const K: usize> TreeBalance for RelaxedAVLBalance<K> {
+    type State = isize;
+    type Cache = Height;
+
+    fn rebalance_insert<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, path: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
         // Check if we need to rebalance
         let rebalance = {
             let pheight = node.inspect_child(path.0).unwrap().inspect_height();
             let uheight = node.inspect_child(path.0.reflect()).map_or(0, |b| b.inspect_height());
 
-            pheight - uheight > 1
+            pheight > uheight + K
         };
 
         if rebalance {
-            node.rotate(path).into_position(NodeOffset::Root)
+            // With a tolerance above one the taller grandchild may be off the insertion path
+            let xpath = Self::taller_path(&node, path.0);
+            node.rotate((path.0, xpath)).into_position(NodeOffset::Root)
         } else {
             node.into_position(NodeOffset::Parent)
         }
 
     }
 
-    fn new() -> Self {
-        AVLBalance()
+    fn new<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::VALID_K;
+        RelaxedAVLBalance()
+    }
+
+    fn new_root<T: Ord>(context: &mut BalanceContext<T>) -> Self {
+        Self::new(context)
     }
 
-    fn new_root() -> Self {
-        AVLBalance()
+    fn new_rebuilt(_: usize, _: Option<usize>) -> Option<Self> {
+        Some(RelaxedAVLBalance())
     }
 
-    fn rebalance_delete<T: Ord>(node: NodeInspector<T, Self>, upath: TreePath, _: &Self) -> TreePosition<T, Self> {
+    fn rebalance_delete<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, upath: TreePath, _: &Self) -> TreePosition<T, Self, A> {
         // Check if we need to rebalance
         let ppath = upath.reflect();
         let rebalance = {
             let pheight = node.inspect_child(ppath).map_or(0, |b| b.inspect_height());
             let uheight = node.inspect_child(upath).map_or(0, |b| b.inspect_height());
 
-            pheight - uheight > 1
+            pheight > uheight + K
         };
 
         if rebalance {
-            // Get child path that we need for the rebalance
-            let xpath = {
-                let pnode = node.inspect_child(ppath).unwrap();
-                let inline_height = pnode.inspect_child(ppath).map_or(0, |x| x.inspect_height());
-                let elbow_height = pnode.inspect_child(ppath.reflect()).map_or(0, |x| x.inspect_height());
-
-                // Optimize selection to prevent unnecessary rotation
-                if inline_height >= elbow_height {
-                    ppath
-                } else {
-                    ppath.reflect()
-                }
-            };
+            let xpath = Self::taller_path(&node, ppath);
             node.rotate((ppath, xpath)).into_position(NodeOffset::Parent)
         } else {
             node.into_position(NodeOffset::Parent)
         }
     }
 
+    fn verify<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Result<(), ValidationError> {
+        let height = |path| node.inspect_child(path).map_or(0, |n| n.inspect_height());
+        if height(TreePath::Left).abs_diff(height(TreePath::Right)) > K {
+            Err(ValidationError::Balance(if K == 1 {
+                "AVL subtree heights differ by more than one"
+            } else {
+                "Relaxed AVL subtree heights differ by more than the tolerance"
+            }))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn state<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Self::State {
+        let height = |path| node.inspect_child(path).map_or(0, |n| n.inspect_height()) as isize;
+        height(TreePath::Right) - height(TreePath::Left)
+    }
+
     fn adjust_root(&mut self) {
         // Do nothing
     }
 }
 
-impl Debug for AVLBalance {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        write!(f, "AVL")
+impl <const K: usize> Debug for RelaxedAVLBalance<K> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        if K == 1 {
+            write!(f, "AVL")
+        } else {
+            write!(f, "AVL({})", K)
+        }
+    }
+}
+
+impl <T: Ord, A: Augment<T>, const K: usize> Tree<T, RelaxedAVLBalance<K>, A> {
+    /// Returns the balance factor of the node holding the given key
+    /// 
+    /// The balance factor is the height of the node's right subtree minus the height of its left subtree,
+    /// which is never more than `K` away from zero.  Returns None if the key is not in the tree.
+    /// 
+    /// ```
+    /// use project2::AVLTree;
+    /// let mut tree = AVLTree::new();
+    /// for key in [2, 1, 3, 4] {
+    ///     tree.insert(key);
+    /// }
+    /// assert_eq!(tree.balance_factor(&2), Some(1));
+    /// assert_eq!(tree.balance_factor(&3), Some(1));
+    /// assert_eq!(tree.balance_factor(&4), Some(0));
+    /// assert_eq!(tree.balance_factor(&5), None);
+    /// ```
+    pub fn balance_factor(&self, key: &T) -> Option<isize> {
+        self.node_info(key).map(|info| info.state)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::AVLBalance;
+    use super::{AVLBalance, RelaxedAVLBalance};
     use crate::Tree;
 
     #[test]
@@ -89,6 +166,20 @@ mod tests {
         assert_eq!(tree.height(), 2)
     }
 
+    #[test]
+    fn leaves() {
+        let mut tree: Tree<u32, AVLBalance> = Tree::new();
+
+        tree.insert(40);
+        tree.insert(50);
+        tree.insert(60);
+        tree.insert(70);
+        tree.insert(30);
+        // Leaves are computed on demand since AVL only caches heights
+        assert_eq!(tree.leaves(), 2);
+        assert_eq!(tree.height(), 3)
+    }
+
     #[test]
     fn delete() {
         let mut tree: Tree<u32, AVLBalance> = Tree::new();
@@ -100,4 +191,84 @@ mod tests {
         tree.delete(&40);
         assert_eq!(tree.height(), 2)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn validate() {
+        let mut tree: Tree<u32, AVLBalance> = Tree::new();
+
+        let mut seed = 5u32;
+        for _ in 0..500 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let key = (seed >> 8) % 200;
+            if seed & 3 == 0 {
+                tree.delete(&key);
+            } else {
+                tree.insert(key);
+            }
+            assert_eq!(tree.validate(), Ok(()));
+        }
+    }
+
+    /// Inserts scrambled keys into a tree and deletes every other one, validating along the way
+    fn insert_delete<const K: usize>() -> Tree<u32, RelaxedAVLBalance<K>> {
+        let mut tree = Tree::new();
+
+        let keys: Vec<u32> = (0..2000u32).map(|key| key.wrapping_mul(7919) % 2000).collect();
+        for key in &keys {
+            tree.insert(*key);
+            assert_eq!(tree.validate(), Ok(()));
+        }
+        for key in keys.iter().step_by(2) {
+            tree.delete(key);
+            assert_eq!(tree.validate(), Ok(()));
+        }
+        tree
+    }
+
+    #[test]
+    fn relaxed() {
+        let strict = insert_delete::<1>();
+        let relaxed = insert_delete::<2>();
+        let loose = insert_delete::<3>();
+
+        assert!(strict.rotations() > relaxed.rotations() && relaxed.rotations() > loose.rotations());
+        assert!(strict.height() <= relaxed.height() && relaxed.height() <= loose.height());
+    }
+
+    #[test]
+    fn relaxed_ascending() {
+        let mut tree: Tree<u32, RelaxedAVLBalance<2>> = Tree::new();
+
+        tree.insert(40);
+        tree.insert(50);
+        tree.insert(60);
+        assert_eq!(tree.height(), 3);
+        tree.insert(70);
+        assert_eq!(tree.height(), 3);
+        assert_eq!(tree.rotations(), 1)
+    }
+
+    #[test]
+    fn balance_factor() {
+        let mut tight: Tree<u32, AVLBalance> = Tree::new();
+        let mut loose: Tree<u32, RelaxedAVLBalance<3>> = Tree::new();
+
+        let mut seed = 5u32;
+        for _ in 0..2000 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let key = (seed >> 8) % 500;
+            if seed & 3 == 0 {
+                tight.delete(&key);
+                loose.delete(&key);
+            } else {
+                tight.insert(key);
+                loose.insert(key);
+            }
+        }
+        let tight: Vec<isize> = (0..500).filter_map(|key| tight.balance_factor(&key)).collect();
+        let loose: Vec<isize> = (0..500).filter_map(|key| loose.balance_factor(&key)).collect();
+        assert!(tight.iter().all(|factor| factor.abs() <= 1));
+        assert!(loose.iter().all(|factor| factor.abs() <= 3));
+        assert!(loose.iter().any(|factor| factor.abs() > 1));
+    }
+}
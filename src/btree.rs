@@ -0,0 +1,214 @@
+use crate::multiway::inspect::*;
+
+/// Implementation of a balance for a multiway tree
+/// that effectively converts a [MultiTree](crate::multiway::MultiTree) into a B-Tree
+///
+/// Each node has at most `ORDER` children (so holds up to `ORDER - 1` keys) and every node other than the root
+/// has at least half of that.  A node that overflows after an insertion is split around its middle key on the way
+/// back up, while a node that underflows after a deletion takes a key from a sibling that can spare one
+/// or is merged with a sibling.  `ORDER` must be at least 3.
+pub struct BTreeBalance<const ORDER: usize>();
+
+impl <const ORDER: usize> BTreeBalance<ORDER> {
+    /// Fails to compile if ORDER is out of range
+    const VALID_ORDER: () = assert!(ORDER >= 3, "ORDER must be at least 3");
+}
+
+impl <const ORDER: usize> MultiBalance for BTreeBalance<ORDER> {
+    const MAX_KEYS: usize = {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::VALID_ORDER;
+        ORDER - 1
+    };
+    const MIN_KEYS: usize = ORDER.div_ceil(2) - 1;
+
+    fn rebalance_insert<T: Ord>(inspector: &mut MultiInspector<T>, index: usize) {
+        if inspector.child_len(index).is_some_and(|len| len > Self::MAX_KEYS) {
+            inspector.split(index);
+        }
+    }
+
+    fn rebalance_delete<T: Ord>(inspector: &mut MultiInspector<T>, index: usize) {
+        refill::<Self, T>(inspector, index)
+    }
+}
+
+/// Implementation of a balance for a multiway tree
+/// that effectively converts a [MultiTree](crate::multiway::MultiTree) into a 2-3-4 Tree
+///
+/// Each node holds one to three keys.  Unlike [BTreeBalance] insertions are balanced from the top down:
+/// any full node on the way down is split before descending into it, so the leaf the key is added to always
+/// has room and nothing needs to be done on the way back up.  Deletions are balanced from the bottom up
+/// the same as a B-Tree of order 4.
+pub struct Tree234Balance();
+
+impl MultiBalance for Tree234Balance {
+    const MAX_KEYS: usize = 3;
+    const MIN_KEYS: usize = 1;
+
+    fn before_descend<T: Ord>(inspector: &mut MultiInspector<T>, index: usize) {
+        if inspector.child_len(index) == Some(Self::MAX_KEYS) {
+            inspector.split(index);
+        }
+    }
+
+    fn rebalance_insert<T: Ord>(_: &mut MultiInspector<T>, _: usize) {
+        // Do nothing, full nodes were split on the way down
+    }
+
+    fn rebalance_delete<T: Ord>(inspector: &mut MultiInspector<T>, index: usize) {
+        refill::<Self, T>(inspector, index)
+    }
+}
+
+/// Refills the child at the given index if it holds too few keys
+///
+/// Shifts a key over from a sibling that can spare one (preferring the left sibling),
+/// otherwise merges the child with a sibling
+fn refill<U: MultiBalance, T: Ord>(inspector: &mut MultiInspector<T>, index: usize) {
+    if inspector.child_len(index).is_none_or(|len| len >= U::MIN_KEYS) {
+        return;
+    }
+    let spare = |len: Option<usize>| len.is_some_and(|len| len > U::MIN_KEYS);
+    if index > 0 && spare(inspector.child_len(index - 1)) {
+        inspector.shift_right(index - 1);
+    } else if spare(inspector.child_len(index + 1)) {
+        inspector.shift_left(index);
+    } else if index > 0 {
+        inspector.merge(index - 1);
+    } else if inspector.child_len(index + 1).is_some() {
+        inspector.merge(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BTreeBalance, Tree234Balance};
+    use crate::multiway::MultiTree;
+    use crate::multiway::inspect::MultiBalance;
+    use crate::AVLTree;
+
+    /// Inserts and deletes pseudo random keys in the tree and a binary tree, checking they agree
+    fn check_random<U: MultiBalance>(seed: u32) -> MultiTree<u32, U> {
+        let mut tree: MultiTree<u32, U> = MultiTree::new();
+        let mut expected: AVLTree<u32> = AVLTree::new();
+
+        let mut seed = seed;
+        for _ in 0..3000 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let key = (seed >> 8) % 500;
+            if seed & 3 == 0 {
+                assert_eq!(tree.delete(&key), expected.delete(&key));
+            } else {
+                tree.insert(key);
+                expected.insert(key);
+            }
+            assert_eq!(tree.validate(), Ok(()));
+            assert_eq!(tree.search(&key), expected.search(&key));
+        }
+        assert_eq!(tree.len(), expected.len());
+        assert_eq!(format!("{}", tree), format!("{}", expected));
+        tree
+    }
+
+    #[test]
+    fn insert_split() {
+        let mut tree: MultiTree<u32, BTreeBalance<3>> = MultiTree::new();
+
+        tree.insert(40);
+        tree.insert(50);
+        assert_eq!(tree.height(), 1);
+        tree.insert(60);
+        assert_eq!(tree.height(), 2);
+        assert_eq!(format!("{:?}", tree), "[50] { children: [[40], [60]] }")
+    }
+
+    #[test]
+    fn insert_ascending() {
+        let mut tree: MultiTree<u32, BTreeBalance<16>> = MultiTree::new();
+
+        for key in 0..1000 {
+            tree.insert(key);
+        }
+        assert_eq!(tree.validate(), Ok(()));
+        // Each node other than the root has at least 8 children
+        assert!(tree.height() <= 4);
+        assert!((0..1000).all(|key| tree.search(&key)));
+    }
+
+    #[test]
+    fn delete_shift() {
+        let mut tree: MultiTree<u32, BTreeBalance<3>> = MultiTree::new();
+
+        for key in [40, 50, 60, 30] {
+            tree.insert(key);
+        }
+        // The right leaf borrows from the left leaf through the root
+        tree.delete(&60);
+        assert_eq!(format!("{:?}", tree), "[40] { children: [[30], [50]] }");
+        assert_eq!(tree.merges(), 0)
+    }
+
+    #[test]
+    fn delete_merge() {
+        let mut tree: MultiTree<u32, BTreeBalance<3>> = MultiTree::new();
+
+        for key in [40, 50, 60] {
+            tree.insert(key);
+        }
+        tree.delete(&50);
+        assert_eq!(format!("{:?}", tree), "[40, 60]");
+        assert_eq!(tree.merges(), 1)
+    }
+
+    #[test]
+    fn delete_all() {
+        let mut tree: MultiTree<u32, BTreeBalance<5>> = MultiTree::new();
+
+        for key in 0..200 {
+            tree.insert(key);
+        }
+        for key in (0..200).rev() {
+            assert_eq!(tree.delete(&key), Some(key));
+            assert_eq!(tree.validate(), Ok(()));
+        }
+        assert!(tree.is_empty());
+        assert_eq!(tree.height(), 0)
+    }
+
+    #[test]
+    fn random() {
+        check_random::<BTreeBalance<3>>(3);
+        check_random::<BTreeBalance<4>>(5);
+        check_random::<BTreeBalance<7>>(7);
+        check_random::<Tree234Balance>(9);
+    }
+
+    #[test]
+    fn top_down_insert() {
+        let mut tree: MultiTree<u32, Tree234Balance> = MultiTree::new();
+
+        for key in [10, 20, 30] {
+            tree.insert(key);
+        }
+        assert_eq!(tree.height(), 1);
+        // The full root is split on the way down
+        tree.insert(40);
+        assert_eq!(format!("{:?}", tree), "[20] { children: [[10], [30, 40]] }");
+    }
+
+    #[test]
+    fn top_down_splits_early() {
+        let mut top_down: MultiTree<u32, Tree234Balance> = MultiTree::new();
+        let mut bottom_up: MultiTree<u32, BTreeBalance<4>> = MultiTree::new();
+
+        for key in 0..1000 {
+            top_down.insert(key);
+            bottom_up.insert(key);
+        }
+        assert_eq!(top_down.validate(), Ok(()));
+        assert_eq!(bottom_up.validate(), Ok(()));
+        // Full nodes are split even when the leaf below has room
+        assert!(top_down.splits() >= bottom_up.splits());
+    }
+}
@@ -0,0 +1,715 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt::{Debug, Display};
+use core::ops::{Bound, RangeBounds};
+
+use crate::aa::AABalance;
+use crate::avl::{AVLBalance, RelaxedAVLBalance};
+use crate::redblack::{LeftLeaningRedBlackBalance, RedBlackBalance, TopDownRedBlackBalance};
+use crate::scapegoat::ScapegoatBalance;
+use crate::splay::SplayBalance;
+use crate::treap::TreapBalance;
+use crate::tree::{Finger, NodeInfo, Op, OpOutcome, Tree};
+use crate::tree::augment::Augment;
+use crate::tree::inspect::{InspectError, TreeBalance, ValidationError};
+use crate::unbalanced::UnbalancedBalance;
+use crate::wavl::WAVLBalance;
+use crate::weight::WeightBalance;
+
+/// Balancing strategies that a [DynTree] can switch between at runtime
+///
+/// Balances with a parameter use their default (see [DynTree::custom] for other parameters).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Strategy {
+    AVL,
+    /// [RelaxedAVLBalance] whose subtree heights may differ by up to 2
+    RelaxedAVL,
+    RedBlack,
+    TopDownRedBlack,
+    LeftLeaningRedBlack,
+    AA,
+    WAVL,
+    Unbalanced,
+    Splay,
+    /// [ScapegoatBalance] with the default α
+    Scapegoat,
+    Treap,
+    /// [WeightBalance] with the default α
+    Weight
+}
+
+/// Evaluates the expression with the balance used by the given strategy named as a type
+macro_rules! with_balance {
+    ($strategy:expr, $balance:ident => $apply:expr) => {
+        match $strategy {
+            Strategy::AVL => { type $balance = AVLBalance; $apply },
+            Strategy::RelaxedAVL => { type $balance = RelaxedAVLBalance<2>; $apply },
+            Strategy::RedBlack => { type $balance = RedBlackBalance; $apply },
+            Strategy::TopDownRedBlack => { type $balance = TopDownRedBlackBalance; $apply },
+            Strategy::LeftLeaningRedBlack => { type $balance = LeftLeaningRedBlackBalance; $apply },
+            Strategy::AA => { type $balance = AABalance; $apply },
+            Strategy::WAVL => { type $balance = WAVLBalance; $apply },
+            Strategy::Unbalanced => { type $balance = UnbalancedBalance; $apply },
+            Strategy::Splay => { type $balance = SplayBalance; $apply },
+            Strategy::Scapegoat => { type $balance = ScapegoatBalance; $apply },
+            Strategy::Treap => { type $balance = TreapBalance; $apply },
+            Strategy::Weight => { type $balance = WeightBalance; $apply }
+        }
+    };
+}
+
+/// Binary search tree whose balancing strategy is chosen at runtime
+///
+/// Behaves like a [Tree] but the balance is selected with a [Strategy] (or any [TreeBalance]
+/// with [DynTree::custom]) when the tree is created and can be changed in place afterwards.
+/// Calls go through a trait object, so code using the tree is only compiled once
+/// no matter how many strategies it is used with.
+///
+/// The API matches [Tree], except that searches near a finger take a [DynFinger] and
+/// [DynTree::node_info] reports the state of the balance as text, since their types
+/// depend on the balance.
+///
+/// ```
+/// use project2::dyntree::{DynTree, Strategy};
+/// let mut tree = DynTree::<u32>::new(Strategy::AVL);
+/// for key in 0..100 {
+///     tree.insert(key);
+/// }
+/// assert_eq!(tree.height(), 7);
+///
+/// tree.set_strategy(Strategy::RedBlack);
+/// assert_eq!(tree.strategy(), Some(Strategy::RedBlack));
+/// assert_eq!(tree.len(), 100);
+/// assert!(tree.search(&50));
+/// ```
+pub struct DynTree<T: Ord, A: Augment<T> = ()> {
+    tree: Box<dyn DynBalanced<T, A>>,
+    strategy: Option<Strategy>
+}
+
+impl <T: Ord + 'static, A: Augment<T> + 'static> DynTree<T, A> {
+
+    /// Creates a new empty tree using the given strategy
+    pub fn new(strategy: Strategy) -> Self {
+        DynTree {
+            tree: with_balance!(strategy, B => Box::new(Tree::<T, B, A>::new())),
+            strategy: Some(strategy)
+        }
+    }
+
+    /// Creates a new empty tree using the given strategy and seed for random numbers
+    ///
+    /// See [Tree::with_seed]
+    pub fn with_seed(strategy: Strategy, seed: u64) -> Self {
+        DynTree {
+            tree: with_balance!(strategy, B => Box::new(Tree::<T, B, A>::with_seed(seed))),
+            strategy: Some(strategy)
+        }
+    }
+
+    /// Creates a new empty tree using the given strategy that rebuilds itself when it grows too tall
+    ///
+    /// See [Tree::with_auto_rebuild]
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `factor` is less than 1
+    pub fn with_auto_rebuild(strategy: Strategy, factor: f64) -> Self {
+        let mut tree = DynTree::new(strategy);
+        tree.set_auto_rebuild(Some(factor));
+        tree
+    }
+
+    /// Creates a new empty tree using a custom [TreeBalance]
+    ///
+    /// ```
+    /// use project2::dyntree::DynTree;
+    /// use project2::scapegoat::ScapegoatBalance;
+    /// let mut tree = DynTree::<u32>::custom::<ScapegoatBalance<60>>();
+    /// tree.insert(2);
+    /// assert!(tree.search(&2));
+    /// assert_eq!(tree.strategy(), None);
+    /// ```
+    pub fn custom<U: TreeBalance + Debug + 'static>() -> Self {
+        DynTree {
+            tree: Box::new(Tree::<T, U, A>::new()),
+            strategy: None
+        }
+    }
+
+    /// Creates a new empty tree using a custom [TreeBalance] and seed for random numbers
+    ///
+    /// See [Tree::with_seed]
+    pub fn custom_with_seed<U: TreeBalance + Debug + 'static>(seed: u64) -> Self {
+        DynTree {
+            tree: Box::new(Tree::<T, U, A>::with_seed(seed)),
+            strategy: None
+        }
+    }
+
+    /// Returns the strategy used by the tree, or [None] if it uses a custom balance
+    pub fn strategy(&self) -> Option<Strategy> {
+        self.strategy
+    }
+
+    /// Changes the strategy used by the tree, keeping its contents
+    ///
+    /// The tree is converted directly to the new balance in O(n) (see [Tree::rebalance_as]).
+    /// Fingers used with the tree start over from the root afterwards.
+    pub fn set_strategy(&mut self, strategy: Strategy) {
+        if self.strategy != Some(strategy) {
+            self.tree = self.take().convert(strategy);
+            self.strategy = Some(strategy);
+        }
+    }
+
+    /// Changes the tree to use a custom [TreeBalance], keeping its contents
+    ///
+    /// The tree is converted directly to the new balance in O(n) (see [Tree::rebalance_as]), unless it already
+    /// uses a custom balance, in which case it is converted to an AVL tree first since its balance is not known.
+    /// Fingers used with the tree start over from the root afterwards.
+    ///
+    /// ```
+    /// use project2::dyntree::{DynTree, Strategy};
+    /// use project2::splay::SplayBalance;
+    /// let mut tree = DynTree::<u32>::new(Strategy::Unbalanced);
+    /// for key in 0..100 {
+    ///     tree.insert(key);
+    /// }
+    ///
+    /// // Any shape is a valid splay tree, so the tree is left as a path
+    /// tree.set_custom::<SplayBalance>();
+    /// assert_eq!(tree.height(), 100);
+    /// ```
+    pub fn set_custom<U: TreeBalance + Debug + 'static>(&mut self) {
+        let tree = self.take();
+        self.tree = match self.strategy {
+            Some(strategy) => with_balance!(strategy, B => {
+                let tree = tree.into_any().downcast::<Tree<T, B, A>>().unwrap();
+                Box::new(tree.rebalance_as::<U>())
+            }),
+            None => Box::new(tree.into_avl().rebalance_as::<U>())
+        };
+        self.strategy = None;
+    }
+
+    /// Takes the inner tree, leaving an empty placeholder in its place
+    fn take(&mut self) -> Box<dyn DynBalanced<T, A>> {
+        core::mem::replace(&mut self.tree, Box::new(Tree::<T, UnbalancedBalance, A>::new()))
+    }
+
+    /// Returns true if the tree has no contents (see [Tree::is_empty])
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Returns the number of keys in the tree (see [Tree::len])
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Inserts an element into the tree (see [Tree::insert])
+    pub fn insert(&mut self, key: T) {
+        self.tree.insert(key)
+    }
+
+    /// Inserts an element into the tree, returning an error if the balance fails (see [Tree::try_insert])
+    pub fn try_insert(&mut self, key: T) -> Result<(), InspectError> {
+        self.tree.try_insert(key)
+    }
+
+    /// Inserts an element into the tree, starting the search at a finger (see [Tree::insert_near])
+    ///
+    /// ```
+    /// use project2::dyntree::{DynFinger, DynTree, Strategy};
+    /// let mut tree = DynTree::<u32>::new(Strategy::RedBlack);
+    /// let mut finger = DynFinger::new();
+    ///
+    /// for key in 0..100 {
+    ///     tree.insert_near(&mut finger, key);
+    /// }
+    /// assert!(tree.search_near(&mut finger, &98));
+    /// assert_eq!(tree.delete_near(&mut finger, &97), Some(97));
+    /// assert_eq!(tree.len(), 99);
+    /// ```
+    pub fn insert_near(&mut self, finger: &mut DynFinger, key: T) {
+        self.tree.insert_near(finger, key)
+    }
+
+    /// Finds if an element exists in the tree (see [Tree::search])
+    pub fn search(&self, key: &T) -> bool {
+        self.tree.search(key)
+    }
+
+    /// Finds if an element exists in the tree, letting the balance restructure the tree (see [Tree::search_mut])
+    pub fn search_mut(&mut self, key: &T) -> bool {
+        self.tree.search_mut(key)
+    }
+
+    /// Finds if an element exists in the tree, starting the search at a finger (see [Tree::search_near])
+    pub fn search_near(&self, finger: &mut DynFinger, key: &T) -> bool {
+        self.tree.search_near(finger, key)
+    }
+
+    /// Deletes an element from the tree (see [Tree::delete])
+    pub fn delete(&mut self, key: &T) -> Option<T> {
+        self.tree.delete(key)
+    }
+
+    /// Deletes an element from the tree, returning an error if the balance fails (see [Tree::try_delete])
+    pub fn try_delete(&mut self, key: &T) -> Result<Option<T>, InspectError> {
+        self.tree.try_delete(key)
+    }
+
+    /// Deletes an element from the tree, starting the search at a finger (see [Tree::delete_near])
+    pub fn delete_near(&mut self, finger: &mut DynFinger, key: &T) -> Option<T> {
+        self.tree.delete_near(finger, key)
+    }
+
+    /// Applies a batch of operations to the tree (see [Tree::apply_batch])
+    pub fn apply_batch<I: IntoIterator<Item = Op<T>>>(&mut self, batch: I) -> Vec<OpOutcome<T>> {
+        self.tree.apply_batch(batch.into_iter().collect())
+    }
+
+    /// Returns the height of the tree (see [Tree::height])
+    pub fn height(&self) -> usize {
+        self.tree.height()
+    }
+
+    /// Returns the number of leaves in the tree (see [Tree::leaves])
+    pub fn leaves(&self) -> usize {
+        self.tree.leaves()
+    }
+
+    /// Returns the aggregate of the keys in the given range (see [Tree::aggregate])
+    ///
+    /// ```
+    /// use project2::dyntree::{DynTree, Strategy};
+    /// use project2::tree::augment::Sum;
+    /// let mut tree = DynTree::<u32, Sum>::new(Strategy::WAVL);
+    /// for key in 0..=10 {
+    ///     tree.insert(key);
+    /// }
+    /// assert_eq!(tree.aggregate(3..6), 3 + 4 + 5);
+    ///
+    /// tree.set_strategy(Strategy::Treap);
+    /// assert_eq!(tree.aggregate(8..), 8 + 9 + 10);
+    /// ```
+    pub fn aggregate<R: RangeBounds<T>>(&self, range: R) -> A::Value {
+        self.tree.aggregate((range.start_bound(), range.end_bound()))
+    }
+
+    /// Describes the node holding the given key (see [Tree::node_info])
+    ///
+    /// The state the balance keeps for the node is formatted with [Debug],
+    /// since its type depends on the balance.
+    ///
+    /// ```
+    /// use project2::dyntree::{DynTree, Strategy};
+    /// let mut tree = DynTree::<u32>::new(Strategy::RedBlack);
+    /// for key in 1..=3 {
+    ///     tree.insert(key);
+    /// }
+    /// assert_eq!(tree.node_info(&1).unwrap().state, "Red");
+    ///
+    /// tree.set_strategy(Strategy::AA);
+    /// assert_eq!(tree.node_info(&2).unwrap().state, "2");
+    /// assert!(tree.node_info(&4).is_none());
+    /// ```
+    pub fn node_info(&self, key: &T) -> Option<NodeInfo<String>> {
+        self.tree.node_info(key)
+    }
+
+    /// Returns the number of rotations the balance has performed on the tree (see [Tree::rotations])
+    pub fn rotations(&self) -> usize {
+        self.tree.rotations()
+    }
+
+    /// Checks that the tree is valid (see [Tree::validate])
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.tree.validate()
+    }
+
+    /// Clears the contents of the tree (see [Tree::clear])
+    pub fn clear(&mut self) {
+        self.tree.clear()
+    }
+
+    /// Returns the number of lazily deleted nodes still in the tree (see [Tree::tombstones])
+    pub fn tombstones(&self) -> usize {
+        self.tree.tombstones()
+    }
+
+    /// Removes all lazily deleted nodes from the tree (see [Tree::compact])
+    pub fn compact(&mut self) {
+        self.tree.compact()
+    }
+
+    /// Rebuilds the tree into a height balanced shape if the balance supports it (see [Tree::rebuild])
+    pub fn rebuild(&mut self) -> bool {
+        self.tree.rebuild()
+    }
+
+    /// Enables or disables rebuilding the tree when it grows too tall (see [Tree::set_auto_rebuild])
+    ///
+    /// The setting is kept when the strategy is changed.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `factor` is less than 1
+    pub fn set_auto_rebuild(&mut self, factor: Option<f64>) {
+        self.tree.set_auto_rebuild(factor)
+    }
+
+}
+
+impl <T: Ord + Clone + 'static, A: Augment<T> + 'static> DynTree<T, A> {
+
+    /// Creates a new empty tree using the given strategy that deletes lazily
+    ///
+    /// See [Tree::with_lazy_delete]
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `fraction` is not in the range (0, 1]
+    pub fn with_lazy_delete(strategy: Strategy, fraction: f64) -> Self {
+        let mut tree = DynTree::new(strategy);
+        tree.set_lazy_delete(Some(fraction));
+        tree
+    }
+
+    /// Enables or disables lazy deletion (see [Tree::set_lazy_delete])
+    ///
+    /// The setting is kept when the strategy is changed.
+    pub fn set_lazy_delete(&mut self, fraction: Option<f64>) {
+        self.tree.set_lazy_delete(fraction)
+    }
+
+}
+
+impl <T: Ord + Display, A: Augment<T>> Display for DynTree<T, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        self.tree.fmt_display(f)
+    }
+}
+
+impl <T: Ord + Debug, A: Augment<T>> Debug for DynTree<T, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        self.tree.fmt_debug(f)
+    }
+}
+
+/// Remembered position in a [DynTree] used to speed up nearby operations
+///
+/// Holds a [Finger] for the balance the tree currently uses (see [DynTree::insert_near]).
+/// A new finger, or one last used before the strategy of the tree changed, starts at the root.
+#[derive(Default)]
+pub struct DynFinger(Option<Box<dyn Any>>);
+
+impl DynFinger {
+
+    /// Creates a new finger that starts at the root of a tree
+    pub fn new() -> Self {
+        DynFinger(None)
+    }
+
+    /// Returns the [Finger] for the given type of tree, replacing the held one if it is for a different type
+    fn typed<T: Ord + 'static, U: TreeBalance + 'static, A: Augment<T> + 'static>(&mut self) -> &mut Finger<T, U, A> {
+        if !self.0.as_ref().is_some_and(|finger| finger.is::<Finger<T, U, A>>()) {
+            self.0 = Some(Box::new(Finger::<T, U, A>::new()));
+        }
+        self.0.as_mut().and_then(|finger| finger.downcast_mut()).unwrap()
+    }
+
+}
+
+/// Converts a tree into a boxed tree using the given strategy
+fn boxed<T: Ord + 'static, U: TreeBalance, A: Augment<T> + 'static>(strategy: Strategy, tree: Tree<T, U, A>) -> Box<dyn DynBalanced<T, A>> {
+    with_balance!(strategy, B => Box::new(tree.rebalance_as::<B>()))
+}
+
+// Object safe version of the [Tree] API used by [DynTree].
+//
+// Generic arguments are replaced by concrete ones.  Trees convert themselves to the
+// balance of a strategy, or are unboxed by the caller when the strategy is known.
+trait DynBalanced<T: Ord, A: Augment<T>> {
+    fn is_empty(&self) -> bool;
+    fn len(&self) -> usize;
+    fn insert(&mut self, key: T);
+    fn try_insert(&mut self, key: T) -> Result<(), InspectError>;
+    fn insert_near(&mut self, finger: &mut DynFinger, key: T);
+    fn search(&self, key: &T) -> bool;
+    fn search_mut(&mut self, key: &T) -> bool;
+    fn search_near(&self, finger: &mut DynFinger, key: &T) -> bool;
+    fn delete(&mut self, key: &T) -> Option<T>;
+    fn try_delete(&mut self, key: &T) -> Result<Option<T>, InspectError>;
+    fn delete_near(&mut self, finger: &mut DynFinger, key: &T) -> Option<T>;
+    fn apply_batch(&mut self, batch: Vec<Op<T>>) -> Vec<OpOutcome<T>>;
+    fn height(&self) -> usize;
+    fn leaves(&self) -> usize;
+    fn aggregate(&self, range: (Bound<&T>, Bound<&T>)) -> A::Value;
+    fn node_info(&self, key: &T) -> Option<NodeInfo<String>>;
+    fn rotations(&self) -> usize;
+    fn validate(&self) -> Result<(), ValidationError>;
+    fn clear(&mut self);
+    fn tombstones(&self) -> usize;
+    fn compact(&mut self);
+    fn rebuild(&mut self) -> bool;
+    fn set_auto_rebuild(&mut self, factor: Option<f64>);
+    fn set_lazy_delete(&mut self, fraction: Option<f64>) where T: Clone;
+    fn fmt_display(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result where T: Display;
+    fn fmt_debug(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result where T: Debug;
+    fn convert(self: Box<Self>, strategy: Strategy) -> Box<dyn DynBalanced<T, A>>;
+    fn into_avl(self: Box<Self>) -> Tree<T, AVLBalance, A>;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl <T: Ord + 'static, U: TreeBalance + Debug + 'static, A: Augment<T> + 'static> DynBalanced<T, A> for Tree<T, U, A> {
+    fn is_empty(&self) -> bool {
+        Tree::is_empty(self)
+    }
+
+    fn len(&self) -> usize {
+        Tree::len(self)
+    }
+
+    fn insert(&mut self, key: T) {
+        Tree::insert(self, key)
+    }
+
+    fn try_insert(&mut self, key: T) -> Result<(), InspectError> {
+        Tree::try_insert(self, key)
+    }
+
+    fn insert_near(&mut self, finger: &mut DynFinger, key: T) {
+        Tree::insert_near(self, finger.typed(), key)
+    }
+
+    fn search(&self, key: &T) -> bool {
+        Tree::search(self, key)
+    }
+
+    fn search_mut(&mut self, key: &T) -> bool {
+        Tree::search_mut(self, key)
+    }
+
+    fn search_near(&self, finger: &mut DynFinger, key: &T) -> bool {
+        Tree::search_near(self, finger.typed(), key)
+    }
+
+    fn delete(&mut self, key: &T) -> Option<T> {
+        Tree::delete(self, key)
+    }
+
+    fn try_delete(&mut self, key: &T) -> Result<Option<T>, InspectError> {
+        Tree::try_delete(self, key)
+    }
+
+    fn delete_near(&mut self, finger: &mut DynFinger, key: &T) -> Option<T> {
+        Tree::delete_near(self, finger.typed(), key)
+    }
+
+    fn apply_batch(&mut self, batch: Vec<Op<T>>) -> Vec<OpOutcome<T>> {
+        Tree::apply_batch(self, batch)
+    }
+
+    fn height(&self) -> usize {
+        Tree::height(self)
+    }
+
+    fn leaves(&self) -> usize {
+        Tree::leaves(self)
+    }
+
+    fn aggregate(&self, range: (Bound<&T>, Bound<&T>)) -> A::Value {
+        Tree::aggregate(self, range)
+    }
+
+    fn node_info(&self, key: &T) -> Option<NodeInfo<String>> {
+        Tree::node_info(self, key).map(|info| NodeInfo {
+            depth: info.depth,
+            height: info.height,
+            leaves: info.leaves,
+            state: format!("{:?}", info.state)
+        })
+    }
+
+    fn rotations(&self) -> usize {
+        Tree::rotations(self)
+    }
+
+    fn validate(&self) -> Result<(), ValidationError> {
+        Tree::validate(self)
+    }
+
+    fn clear(&mut self) {
+        Tree::clear(self)
+    }
+
+    fn tombstones(&self) -> usize {
+        Tree::tombstones(self)
+    }
+
+    fn compact(&mut self) {
+        Tree::compact(self)
+    }
+
+    fn rebuild(&mut self) -> bool {
+        Tree::rebuild(self)
+    }
+
+    fn set_auto_rebuild(&mut self, factor: Option<f64>) {
+        Tree::set_auto_rebuild(self, factor)
+    }
+
+    fn set_lazy_delete(&mut self, fraction: Option<f64>) where T: Clone {
+        Tree::set_lazy_delete(self, fraction)
+    }
+
+    fn fmt_display(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result where T: Display {
+        Display::fmt(self, f)
+    }
+
+    fn fmt_debug(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result where T: Debug {
+        Debug::fmt(self, f)
+    }
+
+    fn convert(self: Box<Self>, strategy: Strategy) -> Box<dyn DynBalanced<T, A>> {
+        boxed(strategy, *self)
+    }
+
+    fn into_avl(self: Box<Self>) -> Tree<T, AVLBalance, A> {
+        self.rebalance_as()
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DynFinger, DynTree, Strategy};
+    use crate::{AVLTree, RedBlackTree, BinarySearchTree};
+    use crate::scapegoat::ScapegoatBalance;
+    use crate::tree::augment::Count;
+
+    #[test]
+    fn matches_typed_trees() {
+        let mut avl = AVLTree::new();
+        let mut redblack = RedBlackTree::new();
+        let mut unbalanced = BinarySearchTree::new();
+        let mut trees = [
+            DynTree::<u32>::new(Strategy::AVL),
+            DynTree::<u32>::new(Strategy::RedBlack),
+            DynTree::<u32>::new(Strategy::Unbalanced)
+        ];
+        for key in [40, 50, 60, 70, 90, 80, 100, 110, 30, 20] {
+            avl.insert(key);
+            redblack.insert(key);
+            unbalanced.insert(key);
+            for tree in trees.iter_mut() {
+                tree.insert(key);
+            }
+        }
+        assert_eq!(format!("{:?}", trees[0]), format!("{:?}", avl));
+        assert_eq!(format!("{:?}", trees[1]), format!("{:?}", redblack));
+        assert_eq!(format!("{:?}", trees[2]), format!("{:?}", unbalanced));
+    }
+
+    #[test]
+    fn switch_strategy() {
+        let mut tree = DynTree::<u32>::new(Strategy::Unbalanced);
+        for key in 0..100 {
+            tree.insert(key);
+        }
+        assert_eq!(tree.height(), 100);
+
+        let strategies = [
+            Strategy::AVL, Strategy::RelaxedAVL, Strategy::Splay, Strategy::Treap, Strategy::TopDownRedBlack,
+            Strategy::LeftLeaningRedBlack, Strategy::AA, Strategy::WAVL, Strategy::Weight, Strategy::Scapegoat
+        ];
+        for strategy in strategies {
+            tree.set_strategy(strategy);
+            assert_eq!(tree.strategy(), Some(strategy));
+            assert_eq!(tree.len(), 100);
+            assert_eq!(tree.validate(), Ok(()));
+        }
+        tree.set_custom::<ScapegoatBalance<60>>();
+        assert_eq!(tree.strategy(), None);
+        assert_eq!(tree.delete(&10), Some(10));
+        assert!((0..100).all(|key| tree.search(&key) == (key != 10)));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn switch_converts_once() {
+        let mut tree = DynTree::<u32>::with_seed(Strategy::Treap, 3);
+        for key in 0..1000 {
+            tree.insert(key);
+        }
+        let height = tree.height();
+        assert!(height > 10);
+
+        // Converting straight to a splay tree keeps the treap's shape, going through an AVL tree would not
+        tree.set_strategy(Strategy::Splay);
+        assert_eq!(tree.height(), height);
+        tree.set_custom::<crate::unbalanced::UnbalancedBalance>();
+        assert_eq!(tree.height(), height);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn lazy_delete_survives_switch() {
+        let mut tree = DynTree::<u32>::new(Strategy::RedBlack);
+        tree.set_lazy_delete(Some(1.0));
+        for key in 0..10 {
+            tree.insert(key);
+        }
+        tree.delete(&3);
+        tree.set_strategy(Strategy::AVL);
+        tree.delete(&4);
+        assert_eq!(tree.tombstones(), 2);
+        assert_eq!(tree.len(), 8);
+    }
+
+    #[test]
+    fn finger_across_switch() {
+        let mut tree = DynTree::<u32, Count>::with_lazy_delete(Strategy::WAVL, 0.5);
+        let mut finger = DynFinger::new();
+        for key in 0..100 {
+            tree.insert_near(&mut finger, key);
+        }
+        assert_eq!(tree.delete_near(&mut finger, &99), Some(99));
+
+        // The finger is for the old balance so the search starts at the root
+        tree.set_strategy(Strategy::LeftLeaningRedBlack);
+        assert!(tree.search_near(&mut finger, &98));
+        assert!(!tree.search_near(&mut finger, &99));
+        assert_eq!(tree.delete_near(&mut finger, &97), Some(97));
+        assert_eq!(tree.aggregate(90..), 8);
+        assert_eq!(tree.tombstones(), 2);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn custom_rebuild() {
+        let mut tree = DynTree::<u32>::custom_with_seed::<ScapegoatBalance<60>>(7);
+        for key in 0..100 {
+            tree.insert(key);
+        }
+        assert!(tree.rebuild());
+        assert_eq!(tree.height(), 7);
+        assert_eq!(tree.rotations(), 0);
+        assert_eq!(tree.node_info(&0).map(|info| info.depth), Some(7));
+
+        tree.set_strategy(Strategy::Splay);
+        tree.search_mut(&0);
+        assert!(tree.rotations() > 0);
+        assert_eq!(tree.node_info(&0).map(|info| info.depth), Some(1));
+    }
+}
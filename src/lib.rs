@@ -1,17 +1,495 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod aa;
+pub mod adaptive;
 pub mod avl;
+pub mod btree;
+pub mod dyntree;
+pub mod multiway;
 pub mod tree;
 pub mod redblack;
+pub mod scapegoat;
+pub mod set;
+pub mod splay;
+pub mod treap;
+pub mod wavl;
+pub mod weight;
 mod unbalanced; // Left private because if anyone really requires it, they can just use the typedef
 
 // Typedefs for easy access
-pub type Tree<T, U> = tree::Tree<T, U>;
+pub type Tree<T, U, A = ()> = tree::Tree<T, U, A>;
 pub type AVLTree<T> = Tree<T, avl::AVLBalance>;
+pub type RelaxedAVLTree<T, const K: usize> = Tree<T, avl::RelaxedAVLBalance<K>>;
 pub type RedBlackTree<T> = Tree<T, redblack::RedBlackBalance>;
+pub type TopDownRedBlackTree<T> = Tree<T, redblack::TopDownRedBlackBalance>;
+pub type LeftLeaningRedBlackTree<T> = Tree<T, redblack::LeftLeaningRedBlackBalance>;
 pub type BinarySearchTree<T> = Tree<T, unbalanced::UnbalancedBalance>;
+pub type SplayTree<T> = Tree<T, splay::SplayBalance>;
+pub type ScapegoatTree<T> = Tree<T, scapegoat::ScapegoatBalance>;
+pub type TreapTree<T> = Tree<T, treap::TreapBalance>;
+pub type AATree<T> = Tree<T, aa::AABalance>;
+pub type WAVLTree<T> = Tree<T, wavl::WAVLBalance>;
+pub type WeightBalancedTree<T> = Tree<T, weight::WeightBalance>;
+pub type DynTree<T, A = ()> = dyntree::DynTree<T, A>;
+pub type AdaptiveTree<T> = adaptive::AdaptiveTree<T>;
+pub type BTree<T, const ORDER: usize> = multiway::MultiTree<T, btree::BTreeBalance<ORDER>>;
+pub type Tree234<T> = multiway::MultiTree<T, btree::Tree234Balance>;
+
+// Conversions between the typedefs (see Tree::rebalance_as)
+macro_rules! impl_tree_from {
+    ($($from:ty => $($to:ty),+;)+) => {
+        $($(
+            impl <T: Ord, A: tree::augment::Augment<T>> From<Tree<T, $from, A>> for Tree<T, $to, A> {
+                fn from(tree: Tree<T, $from, A>) -> Self {
+                    tree.rebalance_as()
+                }
+            }
+        )+)+
+    };
+}
+
+impl_tree_from! {
+    avl::AVLBalance => redblack::RedBlackBalance, redblack::TopDownRedBlackBalance, redblack::LeftLeaningRedBlackBalance, unbalanced::UnbalancedBalance, splay::SplayBalance, scapegoat::ScapegoatBalance, treap::TreapBalance, aa::AABalance, wavl::WAVLBalance, weight::WeightBalance;
+    redblack::RedBlackBalance => avl::AVLBalance, redblack::TopDownRedBlackBalance, redblack::LeftLeaningRedBlackBalance, unbalanced::UnbalancedBalance, splay::SplayBalance, scapegoat::ScapegoatBalance, treap::TreapBalance, aa::AABalance, wavl::WAVLBalance, weight::WeightBalance;
+    redblack::TopDownRedBlackBalance => avl::AVLBalance, redblack::RedBlackBalance, redblack::LeftLeaningRedBlackBalance, unbalanced::UnbalancedBalance, splay::SplayBalance, scapegoat::ScapegoatBalance, treap::TreapBalance, aa::AABalance, wavl::WAVLBalance, weight::WeightBalance;
+    redblack::LeftLeaningRedBlackBalance => avl::AVLBalance, redblack::RedBlackBalance, redblack::TopDownRedBlackBalance, unbalanced::UnbalancedBalance, splay::SplayBalance, scapegoat::ScapegoatBalance, treap::TreapBalance, aa::AABalance, wavl::WAVLBalance, weight::WeightBalance;
+    unbalanced::UnbalancedBalance => avl::AVLBalance, redblack::RedBlackBalance, redblack::TopDownRedBlackBalance, redblack::LeftLeaningRedBlackBalance, splay::SplayBalance, scapegoat::ScapegoatBalance, treap::TreapBalance, aa::AABalance, wavl::WAVLBalance, weight::WeightBalance;
+    splay::SplayBalance => avl::AVLBalance, redblack::RedBlackBalance, redblack::TopDownRedBlackBalance, redblack::LeftLeaningRedBlackBalance, unbalanced::UnbalancedBalance, scapegoat::ScapegoatBalance, treap::TreapBalance, aa::AABalance, wavl::WAVLBalance, weight::WeightBalance;
+    scapegoat::ScapegoatBalance => avl::AVLBalance, redblack::RedBlackBalance, redblack::TopDownRedBlackBalance, redblack::LeftLeaningRedBlackBalance, unbalanced::UnbalancedBalance, splay::SplayBalance, treap::TreapBalance, aa::AABalance, wavl::WAVLBalance, weight::WeightBalance;
+    treap::TreapBalance => avl::AVLBalance, redblack::RedBlackBalance, redblack::TopDownRedBlackBalance, redblack::LeftLeaningRedBlackBalance, unbalanced::UnbalancedBalance, splay::SplayBalance, scapegoat::ScapegoatBalance, aa::AABalance, wavl::WAVLBalance, weight::WeightBalance;
+    aa::AABalance => avl::AVLBalance, redblack::RedBlackBalance, redblack::TopDownRedBlackBalance, redblack::LeftLeaningRedBlackBalance, unbalanced::UnbalancedBalance, splay::SplayBalance, scapegoat::ScapegoatBalance, treap::TreapBalance, wavl::WAVLBalance, weight::WeightBalance;
+    wavl::WAVLBalance => avl::AVLBalance, redblack::RedBlackBalance, redblack::TopDownRedBlackBalance, redblack::LeftLeaningRedBlackBalance, unbalanced::UnbalancedBalance, splay::SplayBalance, scapegoat::ScapegoatBalance, treap::TreapBalance, aa::AABalance, weight::WeightBalance;
+    weight::WeightBalance => avl::AVLBalance, redblack::RedBlackBalance, redblack::TopDownRedBlackBalance, redblack::LeftLeaningRedBlackBalance, unbalanced::UnbalancedBalance, splay::SplayBalance, scapegoat::ScapegoatBalance, treap::TreapBalance, aa::AABalance, wavl::WAVLBalance;
+}
 
 #[cfg(test)]
 mod tests {
     use super::{AVLTree, RedBlackTree};
+    use super::tree::{Finger, TreePath};
+    use super::tree::inspect::{Augment, BalanceContext, InspectError, InspectNode, NodeInspector, NodeOffset, TreePosition};
+
+    #[test]
+    fn finger_insert_search() {
+        let mut tree: RedBlackTree<u32> = RedBlackTree::new();
+        let mut finger = Finger::new();
+
+        // Insert keys that jump around but stay close to the last one
+        for base in (0..200).step_by(10) {
+            for offset in &[5, 2, 8, 0, 9, 1, 7, 3, 6, 4] {
+                tree.insert_near(&mut finger, base + offset);
+            }
+        }
+
+        for key in (0..200).rev() {
+            assert!(tree.search_near(&mut finger, &key));
+            assert!(tree.search(&key));
+        }
+        assert!(!tree.search_near(&mut finger, &200));
+
+        // Finger to a removed node falls back to the root
+        tree.delete(&0);
+        assert!(!tree.search_near(&mut finger, &0));
+        assert!(tree.search_near(&mut finger, &1));
+    }
+
+    #[test]
+    fn node_info() {
+        use super::{BinarySearchTree, TreapTree, WAVLTree};
+
+        let mut path: BinarySearchTree<u32> = BinarySearchTree::with_lazy_delete(1.0);
+        for key in [2, 1, 3, 4] {
+            path.insert(key);
+        }
+        let info = path.node_info(&3).unwrap();
+        assert_eq!((info.depth, info.height, info.leaves), (2, 2, 1));
+        assert_eq!(info.state, ());
+        // Lazily deleted keys are not described
+        path.delete(&3);
+        assert_eq!(path.node_info(&3), None);
+        assert_eq!(path.node_info(&4).map(|info| info.depth), Some(3));
+
+        // The root of a treap has the highest priority
+        let mut treap: TreapTree<u32> = TreapTree::new();
+        let mut wavl: WAVLTree<u32> = WAVLTree::new();
+        for key in 0..100 {
+            treap.insert(key);
+            wavl.insert(key);
+        }
+        let priority = |key| treap.node_info(&key).map_or(0, |info| info.state);
+        let root = (0..100).find(|key| treap.node_info(key).unwrap().depth == 1).unwrap();
+        assert_eq!(priority(root), (0..100).map(priority).max().unwrap());
+
+        // A WAVL tree only inserted into has its heights as ranks
+        assert!((0..100).all(|key| {
+            let info = wavl.node_info(&key).unwrap();
+            info.state == info.height
+        }));
+    }
+
+    #[test]
+    fn lazy_delete() {
+        let mut tree: AVLTree<u32> = AVLTree::with_lazy_delete(0.25);
+
+        for key in 0..100 {
+            tree.insert(key);
+        }
+        for key in (0..40).step_by(2) {
+            assert_eq!(tree.delete(&key), Some(key));
+            assert_eq!(tree.delete(&key), None);
+        }
+        assert_eq!(tree.tombstones(), 20);
+        assert_eq!(tree.len(), 80);
+        assert!(!tree.search(&10) && tree.search(&11));
+
+        // Reinserting revives the tombstone
+        tree.insert(10);
+        assert!(tree.search(&10));
+        assert_eq!(tree.tombstones(), 19);
+
+        // Pushing past a quarter of the nodes compacts the tree
+        for key in (40..54).step_by(2) {
+            tree.delete(&key);
+        }
+        assert_eq!(tree.tombstones(), 0);
+        assert_eq!(tree.len(), 74);
+        assert_eq!(tree.height(), 7);
+        for key in 0..100 {
+            assert_eq!(tree.search(&key), key >= 54 || key % 2 == 1 || key == 10);
+        }
+
+        // Disabling lazy deletion compacts the tree
+        tree.delete(&99);
+        tree.set_lazy_delete(None);
+        assert_eq!(tree.tombstones(), 0);
+        assert_eq!(tree.delete(&98), Some(98));
+        assert_eq!(tree.len(), 72);
+
+        // A tree holding only tombstones is empty
+        let mut tree: AVLTree<u32> = AVLTree::with_lazy_delete(1.0);
+        tree.insert(1);
+        tree.delete(&1);
+        assert_eq!(tree.tombstones(), 1);
+        assert!(tree.is_empty());
+        tree.insert(1);
+        assert!(!tree.is_empty());
+    }
+
+    /// Balance numbering the nodes in the order they are created,
+    /// checking the relatives of each inserted node through the inspector
+    struct Probe(usize);
+
+    static NEXT_PROBE: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    impl super::tree::inspect::TreeBalance for Probe {
+        type State = ();
+        type Cache = super::tree::cache::Height;
+
+        fn rebalance_insert<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, path: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
+            let (ppath, xpath) = match path {
+                (ppath, Some(xpath)) => (ppath, xpath),
+                // Children of the root are checked once they have a grandparent
+                (_, None) => return node.into_position(NodeOffset::Root)
+            };
+            let gid = node.inspect_balance(|b| b.0);
+            let pid = node.inspect_child(ppath).unwrap().inspect_balance(|b| b.0);
+            let uid = node.inspect_child(ppath.reflect()).map(|n| n.inspect_balance(|b| b.0));
+
+            // Move down to the inserted node and look back up
+            let x = node.move_to(NodeOffset::Descendant(vec![ppath, xpath])).ok().unwrap();
+            // The inserted node is a leaf so it cannot move down
+            let mut x = match x.move_to(NodeOffset::Child(TreePath::Left)) {
+                Ok(_) => panic!("Inserted node has a child"),
+                Err(x) => x
+            };
+            assert_eq!(x.inspect_placement(), Some(xpath));
+            assert_eq!(x.inspect_parent().unwrap().inspect_balance(|b| b.0), pid);
+            assert_eq!(x.inspect_grandparent().unwrap().inspect_balance(|b| b.0), gid);
+            let mut p = x.move_to(NodeOffset::Parent).ok().unwrap();
+            assert_eq!(p.inspect_sibling().map(|n| n.inspect_balance(|b| b.0)), uid);
+            let node = p.move_to(NodeOffset::Parent).ok().unwrap();
+            assert_eq!(node.inspect_balance(|b| b.0), gid);
+
+            // Rotate the inserted node above its parent, keeping the heights correct
+            let node = node.rotate_at(&[ppath], xpath);
+            assert_eq!(node.inspect_balance(|b| b.0), gid);
+            let xnode = node.inspect_child(ppath).unwrap();
+            let height = |path| xnode.inspect_child(path).map_or(0, |n| n.inspect_height());
+            assert_eq!(xnode.inspect_height(), height(TreePath::Left).max(height(TreePath::Right)) + 1);
+            drop(xnode);
+            let height = |path| node.inspect_child(path).map_or(0, |n| n.inspect_height());
+            assert_eq!(node.inspect_height(), height(TreePath::Left).max(height(TreePath::Right)) + 1);
+            node.into_position(NodeOffset::Root)
+        }
+
+        fn new<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+            Probe(NEXT_PROBE.fetch_add(1, std::sync::atomic::Ordering::Relaxed))
+        }
+
+        fn new_root<T: Ord>(context: &mut BalanceContext<T>) -> Self {
+            Self::new(context)
+        }
+
+        fn rebalance_delete<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: TreePath, _: &Self) -> TreePosition<T, Self, A> {
+            node.into_position(NodeOffset::Root)
+        }
+
+        fn state<T: Ord, A: Augment<T>>(_: &NodeInspector<T, Self, A>) -> Self::State {}
+
+        fn adjust_root(&mut self) {}
+    }
+
+    #[test]
+    fn inspect_relatives() {
+        let mut tree: super::Tree<u32, Probe> = super::Tree::new();
+
+        for key in [50, 25, 75, 60, 65, 10, 5, 90, 80] {
+            tree.insert(key);
+        }
+        assert_eq!(format!("{}", tree), "Tree: { 5, 10, 25, 50, 60, 65, 75, 80, 90, }");
+    }
+
+    /// Balance that misuses the inspector, checking the errors it gets back
+    struct Faulty();
+
+    impl super::tree::inspect::TreeBalance for Faulty {
+        type State = ();
+        type Cache = ();
+
+        fn rebalance_insert<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, path: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
+            let ppath = match path {
+                (ppath, Some(_)) => ppath,
+                (_, None) => return node.into_position(NodeOffset::Root)
+            };
+            {
+                // Updating a node while it is being inspected fails
+                let p = node.inspect_child(ppath).unwrap();
+                let mut again = node.inspect_child(ppath).unwrap();
+                p.inspect_balance(|_| assert_eq!(again.try_update_balance(|_| ()), Err(InspectError::Borrowed)));
+                assert!(again.try_update_balance(|_| ()).is_ok());
+            }
+
+            // Rotate up the uncle, then point past the bottom of the tree
+            match node.try_rotate((ppath.reflect(), ppath.reflect())) {
+                Ok(node) => node.into_position(NodeOffset::Descendant(vec![TreePath::Left; 8])),
+                Err((node, error)) => node.into_error(error)
+            }
+        }
+
+        fn new<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+            Faulty()
+        }
+
+        fn new_root<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+            Faulty()
+        }
+
+        fn rebalance_delete<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: TreePath, _: &Self) -> TreePosition<T, Self, A> {
+            node.into_position(NodeOffset::Descendant(vec![TreePath::Left; 8]))
+        }
+
+        fn state<T: Ord, A: Augment<T>>(_: &NodeInspector<T, Self, A>) -> Self::State {}
+
+        fn adjust_root(&mut self) {}
+    }
+
+    #[test]
+    fn inspect_errors() {
+        let mut tree: super::Tree<u32, Faulty> = super::Tree::new();
+
+        for key in [2, 1, 3] {
+            assert_eq!(tree.try_insert(key), Ok(()));
+        }
+        // The uncle of 4 can be rotated up but the offset returned is invalid
+        assert_eq!(tree.try_insert(4), Err(InspectError::InvalidOffset));
+        // 5 has no uncle to rotate up
+        assert_eq!(tree.try_insert(5), Err(InspectError::MissingChild(TreePath::Left)));
+        assert_eq!(format!("{}", tree), "Tree: { 1, 2, 3, 4, 5, }");
+
+        // Deletes still take place
+        assert_eq!(tree.try_delete(&5), Err(InspectError::InvalidOffset));
+        assert_eq!(tree.try_delete(&5), Ok(None));
+        assert_eq!(tree.len(), 4);
+        assert_eq!(format!("{}", tree), "Tree: { 1, 2, 3, 4, }");
+    }
+
+    /// Balance that always points to the node it was given through an empty list of paths
+    struct Stuck();
+
+    impl super::tree::inspect::TreeBalance for Stuck {
+        type State = ();
+        type Cache = ();
+
+        const ON_ACCESS: bool = true;
+
+        fn rebalance_insert<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
+            node.into_position(NodeOffset::Descendant(vec![]))
+        }
+
+        fn new<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+            Stuck()
+        }
+
+        fn new_root<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+            Stuck()
+        }
+
+        fn rebalance_delete<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: TreePath, _: &Self) -> TreePosition<T, Self, A> {
+            node.into_position(NodeOffset::Descendant(vec![]))
+        }
+
+        fn on_access<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: &[TreePath]) -> TreePosition<T, Self, A> {
+            node.into_position(NodeOffset::Descendant(vec![]))
+        }
+
+        fn state<T: Ord, A: Augment<T>>(_: &NodeInspector<T, Self, A>) -> Self::State {}
+
+        fn adjust_root(&mut self) {}
+    }
+
+    #[test]
+    fn empty_descendant_offset() {
+        let mut tree: super::Tree<u32, Stuck> = super::Tree::new();
+
+        // The offset is rejected instead of rebalancing the same node forever
+        for key in [2, 1, 3, 4] {
+            assert_eq!(tree.try_insert(key), Err(InspectError::InvalidOffset));
+        }
+        assert_eq!(tree.try_delete(&3), Err(InspectError::InvalidOffset));
+        assert_eq!(format!("{}", tree), "Tree: { 1, 2, 4, }");
+    }
+
+    /// Balance that fails if it is ever notified of an access
+    struct Unaccessed();
+
+    impl super::tree::inspect::TreeBalance for Unaccessed {
+        type State = ();
+        type Cache = ();
+
+        fn rebalance_insert<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
+            node.into_position(NodeOffset::Root)
+        }
+
+        fn new<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+            Unaccessed()
+        }
+
+        fn new_root<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+            Unaccessed()
+        }
+
+        fn rebalance_delete<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: TreePath, _: &Self) -> TreePosition<T, Self, A> {
+            node.into_position(NodeOffset::Root)
+        }
+
+        fn on_access<T: Ord, A: Augment<T>>(_: NodeInspector<T, Self, A>, _: &[TreePath]) -> TreePosition<T, Self, A> {
+            panic!("Accessed a balance that does not restructure on access")
+        }
+
+        fn state<T: Ord, A: Augment<T>>(_: &NodeInspector<T, Self, A>) -> Self::State {}
+
+        fn adjust_root(&mut self) {}
+    }
+
+    #[test]
+    fn access_skipped() {
+        let mut tree: super::Tree<u32, Unaccessed> = super::Tree::new();
+
+        for key in [2, 1, 3, 4] {
+            tree.insert(key);
+        }
+        assert!(tree.search_mut(&3));
+        assert_eq!(tree.delete(&3), Some(3));
+        assert_eq!(format!("{}", tree), "Tree: { 1, 2, 4, }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Inspected node has no Left child")]
+    fn inspect_error_panics() {
+        let mut tree: super::Tree<u32, Faulty> = super::Tree::new();
+
+        for key in [1, 2, 3] {
+            tree.insert(key);
+        }
+    }
+
+    #[test]
+    fn rebalance_as() {
+        use super::{AATree, BinarySearchTree, LeftLeaningRedBlackTree, ScapegoatTree, TreapTree, WeightBalancedTree};
+
+        let mut avl: AVLTree<u32> = AVLTree::with_lazy_delete(0.5);
+        for key in 0..100 {
+            avl.insert(key);
+        }
+        avl.delete(&50);
+
+        // The shape is kept and the tombstone carried over
+        let red_black: RedBlackTree<u32> = avl.into();
+        assert_eq!(red_black.height(), 7);
+        assert_eq!((red_black.len(), red_black.tombstones()), (99, 1));
+        assert_eq!(red_black.validate(), Ok(()));
+
+        // A path is rebuilt into a height balanced shape
+        let mut path: BinarySearchTree<u32> = BinarySearchTree::new();
+        for key in 0..1000 {
+            path.insert(key);
+        }
+        let avl: AVLTree<u32> = path.into();
+        assert_eq!(avl.height(), 10);
+        assert_eq!(avl.validate(), Ok(()));
+        let weight: WeightBalancedTree<u32> = avl.into();
+        assert_eq!(weight.validate(), Ok(()));
+
+        // Balances that cannot be rebuilt have the nodes appended in order
+        let treap: TreapTree<u32> = red_black.into();
+        assert_eq!((treap.len(), treap.tombstones()), (99, 1));
+        assert_eq!(treap.validate(), Ok(()));
+        let aa: AATree<u32> = treap.into();
+        assert_eq!(aa.validate(), Ok(()));
+        let left_leaning: LeftLeaningRedBlackTree<u32> = aa.into();
+        assert_eq!(left_leaning.validate(), Ok(()));
+        let scapegoat: ScapegoatTree<u32> = left_leaning.into();
+        assert!((0..100).all(|key| scapegoat.search(&key) == (key != 50)));
+        assert_eq!(scapegoat.validate(), Ok(()));
+    }
+
+    #[test]
+    fn rebalance_as_keeps_shape() {
+        use std::rc::Rc;
+
+        let keys: Vec<Rc<u32>> = (0..200).map(Rc::new).collect();
+        let build = || {
+            let mut avl: AVLTree<Rc<u32>> = AVLTree::new();
+            for key in &keys {
+                avl.insert(Rc::clone(key));
+            }
+            for key in keys.iter().step_by(3) {
+                avl.delete(key);
+            }
+            avl
+        };
+        let avl = build();
+        let shape = format!("{:?}", avl);
+        let height = avl.height();
+
+        // Rebuilding would give the tree a different shape
+        let mut rebuilt = build();
+        assert!(rebuilt.rebuild());
+        assert_ne!(format!("{:?}", rebuilt), shape);
+
+        // Every AVL tree can be colored as a red black tree, which keeps the AVL shape on the way back
+        let red_black: RedBlackTree<Rc<u32>> = avl.rebalance_as();
+        assert_eq!(red_black.height(), height);
+        assert_eq!(red_black.validate(), Ok(()));
+        let mut avl: AVLTree<Rc<u32>> = red_black.rebalance_as();
+        assert_eq!(format!("{:?}", avl), shape);
+
+        // The keys are moved between the trees rather than copied
+        for key in keys.iter().skip(1).step_by(3) {
+            assert!(Rc::ptr_eq(&avl.delete(key).unwrap(), key));
+        }
+    }
 
     #[test]
     #[ignore]
@@ -1,26 +1,32 @@
 extern crate project2;
 
-use std::fmt::Debug;
 use std::io;
 use std::io::Write;
 
-use project2::avl::AVLBalance;
-use project2::redblack::RedBlackBalance;
-use project2::tree::inspect::TreeBalance;
-use project2::tree::Tree;
+use project2::dyntree::{DynTree, Strategy};
 
 #[derive(strum_macros::Display)]
+#[allow(clippy::upper_case_acronyms)]
 enum TreeType {
     RedBlack,
     AVL,
 }
 
+impl From<TreeType> for Strategy {
+    fn from(tree_type: TreeType) -> Self {
+        match tree_type {
+            TreeType::RedBlack => Strategy::RedBlack,
+            TreeType::AVL => Strategy::AVL,
+        }
+    }
+}
+
 /// Get user input from stdin and split into a vector of strings
 fn get_user_input() -> Vec<String> {
     print!("> ");
     io::stdout().flush().unwrap();
     let mut input = String::new();
-    io::stdin().read_line(&mut input).ok().expect("stdin error");
+    io::stdin().read_line(&mut input).expect("stdin error");
 
     let mut result: Vec<String> = Vec::new();
 
@@ -28,7 +34,7 @@ fn get_user_input() -> Vec<String> {
         result.push(String::from(i));
     }
 
-    return result;
+    result
 }
 
 /// Attempt to get which tree the user wants to use
@@ -39,7 +45,7 @@ fn get_tree_selection() -> TreeType {
     loop {
         let input = get_user_input();
 
-        if input.len() <= 0 {
+        if input.is_empty() {
             continue;
         } else if input.len() >= 2 {
             println!("Too many arguments!");
@@ -78,13 +84,15 @@ fn print_help() {
 }
 
 /// Once the tree is selected, we start taking user input to manipulate the tree
-fn manipulate_tree<U: TreeBalance + Debug>(mut tree: Tree<u32, U>) {
+/// 
+/// Returns when the user switches to a new tree type
+fn manipulate_tree(tree: &mut DynTree<u32>) {
     print_help();
 
     loop {
         let input = get_user_input();
 
-        if input.len() <= 0 {
+        if input.is_empty() {
             continue;
         } else if input.len() >= 3 {
             println!("Too many arguments!");
@@ -168,7 +176,7 @@ fn manipulate_tree<U: TreeBalance + Debug>(mut tree: Tree<u32, U>) {
                 println!("Missing argument!");
             }
         } else if command == "switch" {
-            break;
+            return;
         } else if command == "help" {
             print_help();
         } else if command == "exit" {
@@ -184,13 +192,16 @@ fn main() {
     println!("This is the CLI for testing RedBlack and AVL trees.");
     println!("Enter `exit' to exit at any point.");
 
+    // The keys are carried over to the next tree type when switching
+    let mut tree = DynTree::new(Strategy::AVL);
     loop {
         let tree_selection = get_tree_selection();
-        println!("You have selected a {} tree.", tree_selection.to_string());
-
-        match tree_selection {
-            TreeType::RedBlack => manipulate_tree::<RedBlackBalance>(Tree::new()),
-            TreeType::AVL => manipulate_tree::<AVLBalance>(Tree::new()),
+        println!("You have selected a {} tree.", tree_selection);
+        if !tree.is_empty() {
+            println!("Keeping the {} elements from the previous tree.", tree.len());
         }
+
+        tree.set_strategy(tree_selection.into());
+        manipulate_tree(&mut tree);
     }
 }
@@ -0,0 +1,1270 @@
+use alloc::vec::Vec;
+use core::fmt::Display;
+use core::fmt::Debug;
+
+mod node;
+pub mod inspect;
+
+use node::MultiNode;
+use inspect::{MultiBalance, MultiInspector};
+use crate::tree::{LazyDelete, Op, OpOutcome, TreeRng};
+use crate::tree::inspect::{InspectError, ValidationError};
+
+/// "Balanced" multiway tree implementation
+///
+/// Each node holds several keys and has one more child than keys, with every leaf at the same depth.
+/// Like [Tree](crate::tree::Tree) the tree itself does not do any balancing, instead it relies on
+/// the associated [MultiBalance] to decide when to split, merge and shift keys between nodes.
+/// The tree offers the same set operations as [Tree](crate::tree::Tree) (see [SearchTree](crate::set::SearchTree)).
+pub struct MultiTree<T: Ord, U: MultiBalance> {
+    /// Root of the tree
+    root: Option<MultiNode<T>>,
+    /// Number of keys stored in the tree
+    len: usize,
+    /// Number of lazily deleted keys still in the tree
+    tombstones: usize,
+    /// Lazy deletion settings, if enabled
+    lazy: Option<LazyDelete<T>>,
+    /// Random number generator the balance can draw from
+    rng: TreeRng,
+    /// Number of splits performed by the balance
+    splits: usize,
+    /// Number of merges performed by the balance
+    merges: usize,
+    /// The balance is only used through its associated functions
+    balance: core::marker::PhantomData<U>
+}
+
+impl <T: Ord, U: MultiBalance> MultiTree<T, U> {
+
+    /// Creates a new empty tree
+    ///
+    /// ```
+    /// use project2::multiway::MultiTree;
+    /// use project2::btree::BTreeBalance;
+    /// let tree = MultiTree::<usize, BTreeBalance<4>>::new();
+    /// assert!(tree.is_empty());
+    /// ```
+    pub fn new() -> Self {
+        MultiTree {
+            root: None,
+            len: 0,
+            tombstones: 0,
+            lazy: None,
+            rng: TreeRng::new(TreeRng::DEFAULT_SEED),
+            splits: 0,
+            merges: 0,
+            balance: core::marker::PhantomData
+        }
+    }
+
+    /// Creates a new empty tree using the given seed for random numbers
+    ///
+    /// Balances can draw random numbers while restructuring the tree (see [MultiInspector::random]).
+    /// Trees created with the same seed and given the same operations will always have the same shape.
+    /// Trees created with [MultiTree::new] use a fixed seed.
+    ///
+    /// ```
+    /// use project2::Tree234;
+    /// let mut tree = Tree234::<usize>::with_seed(7);
+    /// tree.insert(2);
+    /// assert!(tree.search(&2));
+    /// ```
+    pub fn with_seed(seed: u64) -> Self {
+        MultiTree {
+            rng: TreeRng::new(seed),
+            ..MultiTree::new()
+        }
+    }
+
+    /// Returns true if the tree has no contents
+    ///
+    /// Lazily deleted keys are not counted, so a tree holding only tombstones is empty.
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// let mut tree = BTree::<usize, 4>::new();
+    /// assert!(tree.is_empty());
+    /// // Insert 2
+    /// tree.insert(2);
+    /// assert!(!tree.is_empty());
+    /// // Remove 2
+    /// tree.delete(&2);
+    /// assert!(tree.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of keys in the tree
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// let mut tree = BTree::<usize, 4>::new();
+    /// assert_eq!(tree.len(), 0);
+    ///
+    /// tree.insert(2);
+    /// tree.insert(3);
+    /// tree.insert(3);
+    /// assert_eq!(tree.len(), 2);
+    ///
+    /// tree.delete(&2);
+    /// assert_eq!(tree.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Inserts an element into the tree
+    ///
+    /// The key is added to a leaf, with the associated [MultiBalance] restructuring
+    /// the nodes on the way down and back up the tree
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// let mut tree = BTree::<usize, 4>::new();
+    /// // Insert 2
+    /// tree.insert(2);
+    /// assert!(tree.search(&2));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the balance fails while rebalancing (see [MultiTree::try_insert])
+    pub fn insert(&mut self, key: T) {
+        if let Err(error) = self.try_insert(key) {
+            panic!("{}", error)
+        }
+    }
+
+    /// Inserts an element into the tree, returning an error if the balance fails
+    ///
+    /// Behaves like [MultiTree::insert] but if the associated [MultiBalance] fails while rebalancing
+    /// (e.g. it splits a missing child or stops with [MultiInspector::fail]) the error is returned
+    /// instead of panicking.  The key is still inserted but the nodes above where the balance
+    /// failed are not rebalanced.
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// let mut tree = BTree::<usize, 4>::new();
+    ///
+    /// assert!(tree.try_insert(2).is_ok());
+    /// assert!(tree.search(&2));
+    /// ```
+    pub fn try_insert(&mut self, key: T) -> Result<(), InspectError> {
+        self.insert_at(&mut MultiFinger::new(), key).1
+    }
+
+    /// Inserts an element into the tree, starting the search at a finger
+    ///
+    /// Behaves like [MultiTree::insert] but the search only compares keys from the lowest node
+    /// on the path remembered by the [MultiFinger] whose range holds the key, following the path
+    /// from the root without comparing keys.  The finger is moved to the node the key was added to.
+    ///
+    /// The finger should only be used with the tree it was last used on,
+    /// using it with a different tree will give incorrect results.
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// use project2::multiway::MultiFinger;
+    /// let mut tree = BTree::<usize, 4>::new();
+    /// let mut finger = MultiFinger::new();
+    ///
+    /// // Insert time ordered keys
+    /// for key in 0..100 {
+    ///     tree.insert_near(&mut finger, key);
+    /// }
+    /// assert!(tree.search(&50));
+    /// assert_eq!(tree.validate(), Ok(()));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the balance fails while rebalancing (see [MultiTree::try_insert])
+    pub fn insert_near(&mut self, finger: &mut MultiFinger, key: T) {
+        if let Err(error) = self.insert_at(finger, key).1 {
+            panic!("{}", error)
+        }
+    }
+
+    /// Finds if an element exists in the tree
+    ///
+    /// ```
+    /// # use project2::BTree;
+    /// let mut tree = BTree::<usize, 4>::new();
+    ///
+    /// assert!(!tree.search(&2));
+    ///
+    /// // Insert 2
+    /// tree.insert(2);
+    /// assert!(tree.search(&2));
+    ///
+    /// // Delete 2
+    /// tree.delete(&2);
+    /// assert!(!tree.search(&2));
+    /// ```
+    pub fn search(&self, key: &T) -> bool {
+        let mut next = self.root.as_ref();
+        while let Some(node) = next {
+            match node.search(key) {
+                Ok(index) => return !node.is_tombstone(index),
+                Err(index) => next = node.child(index)
+            }
+        }
+        false
+    }
+
+    /// Finds if an element exists in the tree, letting the balance restructure the tree
+    ///
+    /// Behaves like [MultiTree::search] but each node above the node the search ended at
+    /// is passed to [MultiBalance::on_access] on the way back up.
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// let mut tree = BTree::<usize, 4>::new();
+    ///
+    /// for key in 0..10 {
+    ///     tree.insert(key);
+    /// }
+    ///
+    /// assert!(tree.search_mut(&3));
+    /// assert!(!tree.search_mut(&10));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the balance fails while restructuring
+    pub fn search_mut(&mut self, key: &T) -> bool {
+        let mut root = match self.root.take() {
+            Some(root) => root,
+            None => return false
+        };
+        let mut checked = Ok(());
+        let found = self.access(&mut root, key, &mut checked);
+        self.root = Some(root);
+        if let Err(error) = checked {
+            panic!("{}", error)
+        }
+        self.check_invariants();
+        found
+    }
+
+    /// Finds if an element exists in the tree, starting the search at a finger
+    ///
+    /// Behaves like [MultiTree::search] but starts the search at the node remembered by the
+    /// [MultiFinger] (see [MultiTree::insert_near]).  The finger is moved to the node the search ended at.
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// use project2::multiway::MultiFinger;
+    /// let mut tree = BTree::<usize, 4>::new();
+    /// let mut finger = MultiFinger::new();
+    ///
+    /// for key in 0..100 {
+    ///     tree.insert(key * 2);
+    /// }
+    ///
+    /// assert!(tree.search_near(&mut finger, &50));
+    /// assert!(tree.search_near(&mut finger, &52));
+    /// assert!(!tree.search_near(&mut finger, &53));
+    /// ```
+    pub fn search_near(&self, finger: &mut MultiFinger, key: &T) -> bool {
+        let mut node = match &self.root {
+            Some(root) => root,
+            None => return false
+        };
+        let mut path = core::mem::take(&mut finger.0);
+        path.truncate(finger_depth(node, &path, key));
+        for &index in path.iter() {
+            node = node.child(index).unwrap();
+        }
+
+        let found = loop {
+            match node.search(key) {
+                Ok(index) => break !node.is_tombstone(index),
+                Err(index) => match node.child(index) {
+                    Some(child) => {
+                        path.push(index);
+                        node = child;
+                    },
+                    None => break false
+                }
+            }
+        };
+        finger.0 = path;
+        found
+    }
+
+    /// Removes an element from the tree if it exists
+    ///
+    /// Removes the referenced element from the tree, returning the owned version if it exists.
+    /// Keys held by internal nodes are swapped with the largest key before them (which is always
+    /// in a leaf) and the nodes on the way back up are rebalanced by the associated [MultiBalance].
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// let mut tree = BTree::<usize, 4>::new();
+    ///
+    /// // Insert 2
+    /// tree.insert(2);
+    /// assert!(tree.search(&2));
+    ///
+    /// // Delete 2
+    /// assert_eq!(tree.delete(&2), Some(2));
+    /// assert!(!tree.search(&2) && tree.is_empty());
+    /// assert_eq!(tree.delete(&2), None);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the balance fails while rebalancing (see [MultiTree::try_delete])
+    pub fn delete(&mut self, key: &T) -> Option<T> {
+        self.try_delete(key).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Removes an element from the tree if it exists, returning an error if the balance fails
+    ///
+    /// Behaves like [MultiTree::delete] but if the associated [MultiBalance] fails while rebalancing
+    /// the error is returned instead of panicking.  The key is still removed but the nodes above
+    /// where the balance failed are not rebalanced.
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// let mut tree = BTree::<usize, 4>::new();
+    /// tree.insert(2);
+    ///
+    /// assert_eq!(tree.try_delete(&2), Ok(Some(2)));
+    /// assert_eq!(tree.try_delete(&2), Ok(None));
+    /// ```
+    pub fn try_delete(&mut self, key: &T) -> Result<Option<T>, InspectError> {
+        let (key, checked) = self.delete_at(&mut MultiFinger::new(), key);
+        checked.map(|_| key)
+    }
+
+    /// Removes an element from the tree if it exists, starting the search at a finger
+    ///
+    /// Behaves like [MultiTree::delete] but starts the search at the node remembered by the
+    /// [MultiFinger] (see [MultiTree::insert_near]).  The finger is moved to the node the key was found in.
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// use project2::multiway::MultiFinger;
+    /// let mut tree = BTree::<usize, 4>::new();
+    /// let mut finger = MultiFinger::new();
+    ///
+    /// for key in 0..100 {
+    ///     tree.insert(key);
+    /// }
+    /// for key in 20..40 {
+    ///     assert_eq!(tree.delete_near(&mut finger, &key), Some(key));
+    /// }
+    /// assert_eq!(tree.len(), 80);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the balance fails while rebalancing (see [MultiTree::try_delete])
+    pub fn delete_near(&mut self, finger: &mut MultiFinger, key: &T) -> Option<T> {
+        let (key, checked) = self.delete_at(finger, key);
+        checked.map(|_| key).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Applies a batch of insert and delete operations to the tree
+    ///
+    /// The batch is sorted by key (keeping the order of operations on the same key) and each
+    /// operation is applied on its own through a [MultiFinger] (see [MultiTree::insert_near]),
+    /// so it only compares keys in the part of the tree between it and the previous key.
+    /// Returns the outcome of each operation in the order the operations were given.
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// use project2::tree::{Op, OpOutcome};
+    /// let mut tree = BTree::<usize, 4>::new();
+    /// tree.insert(2);
+    ///
+    /// let outcomes = tree.apply_batch(vec![
+    ///     Op::Insert(3),
+    ///     Op::Delete(2),
+    ///     Op::Insert(3),
+    ///     Op::Delete(4)
+    /// ]);
+    ///
+    /// assert_eq!(outcomes, vec![
+    ///     OpOutcome::Inserted,
+    ///     OpOutcome::Deleted(2),
+    ///     OpOutcome::Exists,
+    ///     OpOutcome::Missing
+    /// ]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the balance fails while rebalancing
+    pub fn apply_batch<I: IntoIterator<Item = Op<T>>>(&mut self, batch: I) -> Vec<OpOutcome<T>> {
+        let mut batch: Vec<(usize, Op<T>)> = batch.into_iter().enumerate().collect();
+        // Stable sort so operations on the same key happen in the given order
+        batch.sort_by(|a, b| a.1.key().cmp(b.1.key()));
+
+        let mut finger = MultiFinger::new();
+        let mut outcomes: Vec<(usize, OpOutcome<T>)> = batch.into_iter().map(|(i, op)| {
+            let outcome = match op {
+                Op::Insert(key) => match self.insert_at(&mut finger, key) {
+                    (_, Err(error)) => panic!("{}", error),
+                    (true, _) => OpOutcome::Inserted,
+                    (false, _) => OpOutcome::Exists
+                },
+                Op::Delete(key) => {
+                    self.delete_near(&mut finger, &key).map_or(OpOutcome::Missing, OpOutcome::Deleted)
+                }
+            };
+            (i, outcome)
+        }).collect();
+
+        outcomes.sort_by_key(|(i, _)| *i);
+        outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+    }
+
+    /// Get the height of the tree
+    ///
+    /// Returns the number of nodes on the path from the root to any leaf
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// let mut tree = BTree::<usize, 4>::new();
+    ///
+    /// assert_eq!(tree.height(), 0);
+    ///
+    /// // The root holds up to three keys
+    /// tree.insert(1);
+    /// tree.insert(2);
+    /// tree.insert(3);
+    /// assert_eq!(tree.height(), 1);
+    ///
+    /// // Splitting the root adds a level
+    /// tree.insert(4);
+    /// assert_eq!(tree.height(), 2);
+    /// ```
+    pub fn height(&self) -> usize {
+        self.root.as_ref().map_or(0, |root| root.height())
+    }
+
+    /// Get the number of leaves of the tree
+    ///
+    /// Returns the number of leaf nodes (nodes with no children) in the tree
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// let mut tree = BTree::<usize, 4>::new();
+    ///
+    /// assert_eq!(tree.leaves(), 0);
+    ///
+    /// for key in 0..4 {
+    ///     tree.insert(key);
+    /// }
+    /// assert_eq!(tree.leaves(), 2);
+    /// ```
+    pub fn leaves(&self) -> usize {
+        self.root.as_ref().map_or(0, |root| root.leaves())
+    }
+
+    /// Returns the number of times the associated [MultiBalance] has split a node
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// let mut tree = BTree::<usize, 4>::new();
+    ///
+    /// for key in 0..4 {
+    ///     tree.insert(key);
+    /// }
+    /// assert_eq!(tree.splits(), 1);
+    /// ```
+    pub fn splits(&self) -> usize {
+        self.splits
+    }
+
+    /// Returns the number of times the associated [MultiBalance] has merged two nodes
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// let mut tree = BTree::<usize, 4>::new();
+    ///
+    /// for key in 0..4 {
+    ///     tree.insert(key);
+    /// }
+    /// tree.delete(&3);
+    /// tree.delete(&2);
+    /// assert_eq!(tree.merges(), 1);
+    /// assert_eq!(tree.height(), 1);
+    /// ```
+    pub fn merges(&self) -> usize {
+        self.merges
+    }
+
+    /// Checks that the tree is still correct
+    ///
+    /// Checks that the keys are in order, that every node holds between [MultiBalance::MIN_KEYS] and
+    /// [MultiBalance::MAX_KEYS] keys (the root may hold fewer), that internal nodes have one more child
+    /// than keys, that every leaf is at the same depth and that [MultiTree::len] and [MultiTree::tombstones]
+    /// match the keys in the tree.
+    /// Runs in O(n).
+    ///
+    /// With the `debug-invariants` feature enabled the tree is validated after every operation that changes it.
+    ///
+    /// ```
+    /// use project2::Tree234;
+    /// let mut tree = Tree234::<usize>::new();
+    ///
+    /// for key in 0..100 {
+    ///     tree.insert(key);
+    /// }
+    /// assert_eq!(tree.validate(), Ok(()));
+    /// ```
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let (keys, tombstones) = match &self.root {
+            Some(root) => validate_node::<T, U>(root, (None, None), root.height(), true)?,
+            None => (0, 0)
+        };
+        if keys != self.len || tombstones != self.tombstones {
+            return Err(ValidationError::Length)
+        }
+        Ok(())
+    }
+
+    /// Clears the contents of the tree
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// let mut tree = BTree::<usize, 4>::new();
+    ///
+    /// tree.insert(2);
+    /// tree.insert(3);
+    /// tree.clear();
+    /// assert!(tree.is_empty());
+    /// assert_eq!(tree.height(), 0);
+    /// ```
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+        self.tombstones = 0;
+    }
+
+    /// Returns the number of lazily deleted keys still in the tree
+    ///
+    /// See [MultiTree::with_lazy_delete]
+    pub fn tombstones(&self) -> usize {
+        self.tombstones
+    }
+
+    /// Removes all lazily deleted keys from the tree
+    ///
+    /// The remaining keys are moved out of the tree and inserted again in order through
+    /// a [MultiFinger], which takes O(n log n) but only O(n) key comparisons.
+    ///
+    /// ```
+    /// use project2::BTree;
+    /// let mut tree = BTree::<usize, 4>::with_lazy_delete(1.0);
+    ///
+    /// for key in 0..100 {
+    ///     tree.insert(key);
+    /// }
+    /// for key in 0..90 {
+    ///     tree.delete(&key);
+    /// }
+    /// assert_eq!(tree.tombstones(), 90);
+    ///
+    /// tree.compact();
+    /// assert_eq!(tree.tombstones(), 0);
+    /// assert_eq!(tree.height(), 2);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the balance fails while rebalancing
+    pub fn compact(&mut self) {
+        if self.tombstones == 0 {
+            return
+        }
+        let mut keys = Vec::with_capacity(self.len + self.tombstones);
+        if let Some(root) = self.root.take() {
+            root.drain_into(&mut keys);
+        }
+        self.len = 0;
+        self.tombstones = 0;
+
+        let mut finger = MultiFinger::new();
+        for (key, _) in keys.into_iter().filter(|(_, tombstone)| !tombstone) {
+            if let Err(error) = self.insert_at(&mut finger, key).1 {
+                panic!("{}", error)
+            }
+        }
+    }
+
+    /// Inserts the key starting at the finger, returning true if it was not already in the tree
+    /// along with the error the balance stopped with (if any)
+    fn insert_at(&mut self, finger: &mut MultiFinger, key: T) -> (bool, Result<(), InspectError>) {
+        let root = match self.root.take() {
+            Some(root) => root,
+            None => {
+                self.root = Some(MultiNode::new_with(key));
+                self.len += 1;
+                finger.0.clear();
+                self.check_invariants();
+                return (true, Ok(()))
+            }
+        };
+
+        let hint = core::mem::take(&mut finger.0);
+        let depth = finger_depth(&root, &hint, &key);
+        // Give the root a parent so the balance can split it
+        let mut top = MultiNode::new_above(root);
+        let mut route = Route::new(&hint[..depth]);
+        let placed = self.insert_into(&mut top, key, &mut route);
+        if top.keys().is_empty() {
+            // The path starts at the root rather than its temporary parent
+            route.path.remove(0);
+        }
+        self.root = Some(top.into_only_child());
+        match placed {
+            Placement::Added => self.len += 1,
+            Placement::Revived => {
+                self.len += 1;
+                self.tombstones -= 1;
+            },
+            Placement::Exists => ()
+        }
+        finger.0 = route.path;
+        self.check_invariants();
+        (placed != Placement::Exists, route.checked)
+    }
+
+    /// Inserts the key below the given node
+    fn insert_into(&mut self, node: &mut MultiNode<T>, key: T, route: &mut Route) -> Placement {
+        let index = match route.next(node, &key) {
+            Ok(index) => return Self::revive(node, index),
+            Err(index) => index
+        };
+        if node.is_leaf() {
+            node.insert_key(index, key);
+            return Placement::Added
+        }
+
+        let index = if self.balance(node, &mut route.checked, |inspector| U::before_descend(inspector, index)) {
+            // The balance moved keys around the node, so search it again
+            route.hint = &[];
+            match node.search(&key) {
+                Ok(index) => return Self::revive(node, index),
+                Err(index) => index
+            }
+        } else {
+            index
+        };
+        route.path.push(index);
+        let placed = self.insert_into(node.child_mut(index).unwrap(), key, route);
+        if placed == Placement::Added {
+            self.balance(node, &mut route.checked, |inspector| U::rebalance_insert(inspector, index));
+        }
+        placed
+    }
+
+    /// Brings the key at the given index of the node back to life if it was lazily deleted
+    fn revive(node: &mut MultiNode<T>, index: usize) -> Placement {
+        if node.is_tombstone(index) {
+            node.set_tombstone(index, false);
+            Placement::Revived
+        } else {
+            Placement::Exists
+        }
+    }
+
+    /// Deletes the key starting at the finger, returning the removed key
+    /// along with the error the balance stopped with (if any)
+    fn delete_at(&mut self, finger: &mut MultiFinger, key: &T) -> (Option<T>, Result<(), InspectError>) {
+        let mut root = match self.root.take() {
+            Some(root) => root,
+            None => return (None, Ok(()))
+        };
+        let hint = core::mem::take(&mut finger.0);
+        let depth = finger_depth(&root, &hint, key);
+        let mut route = Route::new(&hint[..depth]);
+
+        if let Some(clone) = self.lazy.as_ref().map(|lazy| lazy.clone) {
+            // Mark the key as deleted, leaving it in the tree
+            let key = bury(&mut root, key, &mut route, clone);
+            self.root = Some(root);
+            finger.0 = route.path;
+            if key.is_some() {
+                self.len -= 1;
+                self.tombstones += 1;
+                self.check_invariants();
+                self.compact_if_needed();
+            }
+            return (key, Ok(()))
+        }
+
+        let removed = self.delete_from(&mut root, key, &mut route);
+        // A root left without keys is replaced by its only child
+        self.root = if root.keys().is_empty() && root.is_leaf() {
+            None
+        } else {
+            if root.keys().is_empty() && !route.path.is_empty() {
+                route.path.remove(0);
+            }
+            Some(root.into_only_child())
+        };
+        finger.0 = route.path;
+        let key = match removed {
+            Some((key, false)) => {
+                self.len -= 1;
+                Some(key)
+            },
+            // Only reachable if lazily deleted keys are left after lazy deletion is disabled
+            Some((_, true)) => {
+                self.tombstones -= 1;
+                None
+            },
+            None => None
+        };
+        self.check_invariants();
+        (key, route.checked)
+    }
+
+    /// Deletes the key from below the given node
+    fn delete_from(&mut self, node: &mut MultiNode<T>, key: &T, route: &mut Route) -> Option<(T, bool)> {
+        let (index, key) = match route.next(node, key) {
+            Ok(index) if node.is_leaf() => return Some(node.remove_key(index)),
+            Err(_) if node.is_leaf() => return None,
+            Ok(index) => {
+                // Replace the key with the largest key before it
+                let max = self.delete_max(node.child_mut(index).unwrap(), route);
+                (index, Some(node.replace_key(index, max)))
+            },
+            Err(index) => {
+                route.path.push(index);
+                (index, self.delete_from(node.child_mut(index).unwrap(), key, route))
+            }
+        };
+        if key.is_some() {
+            self.balance(node, &mut route.checked, |inspector| U::rebalance_delete(inspector, index));
+        }
+        key
+    }
+
+    /// Deletes the largest key from below the given node
+    fn delete_max(&mut self, node: &mut MultiNode<T>, route: &mut Route) -> (T, bool) {
+        if node.is_leaf() {
+            return node.pop_key().unwrap()
+        }
+        let index = node.children().len() - 1;
+        let key = self.delete_max(node.child_mut(index).unwrap(), route);
+        self.balance(node, &mut route.checked, |inspector| U::rebalance_delete(inspector, index));
+        key
+    }
+
+    /// Searches for the key below the given node, passing the nodes on the way back up to the balance
+    fn access(&mut self, node: &mut MultiNode<T>, key: &T, checked: &mut Result<(), InspectError>) -> bool {
+        let index = match node.search(key) {
+            Ok(index) => return !node.is_tombstone(index),
+            Err(index) => index
+        };
+        let found = match node.child_mut(index) {
+            Some(child) => self.access(child, key, checked),
+            None => return false
+        };
+        self.balance(node, checked, |inspector| U::on_access(inspector, index));
+        found
+    }
+
+    /// Calls the balance with an inspector for the node, unless the balance already failed
+    ///
+    /// Adds the splits and merges performed through the inspector to the tree's counts
+    /// and returns true if the balance restructured the node's children
+    fn balance<F>(&mut self, node: &mut MultiNode<T>, checked: &mut Result<(), InspectError>, apply: F) -> bool
+    where
+        F: FnOnce(&mut MultiInspector<T>)
+    {
+        if checked.is_err() {
+            return false
+        }
+        let mut inspector = MultiInspector::open(node, &mut self.rng);
+        apply(&mut inspector);
+        let (splits, merges, shifts, error) = inspector.into_counts();
+        self.splits += splits;
+        self.merges += merges;
+        if let Some(error) = error {
+            *checked = Err(error);
+        }
+        splits + merges + shifts > 0
+    }
+
+    /// Compacts the tree if the fraction of tombstones exceeds the configured limit
+    fn compact_if_needed(&mut self) {
+        if let Some(lazy) = &self.lazy {
+            if self.tombstones as f64 > lazy.fraction * (self.tombstones + self.len) as f64 {
+                self.compact();
+            }
+        }
+    }
+
+    /// Validates the tree if the `debug-invariants` feature is enabled
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the tree is not valid
+    fn check_invariants(&self) {
+        if cfg!(feature = "debug-invariants") {
+            if let Err(error) = self.validate() {
+                panic!("{}", error)
+            }
+        }
+    }
+
+}
+
+impl <T: Ord + Clone, U: MultiBalance> MultiTree<T, U> {
+
+    /// Creates a new empty tree that deletes lazily
+    ///
+    /// Instead of removing keys (and rebalancing the tree) [MultiTree::delete] marks the key as a tombstone
+    /// which is skipped by searches, printing, and [MultiTree::len].  Once the tombstones make up more than
+    /// `fraction` of the keys in the tree, the tree is compacted (see [MultiTree::compact]).
+    /// Inserting a lazily deleted key brings it back to life.
+    ///
+    /// Since the key stays in the tree, deleting returns a clone of the key.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `fraction` is not in the range (0, 1]
+    ///
+    /// ```
+    /// use project2::Tree234;
+    /// let mut tree = Tree234::<usize>::with_lazy_delete(0.5);
+    ///
+    /// for key in 0..10 {
+    ///     tree.insert(key);
+    /// }
+    /// assert_eq!(tree.delete(&2), Some(2));
+    /// assert!(!tree.search(&2));
+    /// assert_eq!(tree.len(), 9);
+    /// assert_eq!(tree.tombstones(), 1);
+    ///
+    /// // Deleting more than half of the keys compacts the tree
+    /// for key in 3..8 {
+    ///     tree.delete(&key);
+    /// }
+    /// assert_eq!(tree.tombstones(), 0);
+    /// assert_eq!(format!("{}", tree), "Tree: { 0, 1, 8, 9, }");
+    /// ```
+    pub fn with_lazy_delete(fraction: f64) -> Self {
+        let mut tree = MultiTree::new();
+        tree.set_lazy_delete(Some(fraction));
+        tree
+    }
+
+    /// Enables or disables lazy deletion
+    ///
+    /// See [MultiTree::with_lazy_delete].  Disabling lazy deletion compacts the tree.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `fraction` is not in the range (0, 1]
+    pub fn set_lazy_delete(&mut self, fraction: Option<f64>) {
+        match fraction {
+            Some(fraction) => {
+                assert!(fraction > 0.0 && fraction <= 1.0, "fraction must be in the range (0, 1]");
+                self.lazy = Some(LazyDelete {
+                    fraction,
+                    clone: T::clone
+                });
+                self.compact_if_needed();
+            },
+            None => {
+                self.lazy = None;
+                self.compact();
+            }
+        }
+    }
+
+}
+
+/// Remembered position in a [MultiTree] used to speed up nearby operations
+///
+/// A finger holds the child indices on the path from the root to the last node touched by an operation
+/// such as [MultiTree::search_near] or [MultiTree::insert_near].  Nodes do not link back to their parents,
+/// so the path is followed from the root without comparing keys, and keys are only compared from the lowest
+/// node on the path whose range holds the key.  Restructuring the tree may leave the path pointing
+/// elsewhere, in which case the search starts higher up (or at the root).
+#[derive(Clone, Debug, Default)]
+pub struct MultiFinger(Vec<usize>);
+
+impl MultiFinger {
+
+    /// Creates a new finger that starts at the root of a tree
+    pub fn new() -> Self {
+        MultiFinger(Vec::new())
+    }
+
+}
+
+/// Outcome of placing a key in a [MultiTree]
+#[derive(PartialEq)]
+enum Placement {
+    /// The key was added to a leaf
+    Added,
+    /// The key was lazily deleted and has been brought back
+    Revived,
+    /// The key was already in the tree
+    Exists
+}
+
+/// Route taken by an operation down a [MultiTree]
+struct Route<'h> {
+    /// Child indices still to be followed without comparing keys (see [MultiFinger])
+    hint: &'h [usize],
+    /// Child indices followed so far
+    path: Vec<usize>,
+    /// Error the balance stopped with, if any
+    checked: Result<(), InspectError>
+}
+
+impl <'h> Route<'h> {
+    /// Creates a route that follows the given child indices first
+    fn new(hint: &'h [usize]) -> Self {
+        Route {
+            hint,
+            path: Vec::new(),
+            checked: Ok(())
+        }
+    }
+
+    /// Searches the keys of the node (see [MultiNode::search]), taking the next index of the hint instead if there is one
+    ///
+    /// The key lies strictly between the keys around every child on the hint, so it is never held by the nodes above
+    /// the end of the hint.  The temporary parent of the root does not take an index.
+    fn next<T: Ord>(&mut self, node: &MultiNode<T>, key: &T) -> Result<usize, usize> {
+        match self.hint.split_first() {
+            Some((&index, rest)) if !node.keys().is_empty() => {
+                self.hint = rest;
+                Err(index)
+            },
+            _ => node.search(key)
+        }
+    }
+}
+
+/// Returns how many child indices of the path to follow from the root to reach the lowest node
+/// on the path whose range holds the key
+///
+/// The ranges of the nodes on the path are found first without comparing keys, then checked from the bottom up
+fn finger_depth<T: Ord>(root: &MultiNode<T>, path: &[usize], key: &T) -> usize {
+    let mut bounds: Vec<(Option<&T>, Option<&T>)> = Vec::with_capacity(path.len());
+    let (mut node, mut low, mut high) = (root, None, None);
+    for &index in path {
+        let child = match node.child(index) {
+            Some(child) => child,
+            None => break
+        };
+        if index > 0 {
+            low = Some(&node.keys()[index - 1]);
+        }
+        if let Some(key) = node.keys().get(index) {
+            high = Some(key);
+        }
+        bounds.push((low, high));
+        node = child;
+    }
+    bounds.iter()
+        .rposition(|&(low, high)| low.is_none_or(|low| low < key) && high.is_none_or(|high| key < high))
+        .map_or(0, |depth| depth + 1)
+}
+
+/// Marks the key as lazily deleted if it is held below the given node, returning a clone of it
+fn bury<T: Ord>(node: &mut MultiNode<T>, key: &T, route: &mut Route, clone: fn(&T) -> T) -> Option<T> {
+    match route.next(node, key) {
+        Ok(index) if node.is_tombstone(index) => None,
+        Ok(index) => {
+            node.set_tombstone(index, true);
+            Some(clone(&node.keys()[index]))
+        },
+        Err(index) => match node.child_mut(index) {
+            Some(child) => {
+                route.path.push(index);
+                bury(child, key, route, clone)
+            },
+            None => None
+        }
+    }
+}
+
+impl <T: Ord, U: MultiBalance> Default for MultiTree<T, U> {
+    fn default() -> Self {
+        MultiTree::new()
+    }
+}
+
+/// Checks the node (and the tree it roots) lies between the given bounds, has a valid
+/// number of keys and children, and that its leaves are at the bottom of a tree of the given height
+///
+/// Returns the number of keys and lazily deleted keys in the tree rooted by the node
+fn validate_node<T: Ord, U: MultiBalance>(node: &MultiNode<T>, bounds: (Option<&T>, Option<&T>), height: usize, root: bool) -> Result<(usize, usize), ValidationError> {
+    let keys = node.keys();
+    if keys.is_empty() || keys.len() > U::MAX_KEYS {
+        return Err(ValidationError::Balance("Multiway node holds too many keys or none"))
+    }
+    if !root && keys.len() < U::MIN_KEYS {
+        return Err(ValidationError::Balance("Multiway node holds too few keys"))
+    }
+    let ordered = keys.windows(2).all(|pair| pair[0] < pair[1])
+        && bounds.0.is_none_or(|low| low < &keys[0])
+        && bounds.1.is_none_or(|high| &keys[keys.len() - 1] < high);
+    if !ordered {
+        return Err(ValidationError::Unordered)
+    }
+
+    let tombstones = (0..keys.len()).filter(|&i| node.is_tombstone(i)).count();
+    if node.is_leaf() {
+        return if height == 1 {
+            Ok((keys.len() - tombstones, tombstones))
+        } else {
+            Err(ValidationError::Balance("Multiway leaves are not all at the same depth"))
+        }
+    }
+    if node.children().len() != keys.len() + 1 {
+        return Err(ValidationError::Balance("Multiway node does not have one more child than keys"))
+    }
+    if height == 1 {
+        return Err(ValidationError::Balance("Multiway leaves are not all at the same depth"))
+    }
+    let mut count = (keys.len() - tombstones, tombstones);
+    for (i, child) in node.children().iter().enumerate() {
+        let low = if i == 0 { bounds.0 } else { Some(&keys[i - 1]) };
+        let high = keys.get(i).or(bounds.1);
+        let (live, dead) = validate_node::<T, U>(child, (low, high), height - 1, false)?;
+        count.0 += live;
+        count.1 += dead;
+    }
+    Ok(count)
+}
+
+impl <T, U> Display for MultiTree<T, U>
+where
+    T: Ord + Display,
+    U: MultiBalance
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        match &self.root {
+            Some(root) => write!(f, "Tree: {{ {}}}", root),
+            None => write!(f, "Tree: Empty")
+        }
+    }
+}
+
+impl <T, U> Debug for MultiTree<T, U>
+where
+    T: Ord + Debug,
+    U: MultiBalance
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        match &self.root {
+            Some(root) => root.fmt(f),
+            None => Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MultiFinger, MultiTree};
+    use super::inspect::{MultiBalance, MultiInspector};
+    use crate::{AVLTree, BTree, Tree234};
+    use crate::tree::{Op, OpOutcome};
+    use crate::tree::inspect::{InspectError, ValidationError};
+
+    /// Balance that never restructures the tree
+    struct Unbalanced();
+
+    impl MultiBalance for Unbalanced {
+        const MAX_KEYS: usize = 3;
+        const MIN_KEYS: usize = 1;
+
+        fn rebalance_insert<T: Ord>(_: &mut MultiInspector<T>, _: usize) {}
+
+        fn rebalance_delete<T: Ord>(_: &mut MultiInspector<T>, _: usize) {}
+    }
+
+    /// Balance that splits full children on the way down when the tree's generator says so
+    struct CoinSplit();
+
+    impl MultiBalance for CoinSplit {
+        const MAX_KEYS: usize = 3;
+        const MIN_KEYS: usize = 1;
+
+        fn before_descend<T: Ord>(inspector: &mut MultiInspector<T>, index: usize) {
+            if inspector.child_len(index) == Some(Self::MAX_KEYS) && inspector.random() & 1 == 1 {
+                inspector.split(index);
+            }
+        }
+
+        fn rebalance_insert<T: Ord>(inspector: &mut MultiInspector<T>, index: usize) {
+            if inspector.child_len(index) > Some(Self::MAX_KEYS) {
+                inspector.split(index);
+            }
+        }
+
+        fn rebalance_delete<T: Ord>(_: &mut MultiInspector<T>, _: usize) {}
+    }
+
+    /// Balance that splits a child that does not exist
+    struct Broken();
+
+    impl MultiBalance for Broken {
+        const MAX_KEYS: usize = 3;
+        const MIN_KEYS: usize = 1;
+
+        fn rebalance_insert<T: Ord>(inspector: &mut MultiInspector<T>, index: usize) {
+            if let Err(error) = inspector.try_split(index + 10) {
+                inspector.fail(error);
+            }
+        }
+
+        fn rebalance_delete<T: Ord>(inspector: &mut MultiInspector<T>, _: usize) {
+            inspector.fail(InspectError::InvalidOffset);
+        }
+    }
+
+    #[test]
+    fn display() {
+        let mut tree: MultiTree<u32, crate::btree::BTreeBalance<3>> = MultiTree::new();
+        assert_eq!(format!("{}", tree), "Tree: Empty");
+
+        for key in [5, 1, 4, 2, 3] {
+            tree.insert(key);
+        }
+        assert_eq!(format!("{}", tree), "Tree: { 1, 2, 3, 4, 5, }");
+        assert_eq!(format!("{:?}", tree), "[2, 4] { children: [[1], [3], [5]] }");
+    }
+
+    #[test]
+    // Validating after every operation catches the overfull root on insert
+    #[cfg_attr(feature = "debug-invariants", should_panic(expected = "Multiway node holds too many keys or none"))]
+    fn verify_invariants() {
+        let mut tree: MultiTree<u32, Unbalanced> = MultiTree::new();
+
+        for key in 0..3 {
+            tree.insert(key);
+        }
+        assert_eq!(tree.validate(), Ok(()));
+
+        tree.insert(3);
+        assert_eq!(tree.validate(), Err(ValidationError::Balance("Multiway node holds too many keys or none")));
+    }
+
+    #[test]
+    fn finger_random() {
+        let mut tree: Tree234<u32> = MultiTree::new();
+        let mut expected: AVLTree<u32> = AVLTree::new();
+        let mut finger = MultiFinger::new();
+
+        // Keys wander around so the finger is sometimes close and sometimes stale
+        let (mut seed, mut key) = (11u32, 250u32);
+        for _ in 0..3000 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            key = (key + (seed >> 8) % 21 + 490) % 500;
+            match seed & 3 {
+                0 => assert_eq!(tree.delete_near(&mut finger, &key), expected.delete(&key)),
+                1 => assert_eq!(tree.search_near(&mut finger, &key), expected.search(&key)),
+                _ => {
+                    tree.insert_near(&mut finger, key);
+                    expected.insert(key);
+                }
+            }
+            assert_eq!(tree.validate(), Ok(()));
+        }
+        assert_eq!(tree.len(), expected.len());
+        assert_eq!(format!("{}", tree), format!("{}", expected));
+    }
+
+    #[test]
+    fn batch_outcomes() {
+        let mut tree: BTree<u32, 3> = MultiTree::new();
+        let mut expected: AVLTree<u32> = AVLTree::new();
+
+        for key in (0..100).step_by(2) {
+            tree.insert(key);
+            expected.insert(key);
+        }
+        let batch = || (0..100).rev().map(|key| if key % 3 == 0 { Op::Delete(key) } else { Op::Insert(key) });
+        let outcomes = tree.apply_batch(batch());
+        assert_eq!(outcomes, expected.apply_batch(batch()));
+        assert_eq!(outcomes[1], OpOutcome::Exists);
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(format!("{}", tree), format!("{}", expected));
+    }
+
+    #[test]
+    fn lazy_revive() {
+        let mut tree: BTree<u32, 4> = MultiTree::with_lazy_delete(1.0);
+
+        for key in 0..50 {
+            tree.insert(key);
+        }
+        let height = tree.height();
+        for key in (0..50).step_by(2) {
+            assert_eq!(tree.delete(&key), Some(key));
+            assert_eq!(tree.delete(&key), None);
+        }
+        assert_eq!((tree.len(), tree.tombstones()), (25, 25));
+        assert_eq!(tree.height(), height);
+        assert!(!tree.search(&10) && tree.search(&11));
+
+        // Inserting a deleted key brings it back without adding a key
+        tree.insert(10);
+        assert!(tree.search(&10));
+        assert_eq!((tree.len(), tree.tombstones()), (26, 24));
+        assert_eq!(tree.validate(), Ok(()));
+
+        // Disabling lazy deletion removes the tombstones
+        tree.set_lazy_delete(None);
+        assert_eq!((tree.len(), tree.tombstones()), (26, 0));
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.delete(&12), None);
+        assert_eq!(tree.delete(&11), Some(11));
+    }
+
+    #[test]
+    fn seeded() {
+        let build = |seed| {
+            let mut tree: MultiTree<u32, CoinSplit> = MultiTree::with_seed(seed);
+            let mut finger = MultiFinger::new();
+            for key in 0..200 {
+                tree.insert_near(&mut finger, (key * 37) % 200);
+                assert_eq!(tree.validate(), Ok(()));
+            }
+            assert!(tree.search_mut(&74));
+            format!("{:?}", tree)
+        };
+        assert_eq!(build(3), build(3));
+        assert_ne!(build(3), build(4));
+    }
+
+    #[test]
+    fn try_errors() {
+        let mut tree: MultiTree<u32, Broken> = MultiTree::new();
+
+        assert_eq!(tree.try_insert(1), Ok(()));
+        assert_eq!(tree.try_insert(2), Err(InspectError::MissingIndex(10)));
+        // The key is still inserted
+        assert!(tree.search(&2));
+        // Deleting from a leaf root does not rebalance
+        assert_eq!(tree.try_delete(&2), Ok(Some(2)));
+        assert_eq!(tree.try_delete(&2), Ok(None));
+    }
+
+    #[test]
+    #[should_panic(expected = "Inspected node has no child at 10")]
+    fn insert_panics() {
+        let mut tree: MultiTree<u32, Broken> = MultiTree::new();
+
+        tree.insert(1);
+        tree.insert(2);
+    }
+}
@@ -0,0 +1,210 @@
+use super::node::MultiNode;
+use crate::tree::TreeRng;
+use crate::tree::inspect::InspectError;
+
+/// Trait that provides the balancing instructions for a [MultiTree](super::MultiTree)
+///
+/// The tree calls into the balance on the way down while searching for where to insert a key,
+/// and on the way back up after a key is inserted or removed below a node.  Each call is given
+/// a [MultiInspector] for the node which can split, merge or shift keys between its children.
+/// The tree itself only ever adds keys to and removes keys from leaves.
+pub trait MultiBalance {
+    /// Largest number of keys a node may hold once an operation has finished
+    const MAX_KEYS: usize;
+
+    /// Smallest number of keys a node other than the root may hold once an operation has finished
+    const MIN_KEYS: usize;
+
+    /// Prepares a node before descending into its child at `index` while searching for where to insert a key
+    ///
+    /// The root is given a temporary parent without keys so that it can be split like any other child.
+    /// The search carries on from the node afterwards, so the balance may restructure its children.
+    /// By default nothing is done.
+    fn before_descend<T: Ord>(_inspector: &mut MultiInspector<T>, _index: usize) {}
+
+    /// Rebalances a node after a key was inserted into the tree rooted by its child at `index`
+    ///
+    /// As with [MultiBalance::before_descend] the root is given a temporary parent so it can be split.
+    fn rebalance_insert<T: Ord>(inspector: &mut MultiInspector<T>, index: usize);
+
+    /// Rebalances a node after a key was removed from the tree rooted by its child at `index`
+    ///
+    /// If the root is left without keys its only child becomes the root.
+    fn rebalance_delete<T: Ord>(inspector: &mut MultiInspector<T>, index: usize);
+
+    /// Restructures a node after [MultiTree::search_mut](super::MultiTree::search_mut) searched
+    /// the tree rooted by its child at `index`
+    ///
+    /// Called for each node above the node the search ended at, from the bottom up.
+    /// By default nothing is done.
+    fn on_access<T: Ord>(_inspector: &mut MultiInspector<T>, _index: usize) {}
+}
+
+/// Provides access to a node of a [MultiTree](super::MultiTree) and the operations that restructure its children
+///
+/// Each operation panics if it cannot take place, the `try_` versions return an error instead.
+/// A balance can stop rebalancing with an error using [MultiInspector::fail].
+pub struct MultiInspector<'a, T: Ord> {
+    /// Node being inspected
+    node: &'a mut MultiNode<T>,
+    /// Random number generator of the tree
+    rng: &'a mut TreeRng,
+    /// Number of splits performed through the inspector
+    splits: usize,
+    /// Number of merges performed through the inspector
+    merges: usize,
+    /// Number of keys shifted between children through the inspector
+    shifts: usize,
+    /// Error the balance stopped with, if any
+    error: Option<InspectError>
+}
+
+impl <'a, T: Ord> MultiInspector<'a, T> {
+    /// Creates an inspector for the given node
+    pub(super) fn open(node: &'a mut MultiNode<T>, rng: &'a mut TreeRng) -> Self {
+        MultiInspector {
+            node,
+            rng,
+            splits: 0,
+            merges: 0,
+            shifts: 0,
+            error: None
+        }
+    }
+
+    /// Returns the number of splits, merges and shifts performed through the inspector,
+    /// and the error the balance stopped with
+    pub(super) fn into_counts(self) -> (usize, usize, usize, Option<InspectError>) {
+        (self.splits, self.merges, self.shifts, self.error)
+    }
+
+    /// Stops rebalancing with the given error
+    ///
+    /// The nodes above are not rebalanced.  The error is returned by [MultiTree::try_insert](super::MultiTree::try_insert)
+    /// and [MultiTree::try_delete](super::MultiTree::try_delete) (the other operations panic with it).
+    pub fn fail(&mut self, error: InspectError) {
+        self.error.get_or_insert(error);
+    }
+
+    /// Returns the next random number from the tree's generator
+    ///
+    /// Trees created with the same seed and given the same operations
+    /// draw the same numbers (see [MultiTree::with_seed](super::MultiTree::with_seed))
+    pub fn random(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    /// Returns the number of keys held by the node
+    pub fn len(&self) -> usize {
+        self.node.keys().len()
+    }
+
+    /// Returns true if the node holds no keys
+    ///
+    /// Only the temporary parent given to the root is ever empty
+    pub fn is_empty(&self) -> bool {
+        self.node.keys().is_empty()
+    }
+
+    /// Returns true if the node has no children
+    pub fn is_leaf(&self) -> bool {
+        self.node.is_leaf()
+    }
+
+    /// Returns the number of keys held by the child at the given index, if there is one
+    pub fn child_len(&self, index: usize) -> Option<usize> {
+        self.node.child(index).map(|child| child.keys().len())
+    }
+
+    /// Splits the child at the given index around its middle key
+    ///
+    /// The middle key moves up into the node, and the keys after it move into a new child
+    /// placed after the split child.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if there is no child at the given index or it holds no keys
+    pub fn split(&mut self, index: usize) {
+        self.try_split(index).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Splits the child at the given index around its middle key
+    ///
+    /// Behaves like [MultiInspector::split] but returns an error instead of panicking
+    pub fn try_split(&mut self, index: usize) -> Result<(), InspectError> {
+        if self.child_len(index).is_none_or(|len| len == 0) {
+            return Err(InspectError::MissingIndex(index))
+        }
+        self.node.split_child(index);
+        self.splits += 1;
+        Ok(())
+    }
+
+    /// Merges the child at the given index, the key between it and the next child, and the next child
+    ///
+    /// # Panics
+    ///
+    /// This function panics if there is no child after the given index
+    pub fn merge(&mut self, index: usize) {
+        self.try_merge(index).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Merges the child at the given index, the key between it and the next child, and the next child
+    ///
+    /// Behaves like [MultiInspector::merge] but returns an error instead of panicking
+    pub fn try_merge(&mut self, index: usize) -> Result<(), InspectError> {
+        if self.child_len(index + 1).is_none() {
+            return Err(InspectError::MissingIndex(index + 1))
+        }
+        self.node.merge_children(index);
+        self.merges += 1;
+        Ok(())
+    }
+
+    /// Moves the largest key of the child at the given index into the node, and the key
+    /// of the node between the child and the next child into the next child
+    ///
+    /// # Panics
+    ///
+    /// This function panics if there is no child after the given index or the child holds no keys
+    pub fn shift_right(&mut self, index: usize) {
+        self.try_shift_right(index).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Moves the largest key of the child at the given index into the next child through the node
+    ///
+    /// Behaves like [MultiInspector::shift_right] but returns an error instead of panicking
+    pub fn try_shift_right(&mut self, index: usize) -> Result<(), InspectError> {
+        if self.child_len(index).is_none_or(|len| len == 0) {
+            return Err(InspectError::MissingIndex(index))
+        }
+        if self.child_len(index + 1).is_none() {
+            return Err(InspectError::MissingIndex(index + 1))
+        }
+        self.node.shift_right(index);
+        self.shifts += 1;
+        Ok(())
+    }
+
+    /// Moves the smallest key of the child after the given index into the node, and the key
+    /// of the node between the children into the child at the given index
+    ///
+    /// # Panics
+    ///
+    /// This function panics if there is no child after the given index or it holds no keys
+    pub fn shift_left(&mut self, index: usize) {
+        self.try_shift_left(index).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Moves the smallest key of the child after the given index into the child at the index through the node
+    ///
+    /// Behaves like [MultiInspector::shift_left] but returns an error instead of panicking
+    pub fn try_shift_left(&mut self, index: usize) -> Result<(), InspectError> {
+        if self.child_len(index + 1).is_none_or(|len| len == 0) {
+            return Err(InspectError::MissingIndex(index + 1))
+        }
+        self.node.shift_left(index);
+        self.shifts += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display};
+
+/// A node in a multiway tree structure
+///
+/// Holds one or more keys in order.  A node with `n` keys is either a leaf (no children)
+/// or has `n + 1` children, where the child at index `i` holds the keys between
+/// the keys at index `i - 1` and `i`
+pub struct MultiNode<T: Ord> {
+    /// Keys of the node in order
+    keys: Vec<T>,
+    /// Whether the key at the same index has been lazily deleted
+    tombstones: Vec<bool>,
+    /// Children of the node, empty for a leaf
+    children: Vec<MultiNode<T>>
+}
+
+impl <T: Ord> MultiNode<T> {
+    /// Constructs a new leaf holding the given key
+    pub fn new_with(key: T) -> Self {
+        MultiNode {
+            keys: alloc::vec![key],
+            tombstones: alloc::vec![false],
+            children: Vec::new()
+        }
+    }
+
+    /// Constructs a new node without keys above the given node
+    ///
+    /// Used to give the root a parent that can split it, the parent must be given a key
+    /// (by splitting the child) or be unwrapped with [MultiNode::into_only_child]
+    pub fn new_above(child: MultiNode<T>) -> Self {
+        MultiNode {
+            keys: Vec::new(),
+            tombstones: Vec::new(),
+            children: alloc::vec![child]
+        }
+    }
+
+    /// Returns the child of a node without keys, or the node itself if it has keys
+    pub fn into_only_child(mut self) -> Self {
+        if self.keys.is_empty() && self.children.len() == 1 {
+            self.children.pop().unwrap()
+        } else {
+            self
+        }
+    }
+
+    /// Searches the keys of the node
+    ///
+    /// Returns the index of the key if it is held by this node,
+    /// otherwise the index of the child that would hold it
+    pub fn search(&self, key: &T) -> Result<usize, usize> {
+        self.keys.binary_search(key)
+    }
+
+    /// Returns the keys of the node
+    pub fn keys(&self) -> &[T] {
+        &self.keys
+    }
+
+    /// Returns true if the key at the given index has been lazily deleted
+    pub fn is_tombstone(&self, index: usize) -> bool {
+        self.tombstones[index]
+    }
+
+    /// Marks (or unmarks) the key at the given index as lazily deleted
+    pub fn set_tombstone(&mut self, index: usize, tombstone: bool) {
+        self.tombstones[index] = tombstone;
+    }
+
+    /// Returns true if the node has no children
+    pub fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Returns the children of the node
+    pub fn children(&self) -> &[MultiNode<T>] {
+        &self.children
+    }
+
+    /// Returns the child at the given index
+    pub fn child(&self, index: usize) -> Option<&MultiNode<T>> {
+        self.children.get(index)
+    }
+
+    /// Returns the child at the given index as a mutable reference
+    pub fn child_mut(&mut self, index: usize) -> Option<&mut MultiNode<T>> {
+        self.children.get_mut(index)
+    }
+
+    /// Inserts a key at the given index of a leaf
+    pub fn insert_key(&mut self, index: usize, key: T) {
+        debug_assert!(self.is_leaf());
+        self.keys.insert(index, key);
+        self.tombstones.insert(index, false);
+    }
+
+    /// Removes the key at the given index of a leaf, along with whether it was lazily deleted
+    pub fn remove_key(&mut self, index: usize) -> (T, bool) {
+        debug_assert!(self.is_leaf());
+        (self.keys.remove(index), self.tombstones.remove(index))
+    }
+
+    /// Replaces the key at the given index and whether it is lazily deleted, returning the old ones
+    pub fn replace_key(&mut self, index: usize, key: (T, bool)) -> (T, bool) {
+        (
+            core::mem::replace(&mut self.keys[index], key.0),
+            core::mem::replace(&mut self.tombstones[index], key.1)
+        )
+    }
+
+    /// Removes the largest key of a leaf, along with whether it was lazily deleted
+    pub fn pop_key(&mut self) -> Option<(T, bool)> {
+        debug_assert!(self.is_leaf());
+        self.keys.pop().zip(self.tombstones.pop())
+    }
+
+    /// Moves the keys of the tree rooted by this node into the given vector in order,
+    /// along with whether they were lazily deleted
+    pub fn drain_into(self, keys: &mut Vec<(T, bool)>) {
+        let mut children = self.children.into_iter();
+        for key in self.keys.into_iter().zip(self.tombstones) {
+            if let Some(child) = children.next() {
+                child.drain_into(keys);
+            }
+            keys.push(key);
+        }
+        if let Some(child) = children.next() {
+            child.drain_into(keys);
+        }
+    }
+
+    /// Splits the child at the given index around its middle key
+    ///
+    /// The middle key moves up into this node, with the keys (and children)
+    /// after it moving into a new child to the right of the split child
+    pub fn split_child(&mut self, index: usize) {
+        let child = &mut self.children[index];
+        let mid = child.keys.len() / 2;
+        let keys = child.keys.split_off(mid + 1);
+        let tombstones = child.tombstones.split_off(mid + 1);
+        let children = if child.is_leaf() {
+            Vec::new()
+        } else {
+            child.children.split_off(mid + 1)
+        };
+        let key = child.keys.pop().unwrap();
+        let tombstone = child.tombstones.pop().unwrap();
+        self.keys.insert(index, key);
+        self.tombstones.insert(index, tombstone);
+        self.children.insert(index + 1, MultiNode { keys, tombstones, children });
+    }
+
+    /// Merges the child at the given index, the key after it and the next child into one child
+    pub fn merge_children(&mut self, index: usize) {
+        let key = self.keys.remove(index);
+        let tombstone = self.tombstones.remove(index);
+        let MultiNode { keys, tombstones, children } = self.children.remove(index + 1);
+        let child = &mut self.children[index];
+        child.keys.push(key);
+        child.keys.extend(keys);
+        child.tombstones.push(tombstone);
+        child.tombstones.extend(tombstones);
+        child.children.extend(children);
+    }
+
+    /// Moves a key from the child at the given index into the next child through this node
+    pub fn shift_right(&mut self, index: usize) {
+        let (left, right) = self.children.split_at_mut(index + 1);
+        let (from, to) = (&mut left[index], &mut right[0]);
+        let key = core::mem::replace(&mut self.keys[index], from.keys.pop().unwrap());
+        let tombstone = core::mem::replace(&mut self.tombstones[index], from.tombstones.pop().unwrap());
+        to.keys.insert(0, key);
+        to.tombstones.insert(0, tombstone);
+        if let Some(child) = from.children.pop() {
+            to.children.insert(0, child);
+        }
+    }
+
+    /// Moves a key from the child after the given index into the child at the index through this node
+    pub fn shift_left(&mut self, index: usize) {
+        let (left, right) = self.children.split_at_mut(index + 1);
+        let (to, from) = (&mut left[index], &mut right[0]);
+        let key = core::mem::replace(&mut self.keys[index], from.keys.remove(0));
+        let tombstone = core::mem::replace(&mut self.tombstones[index], from.tombstones.remove(0));
+        to.keys.push(key);
+        to.tombstones.push(tombstone);
+        if !from.is_leaf() {
+            to.children.push(from.children.remove(0));
+        }
+    }
+
+    /// Computes the height of the tree rooted by this node
+    ///
+    /// Every leaf of a multiway tree is at the same depth, so only the leftmost path is followed
+    pub fn height(&self) -> usize {
+        1 + self.children.first().map_or(0, |child| child.height())
+    }
+
+    /// Computes the number of leaves of the tree rooted by this node
+    pub fn leaves(&self) -> usize {
+        if self.is_leaf() {
+            1
+        } else {
+            self.children.iter().map(|child| child.leaves()).sum()
+        }
+    }
+}
+
+impl <T> Display for MultiNode<T>
+where
+    T: Ord + Display
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        for (i, key) in self.keys.iter().enumerate() {
+            if let Some(child) = self.children.get(i) {
+                Display::fmt(child, f)?;
+            }
+            if !self.tombstones[i] {
+                write!(f, "{}, ", key)?;
+            }
+        }
+        match self.children.last() {
+            Some(child) => Display::fmt(child, f),
+            None => Ok(())
+        }
+    }
+}
+
+impl <T> Debug for MultiNode<T>
+where
+    T: Ord + Debug
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        let mut builder = f.debug_struct(&alloc::format!("{:?}", &self.keys));
+        if self.tombstones.contains(&true) {
+            let tombstones: Vec<&T> = self.keys.iter().zip(&self.tombstones)
+                .filter_map(|(key, &tombstone)| tombstone.then_some(key))
+                .collect();
+            builder.field("tombstones", &tombstones);
+        }
+        if !self.is_leaf() {
+            builder.field("children", &self.children);
+        }
+        builder.finish()
+    }
+}
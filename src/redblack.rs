@@ -1,12 +1,12 @@
-use std::fmt::Debug;
+use core::fmt::Debug;
 
-use crate::tree::TreePath;
+use crate::tree::{Tree, TreePath};
 use crate::tree::inspect::*;
 
 use RBColor::*;
 /// Node colors
 #[derive(Clone, Copy, PartialEq, Debug)]
-enum RBColor {
+pub enum RBColor {
     Red,
     Black
 }
@@ -15,131 +15,335 @@ enum RBColor {
 /// that effectively converts a [Tree] into into an Red Black Tree
 pub struct RedBlackBalance(RBColor);
 impl TreeBalance for RedBlackBalance {
-    fn rebalance_insert<T: Ord>(mut node: NodeInspector<T, Self>, path: (TreePath, TreePath)) -> TreePosition<T, Self> {
-
-        // Get the parent and uncle colors
-        let (xcolor, pcolor, ucolor) = {
-            let pnode = node.inspect_child(path.0).unwrap();
-            (
-                pnode.inspect_child(path.1).map_or(Black, |n| n.inspect_balance(|b| b.0)),
-                pnode.inspect_balance(|b| b.0),
-                node.inspect_child(path.0.reflect()).map_or(Black, |n| n.inspect_balance(|b| b.0))
-            )
-        };
+    type State = RBColor;
+    type Cache = ();
 
-        // Handle case when we have recolored and we need to make it to the next parent
-        if node.inspect_balance(|b| b.0) == Red && pcolor == Red {
-            node.into_position(NodeOffset::Parent)
-        } else if xcolor == Black { // Don't have double red case
-            node.into_position(NodeOffset::Root)
-        } else {
-            match (pcolor, ucolor) {
-                // Perform recoloring
-                (Red, Red) => {
-                    {
-                        node.inspect_child(TreePath::Right).unwrap().update_balance(|b| b.0 = Black);
-                    }
-                    {
-                        node.inspect_child(TreePath::Left).unwrap().update_balance(|b| b.0 = Black); }
-                    {
-                        if !node.inspect_is_root() {
-                            node.update_balance(|b| b.0 = Red);
-                        }
-                    }
-                    node.into_position(NodeOffset::Parent)
-                },
-                // Perform rotation
-                (Red, Black) => {
-                    node = node.rotate(path);
-                    { node.inspect_child(path.0.reflect()).unwrap().update_balance(|b| b.0 = Red);}
-                    { node.update_balance(|b| b.0 = Black); }
-                    node.into_position(NodeOffset::Root)
-                },
-                // Do nothing
-                (Black, _) => node.into_position(NodeOffset::Root)
+    fn rebalance_insert<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, path: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
+        colored_insert(node, path)
+    }
+
+    fn new<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+        RedBlackBalance (Red)
+    }
+
+    fn new_root<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+        RedBlackBalance (Black)
+    }
+
+    fn new_rebuilt(height: usize, parent_height: Option<usize>) -> Option<Self> {
+        Some(RedBlackBalance (rebuilt_color(height, parent_height)))
+    }
+
+    fn rebalance_delete<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, xpath: TreePath, popped_balance: &Self) -> TreePosition<T, Self, A> {
+        colored_delete(node, xpath, popped_balance)
+    }
+
+    fn verify<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Result<(), ValidationError> {
+        colored_verify(node)
+    }
+
+    fn state<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Self::State {
+        node.inspect_balance(|b| b.0)
+    }
+
+    fn adjust_root(&mut self) {
+        self.0 = Black
+    }
+}
+
+impl Debug for RedBlackBalance {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+/// Implementation of a balance for a binary tree that effectively converts a [Tree]
+/// into a Red Black Tree which is rebalanced from the top down on insertion
+/// 
+/// While searching for the insert position any node with two red children is split
+/// (recolored red with black children), rotating if this causes a double red.  This leaves
+/// at most a single rotation to do once the key is inserted, rather than recoloring up the tree.
+/// While searching for the key to delete (and its successor) each node is made red before moving
+/// down to it, by recoloring with or rotating in a red node from its sibling, so the node finally
+/// removed is a red leaf and nothing is left to fix afterwards.
+pub struct TopDownRedBlackBalance(RBColor);
+impl TreeBalance for TopDownRedBlackBalance {
+    type State = RBColor;
+    type Cache = ();
+
+    fn rebalance_insert<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, path: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
+        // The split on the way down guarantees the uncle is black so this is at most a rotation
+        colored_insert(node, path)
+    }
+
+    fn before_descend<T: Ord, A: Augment<T>>(mut node: NodeInspector<T, Self, A>, path: (TreePath, Option<TreePath>), op: Descent) -> NodeInspector<T, Self, A> {
+        if op != Descent::Insert {
+            return redden(node, path.0);
+        }
+        let (ppath, xpath) = path;
+
+        // The root and its child cannot cause a double red with a parent, so just split them
+        if node.inspect_is_root() {
+            if red_children(&node) {
+                node.inspect_child(TreePath::Left).unwrap().update_balance(|b| b.0 = Black);
+                node.inspect_child(TreePath::Right).unwrap().update_balance(|b| b.0 = Black);
+            }
+            let split = red_children(&node.inspect_child(ppath).unwrap());
+            if split {
+                let mut pnode = node.inspect_child(ppath).unwrap();
+                { pnode.inspect_child(TreePath::Left).unwrap().update_balance(|b| b.0 = Black); }
+                { pnode.inspect_child(TreePath::Right).unwrap().update_balance(|b| b.0 = Black); }
+                pnode.update_balance(|b| b.0 = Red);
             }
         }
 
+        // Check if the grandchild on the search path needs to be split
+        let xpath = match xpath {
+            Some(xpath) => xpath,
+            None => return node
+        };
+        let split = node.inspect_child(ppath).unwrap().inspect_child(xpath).is_some_and(|x| red_children(&x));
+        if !split {
+            return node;
+        }
+        let pcolor = {
+            let pnode = node.inspect_child(ppath).unwrap();
+            let mut xnode = pnode.inspect_child(xpath).unwrap();
+            { xnode.inspect_child(TreePath::Left).unwrap().update_balance(|b| b.0 = Black); }
+            { xnode.inspect_child(TreePath::Right).unwrap().update_balance(|b| b.0 = Black); }
+            xnode.update_balance(|b| b.0 = Red);
+            pnode.inspect_balance(|b| b.0)
+        };
+
+        // Resolve a double red caused by the split
+        if pcolor == Red {
+            node = node.rotate((ppath, xpath));
+            { node.inspect_child(TreePath::Left).unwrap().update_balance(|b| b.0 = Red); }
+            { node.inspect_child(TreePath::Right).unwrap().update_balance(|b| b.0 = Red); }
+            node.update_balance(|b| b.0 = Black);
+        }
+        node
     }
 
-    fn new() -> Self {
-        RedBlackBalance (Red)
+    fn new<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+        TopDownRedBlackBalance (Red)
     }
 
-    fn new_root() -> Self {
-        RedBlackBalance (Black)
+    fn new_root<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+        TopDownRedBlackBalance (Black)
+    }
+
+    fn new_rebuilt(height: usize, parent_height: Option<usize>) -> Option<Self> {
+        Some(TopDownRedBlackBalance (rebuilt_color(height, parent_height)))
+    }
+
+    fn rebalance_delete<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, xpath: TreePath, popped_balance: &Self) -> TreePosition<T, Self, A> {
+        // The search leaves a red leaf to remove, or a black node with a red child which is recolored in its place.
+        // A search started from a finger below the root skips the nodes above it, so fall back to fixing from
+        // the bottom up in case a black node was removed anyway
+        colored_delete(node, xpath, popped_balance)
+    }
+
+    fn verify<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Result<(), ValidationError> {
+        colored_verify(node)
+    }
+
+    fn state<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Self::State {
+        node.inspect_balance(|b| b.0)
+    }
+
+    fn adjust_root(&mut self) {
+        self.0 = Black
+    }
+}
+
+/// Makes the child on the given path red (or leaves it a red child) before a deletion moves down to it
+///
+/// A black child with a red sibling has the sibling rotated up, which leaves the node red above
+/// the child to be fixed when the search continues from the new top.  A black child with black
+/// children under a red node (or the root) either merges with its sibling by recoloring, or borrows
+/// a red child from its sibling with a single or double rotation.
+fn redden<T: Ord, A: Augment<T>>(mut node: NodeInspector<T, TopDownRedBlackBalance, A>, cpath: TreePath) -> NodeInspector<T, TopDownRedBlackBalance, A> {
+    let color = |n: Option<BranchInspector<T, TopDownRedBlackBalance, A>>| n.map_or(Black, |n| n.inspect_balance(|b| b.0));
+    let spath = cpath.reflect();
+    let (ccolor, cred) = {
+        let cnode = node.inspect_child(cpath).unwrap();
+        let cred = color(cnode.inspect_child(TreePath::Left)) == Red || color(cnode.inspect_child(TreePath::Right)) == Red;
+        (cnode.inspect_balance(|b| b.0), cred)
+    };
+    if ccolor == Red || cred {
+        return node;
+    }
+    let root = node.inspect_is_root();
+    let ncolor = node.inspect_balance(|b| b.0);
+    if ncolor == Black && color(node.inspect_child(spath)) == Red {
+        node = node.rotate((spath, spath));
+        node.update_balance(|b| b.0 = Black);
+        node.inspect_child(cpath).unwrap().update_balance(|b| b.0 = Red);
+        return node;
+    }
+    if ncolor == Black && !root {
+        return node;
+    }
+
+    // The node is red (or the root, which can be treated as red)
+    let (outer, inner) = {
+        let snode = node.inspect_child(spath).unwrap();
+        (color(snode.inspect_child(spath)), color(snode.inspect_child(cpath)))
+    };
+    if outer == Black && inner == Black {
+        node.update_balance(|b| b.0 = Black);
+        { node.inspect_child(cpath).unwrap().update_balance(|b| b.0 = Red); }
+        { node.inspect_child(spath).unwrap().update_balance(|b| b.0 = Red); }
+        return node;
+    }
+    node = if outer == Red {
+        node.rotate((spath, spath))
+    } else {
+        node.rotate((spath, cpath))
+    };
+    node.update_balance(|b| b.0 = if root { Black } else { Red });
+    { node.inspect_child(spath).unwrap().update_balance(|b| b.0 = Black); }
+    let mut nnode = node.inspect_child(cpath).unwrap();
+    nnode.update_balance(|b| b.0 = Black);
+    nnode.inspect_child(cpath).unwrap().update_balance(|b| b.0 = Red);
+    drop(nnode);
+    node
+}
+
+impl Debug for TopDownRedBlackBalance {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+/// Implementation of a balance for a binary tree that effectively converts a [Tree]
+/// into a Left Leaning Red Black Tree
+/// 
+/// Red nodes are only ever left children, so each black node and its red left child (if any)
+/// form a node of a 2-3 tree.  Insertions are fixed on the way back up with the same three steps
+/// at every node (rotate a red right child left, rotate two reds in a row right, then split a node
+/// with two red children), and deletions borrow from or merge with a sibling like a 2-3 tree.
+pub struct LeftLeaningRedBlackBalance(RBColor);
+impl TreeBalance for LeftLeaningRedBlackBalance {
+    type State = RBColor;
+    type Cache = ();
+
+    fn rebalance_insert<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, path: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
+        // Each node on the insertion path is fixed by its parent, since fixing this node could bring
+        // the inserted node up to be a child of the next node which stops rebalancing.
+        // Splitting a node can push a red link up any number of levels, so this continues until neither
+        // the child nor this node needed fixing (nothing above can have changed), or to the root
+        // (which is fixed by itself and kept black).
+        let fixed = unlean(lean_colors_at(&node, &[path.0]));
+        let mut node = lean(node, &[path.0]);
+        if !fixed && !unlean(lean_colors(&node)) {
+            return node.into_position(NodeOffset::Root);
+        }
+        if node.inspect_is_root() {
+            node = lean(node, &[]);
+            node.update_balance(|b| b.0 = Black);
+        }
+        node.into_position(NodeOffset::Parent)
+    }
+
+    fn new<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+        LeftLeaningRedBlackBalance (Red)
     }
 
-    fn rebalance_delete<T: Ord>(mut node: NodeInspector<T, Self>, xpath: TreePath, popped_balance: &Self) -> TreePosition<T, Self> {
-        // Check if the node removed was black, if not we don't need to investigate further
+    fn new_root<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+        LeftLeaningRedBlackBalance (Black)
+    }
+
+    fn rebalance_delete<T: Ord, A: Augment<T>>(mut node: NodeInspector<T, Self, A>, xpath: TreePath, popped_balance: &Self) -> TreePosition<T, Self, A> {
+        // Removing a red node (always a leaf) leaves the black heights unchanged
         if popped_balance.0 == Red {
             return node.into_position(NodeOffset::Root);
         }
-        let spath = xpath.reflect(); // sibling path
-
-        // Get the color of the node in the direction of the deletion,
-        // the color of the sibling node, and (if any) the position of the sibling's red child
-        let (xcolor, scolor, vpath) = {
-            let snode = node.inspect_child(spath);
-            let scolor = snode.as_ref().map_or(Black, |n| n.inspect_balance(|b| b.0));
-            let vpath = snode.map(|snode| {
-                let inline_color = snode.inspect_child(spath).map_or(Black, |n| n.inspect_balance(|b| b.0));
-                let elbow_color = snode.inspect_child(xpath).map_or(Black, |n| n.inspect_balance(|b| b.0));
-                // Prioritize the outermost child as if both children are red
-                // using the outermost child prevents an extra rotation
-                if inline_color == Red {
-                    Some(spath)
-                } else if elbow_color == Red {
-                    Some(xpath)
-                } else {
-                    None
-                }
-            }).flatten();
-            (
-                node.inspect_child(xpath).map_or(Black, |n| n.inspect_balance(|b| b.0)),
-                scolor,
-                vpath
-            )
+        // A red child replacing the removed node takes its place in the black height
+        let replaced = match node.inspect_child(xpath) {
+            Some(mut xnode) if xnode.inspect_balance(|b| b.0) == Red => {
+                xnode.update_balance(|b| b.0 = Black);
+                true
+            },
+            _ => false
         };
+        if replaced {
+            return node.into_position(NodeOffset::Root);
+        }
 
-        // If the node in the direction of the deletion is black
-        // that means we have a double black case
-        if xcolor == Black {
-            // If the sibling is also black we 
-            if scolor == Black {
-                // Get the parent's color (for later use)
-                let pcolor = node.inspect_balance(|b| b.0);
-                if let Some(path) = vpath {
-                    // Perform rotation
-                    node = node.rotate((spath, path));
-                    node.inspect_child(TreePath::Right).unwrap().update_balance(|b| b.0 = Black);
-                    node.inspect_child(TreePath::Left).unwrap().update_balance(|b| b.0 = Black);
-                    node.update_balance(|b| b.0 = pcolor);
-                    node.into_position(NodeOffset::Root)
+        // The subtree on the path is now one black node short of its sibling
+        let color = node.inspect_balance(|b| b.0);
+        let (scolor, slcolor, srlcolor) = {
+            let snode = node.inspect_child(xpath.reflect()).unwrap();
+            let color = |n: Option<BranchInspector<T, Self, A>>| n.map_or(Black, |n| n.inspect_balance(|b| b.0));
+            let srlcolor = snode.inspect_child(TreePath::Right).map_or(Black, |n| color(n.inspect_child(TreePath::Left)));
+            (snode.inspect_balance(|b| b.0), color(snode.inspect_child(TreePath::Left)), srlcolor)
+        };
+        match (xpath, scolor, slcolor) {
+            // Borrow from the right sibling's red left child
+            (TreePath::Left, _, Red) => {
+                node = node.rotate((TreePath::Right, TreePath::Left));
+                node.update_balance(|b| b.0 = color);
+                { node.inspect_child(TreePath::Left).unwrap().update_balance(|b| b.0 = Black); }
+                { node.inspect_child(TreePath::Right).unwrap().update_balance(|b| b.0 = Black); }
+                node.into_position(NodeOffset::Root)
+            },
+            // Merge with the right sibling, which is rotated up to keep the red link on the left
+            (TreePath::Left, _, Black) => {
+                node = node.rotate((TreePath::Right, TreePath::Right));
+                node.update_balance(|b| b.0 = Black);
+                { node.inspect_child(TreePath::Left).unwrap().update_balance(|b| b.0 = Red); }
+                match color {
+                    Red => node.into_position(NodeOffset::Root),
+                    Black => node.into_position(NodeOffset::Parent)
+                }
+            },
+            // The left sibling is part of this node, so borrow from or merge with its right child
+            (TreePath::Right, Red, _) => {
+                if srlcolor == Red {
+                    node = node.rotate((TreePath::Left, TreePath::Right));
+                    let snode = node.inspect_child(TreePath::Left).unwrap();
+                    { snode.inspect_child(TreePath::Right).unwrap().update_balance(|b| b.0 = Black); }
                 } else {
-                    // Recolor, checking if we have another double black
-                    node.inspect_child(spath).map(|mut n| n.update_balance(|b| b.0 = Red));
-                    match pcolor {
-                        Black => node.into_position(NodeOffset::Parent),
-                        Red => {
-                            node.update_balance(|b| b.0 = Black);
-                            node.into_position(NodeOffset::Root)
-                        }
-                    }
+                    node = node.rotate((TreePath::Left, TreePath::Left));
+                    let pnode = node.inspect_child(TreePath::Right).unwrap();
+                    { pnode.inspect_child(TreePath::Left).unwrap().update_balance(|b| b.0 = Red); }
+                }
+                node.update_balance(|b| b.0 = Black);
+                node.into_position(NodeOffset::Root)
+            },
+            // Borrow from the left sibling's red left child
+            (TreePath::Right, Black, Red) => {
+                node = node.rotate((TreePath::Left, TreePath::Left));
+                node.update_balance(|b| b.0 = color);
+                { node.inspect_child(TreePath::Left).unwrap().update_balance(|b| b.0 = Black); }
+                { node.inspect_child(TreePath::Right).unwrap().update_balance(|b| b.0 = Black); }
+                node.into_position(NodeOffset::Root)
+            },
+            // Merge with the left sibling
+            (TreePath::Right, Black, Black) => {
+                { node.inspect_child(TreePath::Left).unwrap().update_balance(|b| b.0 = Red); }
+                match color {
+                    Red => {
+                        node.update_balance(|b| b.0 = Black);
+                        node.into_position(NodeOffset::Root)
+                    },
+                    Black => node.into_position(NodeOffset::Parent)
                 }
-            } else {
-                // Perform rotation and inspect the new parent of the double black child
-                node = node.rotate((spath, spath));
-                { node.inspect_child(xpath).unwrap().update_balance(|b| b.0 = Red);}
-                { node.update_balance(|b| b.0 = Black); }
-                node.into_position(NodeOffset::Child(xpath))
             }
-        } else {
-            // Nothing to do other than recolor and, return to root
-            { node.inspect_child(xpath).unwrap().update_balance(|b| b.0 = Black);}
-            node.into_position(NodeOffset::Root)
         }
-        
+    }
+
+    fn verify<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Result<(), ValidationError> {
+        if node.inspect_child(TreePath::Right).is_some_and(|n| n.inspect_balance(|b| b.0) == Red) {
+            return Err(ValidationError::Balance("Left leaning red black node has a red right child"))
+        }
+        colored_verify(node)
+    }
+
+    fn state<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Self::State {
+        node.inspect_balance(|b| b.0)
     }
 
     fn adjust_root(&mut self) {
@@ -147,16 +351,331 @@ impl TreeBalance for RedBlackBalance {
     }
 }
 
-impl Debug for RedBlackBalance {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+impl Debug for LeftLeaningRedBlackBalance {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
         write!(f, "{:?}", self.0)
     }
 }
 
+/// Returns the colors of the left child, right child and left grandchild of a node
+/// 
+/// Missing nodes are black
+fn lean_colors<'a, T: Ord + 'a, A: Augment<T> + 'a, I: InspectNode<'a, T, LeftLeaningRedBlackBalance, A>>(node: &'a I) -> (RBColor, RBColor, RBColor) {
+    let (left, left_left) = node.inspect_child(TreePath::Left).map_or((Black, Black), |n| (
+        n.inspect_balance(|b| b.0),
+        n.inspect_child(TreePath::Left).map_or(Black, |n| n.inspect_balance(|b| b.0))
+    ));
+    let right = node.inspect_child(TreePath::Right).map_or(Black, |n| n.inspect_balance(|b| b.0));
+    (left, right, left_left)
+}
+
+/// Checks if a node with the given colors (see [lean_colors]) needs to be leaned
+fn unlean((left, right, left_left): (RBColor, RBColor, RBColor)) -> bool {
+    (right == Red && left == Black) || (left == Red && (left_left == Red || right == Red))
+}
+
+/// Returns the colors (see [lean_colors]) of the node found by following the paths
+fn lean_colors_at<T: Ord, A: Augment<T>>(node: &NodeInspector<T, LeftLeaningRedBlackBalance, A>, paths: &[TreePath]) -> (RBColor, RBColor, RBColor) {
+    match *paths {
+        [] => lean_colors(node),
+        [path] => lean_colors(&node.inspect_child(path).unwrap()),
+        _ => unreachable!()
+    }
+}
+
+/// Paints the node found by following the paths
+fn paint_at<T: Ord, A: Augment<T>>(node: &mut NodeInspector<T, LeftLeaningRedBlackBalance, A>, paths: &[TreePath], color: RBColor) {
+    match *paths {
+        [] => node.update_balance(|b| b.0 = color),
+        [path] => node.inspect_child(path).unwrap().update_balance(|b| b.0 = color),
+        [path, next] => node.inspect_child(path).unwrap().inspect_child(next).unwrap().update_balance(|b| b.0 = color),
+        _ => unreachable!()
+    }
+}
+
+/// Rotates the red child in the given direction of the node found by following the paths up,
+/// swapping the colors of the two nodes
+fn rotate_red<T: Ord, A: Augment<T>>(node: NodeInspector<T, LeftLeaningRedBlackBalance, A>, paths: &[TreePath], direction: TreePath) -> NodeInspector<T, LeftLeaningRedBlackBalance, A> {
+    let color = match *paths {
+        [] => node.inspect_balance(|b| b.0),
+        [path] => node.inspect_child(path).unwrap().inspect_balance(|b| b.0),
+        _ => unreachable!()
+    };
+    let mut node = node.rotate_at(paths, direction);
+    paint_at(&mut node, paths, color);
+    paint_at(&mut node, &[paths, &[direction.reflect()]].concat(), Red);
+    node
+}
+
+/// Restores the left leaning invariants of the node found by following the paths after an insertion below it
+fn lean<T: Ord, A: Augment<T>>(mut node: NodeInspector<T, LeftLeaningRedBlackBalance, A>, paths: &[TreePath]) -> NodeInspector<T, LeftLeaningRedBlackBalance, A> {
+    let (left, right, _) = lean_colors_at(&node, paths);
+    if right == Red && left == Black {
+        node = rotate_red(node, paths, TreePath::Right);
+    }
+    let (left, _, left_left) = lean_colors_at(&node, paths);
+    if left == Red && left_left == Red {
+        node = rotate_red(node, paths, TreePath::Left);
+    }
+    let (left, right, _) = lean_colors_at(&node, paths);
+    if left == Red && right == Red {
+        paint_at(&mut node, &[paths, &[TreePath::Left]].concat(), Black);
+        paint_at(&mut node, &[paths, &[TreePath::Right]].concat(), Black);
+        paint_at(&mut node, paths, Red);
+    }
+    node
+}
+
+/// Checks if both children of a node are red
+fn red_children<'a, T: Ord + 'a, A: Augment<T> + 'a, B: Colored + 'a, I: InspectNode<'a, T, B, A>>(node: &'a I) -> bool {
+    let red = |path| node.inspect_child(path).is_some_and(|n| n.inspect_balance(|b| b.color()) == Red);
+    red(TreePath::Left) && red(TreePath::Right)
+}
+
+/// Checks the red black invariants at a node
+/// 
+/// The root must be black, a red node cannot have a red child and both subtrees must have the same black height.
+/// Since every node is checked, comparing the black heights down the leftmost path of each subtree is enough.
+fn colored_verify<T: Ord, A: Augment<T>, B: Colored>(node: &NodeInspector<T, B, A>) -> Result<(), ValidationError> {
+    let color = node.inspect_balance(|b| b.color());
+    if node.inspect_is_root() && color == Red {
+        return Err(ValidationError::Balance("Red black root is red"))
+    }
+    let red = |path| node.inspect_child(path).is_some_and(|n| n.inspect_balance(|b| b.color()) == Red);
+    if color == Red && (red(TreePath::Left) || red(TreePath::Right)) {
+        return Err(ValidationError::Balance("Red black node has a red parent"))
+    }
+    let black_height = |path| node.inspect_child(path).map_or(0, |n| leftmost_black_height(&n));
+    if black_height(TreePath::Left) != black_height(TreePath::Right) {
+        return Err(ValidationError::Balance("Red black subtrees have different black heights"))
+    }
+    Ok(())
+}
+
+/// Counts the black nodes down the leftmost path from a node
+fn leftmost_black_height<T: Ord, A: Augment<T>, B: Colored>(node: &BranchInspector<T, B, A>) -> usize {
+    let black = usize::from(node.inspect_balance(|b| b.color()) == Black);
+    black + node.inspect_child(TreePath::Left).map_or(0, |n| leftmost_black_height(&n))
+}
+
+/// Implements the queries specific to red black trees for trees using each of the given balances
+macro_rules! impl_black_height {
+    ($($balance:ident),*) => {
+        $(
+            impl <T: Ord, A: Augment<T>> Tree<T, $balance, A> {
+                /// Returns the black height of the tree
+                /// 
+                /// Counts the black nodes on any path from the root down to a missing child,
+                /// which is the same for every path.  Returns zero for an empty tree.
+                /// 
+                /// ```
+                #[doc = concat!("use project2::redblack::", stringify!($balance), ";")]
+                /// use project2::tree::Tree;
+                #[doc = concat!("let mut tree = Tree::<usize, ", stringify!($balance), ">::new();")]
+                /// assert_eq!(tree.black_height(), 0);
+                /// 
+                /// for key in 0..100 {
+                ///     tree.insert(key);
+                /// }
+                /// // No path has two red nodes in a row
+                /// assert!(tree.height() <= 2 * tree.black_height());
+                /// ```
+                pub fn black_height(&self) -> usize {
+                    self.inspect_root(|root| {
+                        let black = usize::from(root.inspect_balance(|b| b.color()) == Black);
+                        black + root.inspect_child(TreePath::Left).map_or(0, |n| leftmost_black_height(&n))
+                    }).unwrap_or(0)
+                }
+            }
+        )*
+    };
+}
+
+impl_black_height!(RedBlackBalance, TopDownRedBlackBalance, LeftLeaningRedBlackBalance);
+
+/// Returns the color for a node of a rebuilt tree
+fn rebuilt_color(height: usize, parent_height: Option<usize>) -> RBColor {
+    // A height balanced tree can always be colored by making the nodes
+    // with an odd height whose parent has an even height red
+    match parent_height {
+        Some(pheight) if height % 2 == 1 && pheight % 2 == 0 => Red,
+        _ => Black
+    }
+}
+
+/// Balance that stores a color for each node, allowing
+/// the rebalancing logic to be shared between the red black balances
+trait Colored: TreeBalance {
+    /// Returns the color of the node
+    fn color(&self) -> RBColor;
+
+    /// Sets the color of the node
+    fn paint(&mut self, color: RBColor);
+}
+
+impl Colored for RedBlackBalance {
+    fn color(&self) -> RBColor {
+        self.0
+    }
+
+    fn paint(&mut self, color: RBColor) {
+        self.0 = color
+    }
+}
+
+impl Colored for TopDownRedBlackBalance {
+    fn color(&self) -> RBColor {
+        self.0
+    }
+
+    fn paint(&mut self, color: RBColor) {
+        self.0 = color
+    }
+}
+
+impl Colored for LeftLeaningRedBlackBalance {
+    fn color(&self) -> RBColor {
+        self.0
+    }
+
+    fn paint(&mut self, color: RBColor) {
+        self.0 = color
+    }
+}
+
+/// Rebalances a red black tree from the bottom up after an insertion
+fn colored_insert<T: Ord, A: Augment<T>, B: Colored>(mut node: NodeInspector<T, B, A>, path: (TreePath, Option<TreePath>)) -> TreePosition<T, B, A> {
+    // A red node inserted below the root cannot be part of a double red, as the root is black
+    let path = match path {
+        (ppath, Some(xpath)) => (ppath, xpath),
+        (_, None) => return node.into_position(NodeOffset::Root)
+    };
+
+    // Get the parent and uncle colors
+    let (xcolor, pcolor, ucolor) = {
+        let pnode = node.inspect_child(path.0).unwrap();
+        (
+            pnode.inspect_child(path.1).map_or(Black, |n| n.inspect_balance(|b| b.color())),
+            pnode.inspect_balance(|b| b.color()),
+            node.inspect_child(path.0.reflect()).map_or(Black, |n| n.inspect_balance(|b| b.color()))
+        )
+    };
+
+    if xcolor == Black { // Don't have double red case
+        node.into_position(NodeOffset::Root)
+    } else {
+        match (pcolor, ucolor) {
+            // Perform recoloring
+            (Red, Red) => {
+                {
+                    node.inspect_child(TreePath::Right).unwrap().update_balance(|b| b.paint(Black));
+                }
+                {
+                    node.inspect_child(TreePath::Left).unwrap().update_balance(|b| b.paint(Black)); }
+                {
+                    if !node.inspect_is_root() {
+                        node.update_balance(|b| b.paint(Red));
+                    }
+                }
+                // This node may now be part of a double red with its parent,
+                // so skip ahead to the grandparent which can resolve it
+                node.into_position(NodeOffset::Ancestor(2))
+            },
+            // Perform rotation
+            (Red, Black) => {
+                node = node.rotate(path);
+                { node.inspect_child(path.0.reflect()).unwrap().update_balance(|b| b.paint(Red));}
+                { node.update_balance(|b| b.paint(Black)); }
+                node.into_position(NodeOffset::Root)
+            },
+            // Do nothing
+            (Black, _) => node.into_position(NodeOffset::Root)
+        }
+    }
+
+}
+
+/// Rebalances a red black tree from the bottom up after a deletion
+fn colored_delete<T: Ord, A: Augment<T>, B: Colored>(mut node: NodeInspector<T, B, A>, xpath: TreePath, popped_balance: &B) -> TreePosition<T, B, A> {
+    // Check if the node removed was black, if not we don't need to investigate further
+    if popped_balance.color() == Red {
+        return node.into_position(NodeOffset::Root);
+    }
+    let spath = xpath.reflect(); // sibling path
+
+    // Get the color of the node in the direction of the deletion,
+    // the color of the sibling node, and (if any) the position of the sibling's red child
+    let (xcolor, scolor, vpath) = {
+        let snode = node.inspect_child(spath);
+        let scolor = snode.as_ref().map_or(Black, |n| n.inspect_balance(|b| b.color()));
+        let vpath = snode.and_then(|snode| {
+            let inline_color = snode.inspect_child(spath).map_or(Black, |n| n.inspect_balance(|b| b.color()));
+            let elbow_color = snode.inspect_child(xpath).map_or(Black, |n| n.inspect_balance(|b| b.color()));
+            // Prioritize the outermost child as if both children are red
+            // using the outermost child prevents an extra rotation
+            if inline_color == Red {
+                Some(spath)
+            } else if elbow_color == Red {
+                Some(xpath)
+            } else {
+                None
+            }
+        });
+        (
+            node.inspect_child(xpath).map_or(Black, |n| n.inspect_balance(|b| b.color())),
+            scolor,
+            vpath
+        )
+    };
+
+    // If the node in the direction of the deletion is black
+    // that means we have a double black case
+    if xcolor == Black {
+        // If the sibling is also black we 
+        if scolor == Black {
+            // Get the parent's color (for later use)
+            let pcolor = node.inspect_balance(|b| b.color());
+            if let Some(path) = vpath {
+                // Perform rotation
+                node = node.rotate((spath, path));
+                node.inspect_child(TreePath::Right).unwrap().update_balance(|b| b.paint(Black));
+                node.inspect_child(TreePath::Left).unwrap().update_balance(|b| b.paint(Black));
+                node.update_balance(|b| b.paint(pcolor));
+                node.into_position(NodeOffset::Root)
+            } else {
+                // Recolor, checking if we have another double black
+                if let Some(mut n) = node.inspect_child(spath) {
+                    n.update_balance(|b| b.paint(Red));
+                }
+                match pcolor {
+                    Black => node.into_position(NodeOffset::Parent),
+                    Red => {
+                        node.update_balance(|b| b.paint(Black));
+                        node.into_position(NodeOffset::Root)
+                    }
+                }
+            }
+        } else {
+            // Perform rotation and inspect the new parent of the double black child
+            node = node.rotate((spath, spath));
+            { node.inspect_child(xpath).unwrap().update_balance(|b| b.paint(Red));}
+            { node.update_balance(|b| b.paint(Black)); }
+            node.into_position(NodeOffset::Child(xpath))
+        }
+    } else {
+        // Nothing to do other than recolor and, return to root
+        { node.inspect_child(xpath).unwrap().update_balance(|b| b.paint(Black));}
+        node.into_position(NodeOffset::Root)
+    }
+    
+}
+
 #[cfg(test)]
 mod tests {
-    use super::RedBlackBalance;
+    use super::{Colored, LeftLeaningRedBlackBalance, RBColor, RedBlackBalance, TopDownRedBlackBalance, colored_verify};
     use crate::Tree;
+    use crate::tree::TreePath;
+    use crate::tree::inspect::*;
 
     #[test]
     fn insert_unbalanced() {
@@ -238,4 +757,266 @@ mod tests {
         tree.delete(&40);
         assert_eq!(tree.height(), 2)
     }
-}
\ No newline at end of file
+
+    // Trees built before the recolor case skipped straight to the grandparent,
+    // the skip should not change the shape or coloring of the resulting tree
+    const RECOLOR_CHAIN: &str = "70 { balance: Black, height: 4, leaves: 4, right: 90 { balance: Red, height: 3, leaves: 2, right: 100 { balance: Black, height: 2, leaves: 1, right: 110 { balance: Red, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: \"None\" }, left: 80 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" } }, left: 50 { balance: Red, height: 2, leaves: 2, right: 60 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 40 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" } } }";
+    const DESCENDING: &str = "24 { balance: Black, height: 8, leaves: 16, right: 28 { balance: Black, height: 3, leaves: 4, right: 30 { balance: Black, height: 2, leaves: 2, right: 31 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 29 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" } }, left: 26 { balance: Black, height: 2, leaves: 2, right: 27 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 25 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" } } }, left: 16 { balance: Red, height: 7, leaves: 12, right: 20 { balance: Black, height: 3, leaves: 4, right: 22 { balance: Black, height: 2, leaves: 2, right: 23 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 21 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" } }, left: 18 { balance: Black, height: 2, leaves: 2, right: 19 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 17 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" } } }, left: 12 { balance: Black, height: 6, leaves: 8, right: 14 { balance: Black, height: 2, leaves: 2, right: 15 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 13 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" } }, left: 8 { balance: Red, height: 5, leaves: 6, right: 10 { balance: Black, height: 2, leaves: 2, right: 11 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 9 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" } }, left: 4 { balance: Black, height: 4, leaves: 4, right: 6 { balance: Red, height: 2, leaves: 2, right: 7 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 5 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" } }, left: 2 { balance: Red, height: 3, leaves: 2, right: 3 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 1 { balance: Black, height: 2, leaves: 1, right: \"None\", left: 0 { balance: Red, height: 1, leaves: 1, right: \"None\", left: \"None\" } } } } } } } }";
+    const RANDOM: &str = "64 { balance: Black, height: 6, leaves: 14, right: 86 { balance: Red, height: 5, leaves: 7, right: 95 { balance: Black, height: 3, leaves: 2, right: 98 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 89 { balance: Black, height: 2, leaves: 1, right: 93 { balance: Red, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: \"None\" } }, left: 70 { balance: Black, height: 4, leaves: 5, right: 75 { balance: Red, height: 3, leaves: 3, right: 79 { balance: Black, height: 2, leaves: 2, right: 85 { balance: Red, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 77 { balance: Red, height: 1, leaves: 1, right: \"None\", left: \"None\" } }, left: 73 { balance: Black, height: 2, leaves: 1, right: \"None\", left: 71 { balance: Red, height: 1, leaves: 1, right: \"None\", left: \"None\" } } }, left: 68 { balance: Black, height: 2, leaves: 2, right: 69 { balance: Red, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 66 { balance: Red, height: 1, leaves: 1, right: \"None\", left: \"None\" } } } }, left: 43 { balance: Red, height: 5, leaves: 7, right: 53 { balance: Black, height: 4, leaves: 3, right: 63 { balance: Black, height: 2, leaves: 1, right: \"None\", left: 62 { balance: Red, height: 1, leaves: 1, right: \"None\", left: \"None\" } }, left: 49 { balance: Red, height: 3, leaves: 2, right: 51 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 48 { balance: Black, height: 2, leaves: 1, right: \"None\", left: 47 { balance: Red, height: 1, leaves: 1, right: \"None\", left: \"None\" } } } }, left: 9 { balance: Black, height: 4, leaves: 4, right: 27 { balance: Red, height: 3, leaves: 3, right: 29 { balance: Black, height: 2, leaves: 1, right: 33 { balance: Red, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: \"None\" }, left: 14 { balance: Black, height: 2, leaves: 2, right: 23 { balance: Red, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: 10 { balance: Red, height: 1, leaves: 1, right: \"None\", left: \"None\" } } }, left: 3 { balance: Black, height: 1, leaves: 1, right: \"None\", left: \"None\" } } } }";
+
+    #[test]
+    fn insert_recolor_chain() {
+        let mut tree: Tree<u32, RedBlackBalance> = Tree::new();
+
+        for key in [40, 50, 60, 70, 90, 80, 100, 110] {
+            tree.insert(key);
+        }
+        assert_eq!(format!("{:?}", tree), RECOLOR_CHAIN)
+    }
+
+    #[test]
+    fn insert_descending() {
+        let mut tree: Tree<u32, RedBlackBalance> = Tree::new();
+
+        for key in (0..32).rev() {
+            tree.insert(key);
+        }
+        assert_eq!(format!("{:?}", tree), DESCENDING)
+    }
+
+    #[test]
+    fn insert_random() {
+        let mut tree: Tree<u32, RedBlackBalance> = Tree::new();
+
+        let mut seed = 11u32;
+        for _ in 0..40 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            tree.insert((seed >> 8) % 100);
+        }
+        assert_eq!(format!("{:?}", tree), RANDOM)
+    }
+
+    /// Upper bound on the height of a red black tree holding `len` keys
+    fn max_height(len: usize) -> usize {
+        2 * (usize::BITS - (len + 1).leading_zeros()) as usize
+    }
+
+    #[test]
+    fn top_down_insert_ascending() {
+        let mut tree: Tree<u32, TopDownRedBlackBalance> = Tree::new();
+
+        for key in 0..1000 {
+            tree.insert(key);
+        }
+        assert!(tree.height() <= max_height(1000));
+        assert!((0..1000).all(|key| tree.search(&key)));
+    }
+
+    #[test]
+    fn top_down_delete() {
+        let mut tree: Tree<u32, TopDownRedBlackBalance> = Tree::new();
+
+        for key in 0..200 {
+            tree.insert(key);
+        }
+        for key in (0..200).step_by(3).chain((0..200).rev()) {
+            tree.delete(&key);
+            assert_eq!(tree.validate(), Ok(()));
+            assert!(tree.height() <= max_height(tree.len()));
+        }
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn top_down_matches_bottom_up() {
+        let mut top_down: Tree<u32, TopDownRedBlackBalance> = Tree::new();
+        let mut bottom_up: Tree<u32, RedBlackBalance> = Tree::new();
+
+        let mut seed = 3u32;
+        for _ in 0..2000 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let key = (seed >> 8) % 500;
+            if seed & 3 == 0 {
+                assert_eq!(top_down.delete(&key), bottom_up.delete(&key));
+            } else {
+                top_down.insert(key);
+                bottom_up.insert(key);
+            }
+            assert!(top_down.height() <= max_height(top_down.len()));
+            assert_eq!(top_down.validate(), Ok(()));
+        }
+        assert_eq!(format!("{}", top_down), format!("{}", bottom_up));
+        assert_eq!(top_down.validate(), Ok(()));
+        assert_eq!(bottom_up.validate(), Ok(()));
+    }
+
+    #[test]
+    fn left_leaning_insert() {
+        let mut tree: Tree<u32, LeftLeaningRedBlackBalance> = Tree::new();
+
+        tree.insert(40);
+        tree.insert(50);
+        tree.insert(60);
+        assert_eq!(tree.height(), 2);
+        assert_eq!(tree.validate(), Ok(()))
+    }
+
+    #[test]
+    fn left_leaning_insert_ascending() {
+        let mut tree: Tree<u32, LeftLeaningRedBlackBalance> = Tree::new();
+
+        for key in 0..1000 {
+            tree.insert(key);
+            assert_eq!(tree.validate(), Ok(()));
+        }
+        assert!(tree.height() <= max_height(1000));
+    }
+
+    #[test]
+    fn left_leaning_delete() {
+        let mut tree: Tree<u32, LeftLeaningRedBlackBalance> = Tree::new();
+
+        for key in [40, 50, 60, 70, 90, 80] {
+            tree.insert(key);
+        }
+        tree.delete(&90);
+        tree.delete(&80);
+        assert_eq!(tree.validate(), Ok(()));
+        tree.delete(&40);
+        assert_eq!(tree.height(), 2);
+        assert_eq!(tree.validate(), Ok(()))
+    }
+
+    #[test]
+    fn left_leaning_validate_reinserted() {
+        // Compacting and converting reinsert the keys without accessing them
+        let mut tree: Tree<u32, LeftLeaningRedBlackBalance> = Tree::with_lazy_delete(1.0);
+        for key in 0..100 {
+            tree.insert(key);
+        }
+        for key in (0..100).step_by(3) {
+            tree.delete(&key);
+        }
+        tree.compact();
+        assert_eq!(tree.validate(), Ok(()));
+
+        let mut avl: crate::AVLTree<u32> = crate::AVLTree::new();
+        for key in 0..100 {
+            avl.insert(key);
+        }
+        let tree: Tree<u32, LeftLeaningRedBlackBalance> = avl.into();
+        assert_eq!(tree.validate(), Ok(()))
+    }
+
+    #[test]
+    fn left_leaning_matches_classic() {
+        let mut left_leaning: Tree<u32, LeftLeaningRedBlackBalance> = Tree::new();
+        let mut classic: Tree<u32, RedBlackBalance> = Tree::new();
+
+        let mut seed = 9u32;
+        for _ in 0..4000 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let key = (seed >> 8) % 500;
+            if seed & 3 == 0 {
+                assert_eq!(left_leaning.delete(&key), classic.delete(&key));
+            } else {
+                left_leaning.insert(key);
+                classic.insert(key);
+            }
+            assert_eq!(left_leaning.validate(), Ok(()));
+        }
+        assert_eq!(format!("{}", left_leaning), format!("{}", classic));
+        for key in 0..500 {
+            assert_eq!(left_leaning.delete(&key), classic.delete(&key));
+            assert_eq!(left_leaning.validate(), Ok(()));
+        }
+        assert!(left_leaning.is_empty())
+    }
+
+    #[test]
+    fn black_height() {
+        let mut classic: Tree<u32, RedBlackBalance> = Tree::new();
+        let mut top_down: Tree<u32, TopDownRedBlackBalance> = Tree::new();
+        let mut left_leaning: Tree<u32, LeftLeaningRedBlackBalance> = Tree::new();
+
+        for key in 1..=7 {
+            classic.insert(key);
+            top_down.insert(key);
+            left_leaning.insert(key);
+        }
+        // The height is at most twice the black height
+        assert!(classic.height() <= 2 * classic.black_height());
+        assert!(top_down.height() <= 2 * top_down.black_height());
+        assert!(left_leaning.height() <= 2 * left_leaning.black_height());
+
+        // A rebuilt perfect tree has a black root and children and red leaves
+        classic.rebuild();
+        assert_eq!(classic.black_height(), 2);
+        let color = |key| classic.node_info(&key).map(|info| info.state);
+        assert_eq!(color(4), Some(RBColor::Black));
+        assert_eq!(color(2), Some(RBColor::Black));
+        assert_eq!(color(1), Some(RBColor::Red));
+        assert_eq!(color(8), None);
+    }
+
+    /// Colored balance that never rebalances
+    struct Uncolored(RBColor);
+
+    impl TreeBalance for Uncolored {
+        type State = RBColor;
+        type Cache = ();
+
+        fn rebalance_insert<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
+            node.into_position(NodeOffset::Root)
+        }
+
+        fn new<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+            Uncolored (RBColor::Red)
+        }
+
+        fn new_root<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+            Uncolored (RBColor::Black)
+        }
+
+        fn rebalance_delete<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: TreePath, _: &Self) -> TreePosition<T, Self, A> {
+            node.into_position(NodeOffset::Root)
+        }
+
+        fn verify<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Result<(), ValidationError> {
+            colored_verify(node)
+        }
+
+        fn state<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Self::State {
+            node.inspect_balance(|b| b.0)
+        }
+
+        fn adjust_root(&mut self) {
+            self.0 = RBColor::Black
+        }
+    }
+
+    impl Colored for Uncolored {
+        fn color(&self) -> RBColor {
+            self.0
+        }
+
+        fn paint(&mut self, color: RBColor) {
+            self.0 = color
+        }
+    }
+
+    #[test]
+    // Validating after every operation catches the broken tree on insert
+    #[cfg_attr(feature = "debug-invariants", should_panic(expected = "Red black node has a red parent"))]
+    fn verify_invariants() {
+        let mut tree: Tree<u32, Uncolored> = Tree::new();
+
+        tree.insert(50);
+        tree.insert(40);
+        tree.insert(60);
+        assert_eq!(tree.validate(), Ok(()));
+
+        tree.insert(70);
+        assert_eq!(tree.validate(), Err(ValidationError::Balance("Red black node has a red parent")));
+    }
+}
@@ -0,0 +1,213 @@
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use crate::tree::TreePath;
+use crate::tree::cache::Size;
+use crate::tree::inspect::*;
+
+/// Implementation of a balance for a binary tree
+/// that effectively converts a [Tree] into a Scapegoat Tree
+///
+/// Nodes only cache the size of the tree they root, and the root records the largest size the tree
+/// has had since it was last rebuilt.  An insertion is only fixed if it leaves the new key deeper than
+/// log base 1/α of the number of keys, where α is `ALPHA` percent: the lowest ancestor of the key with a
+/// child holding more than α of its tree is the scapegoat and its tree is rebuilt into a height
+/// balanced shape.  Once deletions shrink the tree below α of its recorded size the whole tree is rebuilt.
+/// `ALPHA` must be between 50 and 100 (exclusive), lower values keep the tree closer to balanced
+/// at the cost of more rebuilds.
+pub struct ScapegoatBalance<const ALPHA: usize = 70> {
+    max_size: usize
+}
+
+impl <const ALPHA: usize> ScapegoatBalance<ALPHA> {
+    /// Fails to compile if ALPHA is out of range
+    const VALID_ALPHA: () = assert!(ALPHA > 50 && ALPHA < 100, "ALPHA must be between 50 and 100");
+
+    /// Returns the largest depth a key can have in a tree of the given size, log base 1/α of the size
+    fn depth_limit(size: usize) -> usize {
+        let (mut limit, mut reach) = (0, 100.0 / ALPHA as f64);
+        while reach <= size as f64 {
+            limit += 1;
+            reach *= 100.0 / ALPHA as f64;
+        }
+        limit
+    }
+
+    /// Travels up from a node of the given size to the root
+    ///
+    /// Returns an inspector for the root, the paths from the root down to the node and the size of each
+    /// node passed, starting with the node itself.  The sizes are counted from the node upwards since the
+    /// cached sizes above it are not updated until rebalancing moves past them.
+    fn climb<T: Ord, A: Augment<T>>(mut node: NodeInspector<T, Self, A>, mut size: usize) -> (NodeInspector<T, Self, A>, Vec<TreePath>, Vec<usize>) {
+        let mut paths = Vec::new();
+        let mut sizes = alloc::vec![size];
+        while let Some(placement) = node.inspect_placement() {
+            size += node.inspect_sibling().map_or(0, |n| n.inspect_size()) + 1;
+            node = node.move_to(NodeOffset::Parent).ok().unwrap();
+            paths.push(placement);
+            sizes.push(size);
+        }
+        paths.reverse();
+        (node, paths, sizes)
+    }
+
+    /// Moves from the root down the paths
+    fn descend<T: Ord, A: Augment<T>>(root: NodeInspector<T, Self, A>, paths: &[TreePath]) -> NodeInspector<T, Self, A> {
+        if paths.is_empty() {
+            root
+        } else {
+            root.move_to(NodeOffset::Descendant(paths.to_vec())).ok().unwrap()
+        }
+    }
+
+    /// Rebuilds the whole tree, recording its size at the new root
+    fn rebuild_root<T: Ord, A: Augment<T>>(mut root: NodeInspector<T, Self, A>, size: usize) -> NodeInspector<T, Self, A> {
+        root.update_balance(|b| b.max_size = 0);
+        let mut root = root.rebuild_balanced();
+        root.update_balance(|b| b.max_size = size);
+        root
+    }
+}
+
+impl <const ALPHA: usize> TreeBalance for ScapegoatBalance<ALPHA> {
+    type State = usize;
+    type Cache = Size;
+
+    fn rebalance_insert<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, path: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
+        // Everything is done from the parent of the inserted node, which is up to date
+        let parent = match path.1 {
+            Some(_) => node.move_to(NodeOffset::Child(path.0)).ok().unwrap(),
+            None => node
+        };
+        let size = parent.inspect_size();
+        let (mut root, paths, sizes) = Self::climb(parent, size);
+        let total = *sizes.last().unwrap();
+        root.update_balance(|b| b.max_size = b.max_size.max(total));
+
+        // The inserted node is one level below its parent
+        if paths.len() < Self::depth_limit(total) {
+            // Return to the parent so every node above it is updated
+            return Self::descend(root, &paths).into_position(NodeOffset::Root);
+        }
+        // The scapegoat is the lowest ancestor with a child (on the path) holding too much of its tree,
+        // a key deeper than the limit always has one
+        let child = |level: usize| if level == 0 { 1 } else { sizes[level - 1] };
+        let level = (0..sizes.len())
+            .find(|&level| child(level) * 100 > ALPHA * sizes[level])
+            .unwrap_or(paths.len());
+        let node = if level == paths.len() {
+            Self::rebuild_root(root, total)
+        } else {
+            Self::descend(root, &paths[..paths.len() - level]).rebuild_balanced()
+        };
+        node.into_position(NodeOffset::Root)
+    }
+
+    fn new<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::VALID_ALPHA;
+        ScapegoatBalance { max_size: 0 }
+    }
+
+    fn new_root<T: Ord>(context: &mut BalanceContext<T>) -> Self {
+        Self::new(context)
+    }
+
+    fn new_rebuilt(_: usize, _: Option<usize>) -> Option<Self> {
+        // An unrecorded size is taken as the current size
+        Some(ScapegoatBalance { max_size: 0 })
+    }
+
+    fn rebalance_delete<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: TreePath, _: &Self) -> TreePosition<T, Self, A> {
+        // The children of the node are up to date but the node itself is not
+        let size = [TreePath::Left, TreePath::Right].iter()
+            .map(|&path| node.inspect_child(path).map_or(0, |n| n.inspect_size()))
+            .sum::<usize>() + 1;
+        let (mut root, paths, sizes) = Self::climb(node, size);
+        let total = *sizes.last().unwrap();
+        let max_size = root.inspect_balance(|b| b.max_size).max(total);
+        if total * 100 < ALPHA * max_size {
+            return Self::rebuild_root(root, total).into_position(NodeOffset::Root);
+        }
+        root.update_balance(|b| b.max_size = max_size);
+        Self::descend(root, &paths).into_position(NodeOffset::Root)
+    }
+
+    fn state<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Self::State {
+        node.inspect_balance(|b| b.max_size)
+    }
+
+    fn adjust_root(&mut self) {
+        // The recorded size is lost with the old root, the current size is used until the next operation
+        self.max_size = 0;
+    }
+}
+
+impl <const ALPHA: usize> Debug for ScapegoatBalance<ALPHA> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        write!(f, "{}", self.max_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScapegoatBalance;
+    use crate::Tree;
+
+    #[test]
+    fn insert_ascending() {
+        let mut tree: Tree<u32, ScapegoatBalance> = Tree::new();
+
+        for key in 0..1000 {
+            tree.insert(key);
+        }
+        // log base 1/0.7 of 1000 is about 19.4
+        assert!(tree.height() <= 20);
+        assert!((0..1000).all(|key| tree.search(&key)));
+    }
+
+    #[test]
+    fn rebuild_scapegoat() {
+        let mut tree: Tree<u32, ScapegoatBalance<60>> = Tree::new();
+
+        tree.insert(40);
+        tree.insert(50);
+        tree.insert(60);
+        // 60 is at depth 2, within log base 1/0.6 of 3 (about 2.2)
+        assert_eq!(tree.height(), 3);
+        tree.insert(70);
+        // 70 is at depth 3, past log base 1/0.6 of 4 (about 2.7), and 50 holds
+        // a child of size 2 out of 3 so its tree is rebuilt
+        assert_eq!(tree.height(), 3);
+        assert_eq!(tree.node_info(&60).map(|info| info.depth), Some(2));
+        assert_eq!(tree.node_info(&40).map(|info| info.state), Some(4));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn shallow_insert_unfixed() {
+        let mut tree: Tree<u32, ScapegoatBalance> = Tree::new();
+
+        // A tree that is not weight balanced is left alone while keys stay shallow enough
+        for key in [50, 40, 30, 60] {
+            tree.insert(key);
+        }
+        assert_eq!(tree.node_info(&30).map(|info| info.depth), Some(3));
+        assert_eq!(tree.height(), 3);
+    }
+
+    #[test]
+    fn delete_rebuild() {
+        let mut tree: Tree<u32, ScapegoatBalance<60>> = Tree::new();
+
+        for key in 0..100 {
+            tree.insert(key);
+        }
+        for key in 0..90 {
+            assert_eq!(tree.delete(&key), Some(key));
+        }
+        assert!(tree.height() <= 5);
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(format!("{}", tree), "Tree: { 90, 91, 92, 93, 94, 95, 96, 97, 98, 99, }");
+    }
+}
@@ -0,0 +1,187 @@
+use alloc::vec::Vec;
+
+use crate::dyntree::{DynFinger, DynTree};
+use crate::multiway::{MultiFinger, MultiTree};
+use crate::multiway::inspect::MultiBalance;
+use crate::tree::{Finger, Op, OpOutcome, Tree};
+use crate::tree::augment::Augment;
+use crate::tree::inspect::{InspectError, TreeBalance, ValidationError};
+
+/// Set operations shared by every tree in the crate
+///
+/// Implemented by [Tree], [MultiTree] and [DynTree] so that callers can be generic over
+/// the kind of tree (and its balance).  Each method behaves like the tree's own method of the same name.
+///
+/// ```
+/// use project2::{BTree, RedBlackTree};
+/// use project2::set::SearchTree;
+///
+/// fn fill<S: SearchTree<u32>>(mut set: S) -> S {
+///     let mut finger = S::Finger::default();
+///     for key in 0..100 {
+///         set.insert_near(&mut finger, key);
+///     }
+///     set.delete(&50);
+///     set
+/// }
+///
+/// let binary = fill(RedBlackTree::new());
+/// let multiway = fill(BTree::<u32, 4>::new());
+/// assert_eq!(binary.len(), multiway.len());
+/// assert!(!binary.search(&50) && !multiway.search(&50));
+/// assert_eq!(multiway.validate(), Ok(()));
+/// ```
+pub trait SearchTree<T: Ord> {
+    /// Remembered position in the tree used to speed up nearby operations
+    type Finger: Default;
+
+    /// Returns true if the tree has no contents
+    fn is_empty(&self) -> bool;
+
+    /// Returns the number of keys in the tree
+    fn len(&self) -> usize;
+
+    /// Inserts an element into the tree
+    fn insert(&mut self, key: T);
+
+    /// Inserts an element into the tree, returning an error if the balance fails
+    fn try_insert(&mut self, key: T) -> Result<(), InspectError>;
+
+    /// Inserts an element into the tree, starting the search at a finger
+    fn insert_near(&mut self, finger: &mut Self::Finger, key: T);
+
+    /// Finds if an element exists in the tree
+    fn search(&self, key: &T) -> bool;
+
+    /// Finds if an element exists in the tree, letting the balance restructure the tree
+    fn search_mut(&mut self, key: &T) -> bool;
+
+    /// Finds if an element exists in the tree, starting the search at a finger
+    fn search_near(&self, finger: &mut Self::Finger, key: &T) -> bool;
+
+    /// Removes an element from the tree if it exists
+    fn delete(&mut self, key: &T) -> Option<T>;
+
+    /// Removes an element from the tree if it exists, returning an error if the balance fails
+    fn try_delete(&mut self, key: &T) -> Result<Option<T>, InspectError>;
+
+    /// Removes an element from the tree if it exists, starting the search at a finger
+    fn delete_near(&mut self, finger: &mut Self::Finger, key: &T) -> Option<T>;
+
+    /// Applies a batch of insert and delete operations to the tree
+    fn apply_batch<I: IntoIterator<Item = Op<T>>>(&mut self, batch: I) -> Vec<OpOutcome<T>>;
+
+    /// Get the height of the tree
+    fn height(&self) -> usize;
+
+    /// Get the number of leaves of the tree
+    fn leaves(&self) -> usize;
+
+    /// Checks the tree is valid
+    fn validate(&self) -> Result<(), ValidationError>;
+
+    /// Removes every key from the tree
+    fn clear(&mut self);
+
+    /// Returns the number of lazily deleted keys still in the tree
+    fn tombstones(&self) -> usize;
+
+    /// Removes all lazily deleted keys from the tree
+    fn compact(&mut self);
+
+    /// Enables or disables lazy deletion
+    fn set_lazy_delete(&mut self, fraction: Option<f64>) where T: Clone;
+}
+
+// Every method forwards to the tree's own method of the same name
+macro_rules! impl_search_tree {
+    ($($tree:ty, $finger:ty, [$($params:tt)*];)+) => {
+        $(
+            impl <$($params)*> SearchTree<T> for $tree {
+                type Finger = $finger;
+
+                fn is_empty(&self) -> bool {
+                    <$tree>::is_empty(self)
+                }
+
+                fn len(&self) -> usize {
+                    <$tree>::len(self)
+                }
+
+                fn insert(&mut self, key: T) {
+                    <$tree>::insert(self, key)
+                }
+
+                fn try_insert(&mut self, key: T) -> Result<(), InspectError> {
+                    <$tree>::try_insert(self, key)
+                }
+
+                fn insert_near(&mut self, finger: &mut Self::Finger, key: T) {
+                    <$tree>::insert_near(self, finger, key)
+                }
+
+                fn search(&self, key: &T) -> bool {
+                    <$tree>::search(self, key)
+                }
+
+                fn search_mut(&mut self, key: &T) -> bool {
+                    <$tree>::search_mut(self, key)
+                }
+
+                fn search_near(&self, finger: &mut Self::Finger, key: &T) -> bool {
+                    <$tree>::search_near(self, finger, key)
+                }
+
+                fn delete(&mut self, key: &T) -> Option<T> {
+                    <$tree>::delete(self, key)
+                }
+
+                fn try_delete(&mut self, key: &T) -> Result<Option<T>, InspectError> {
+                    <$tree>::try_delete(self, key)
+                }
+
+                fn delete_near(&mut self, finger: &mut Self::Finger, key: &T) -> Option<T> {
+                    <$tree>::delete_near(self, finger, key)
+                }
+
+                fn apply_batch<I: IntoIterator<Item = Op<T>>>(&mut self, batch: I) -> Vec<OpOutcome<T>> {
+                    <$tree>::apply_batch(self, batch)
+                }
+
+                fn height(&self) -> usize {
+                    <$tree>::height(self)
+                }
+
+                fn leaves(&self) -> usize {
+                    <$tree>::leaves(self)
+                }
+
+                fn validate(&self) -> Result<(), ValidationError> {
+                    <$tree>::validate(self)
+                }
+
+                fn clear(&mut self) {
+                    <$tree>::clear(self)
+                }
+
+                fn tombstones(&self) -> usize {
+                    <$tree>::tombstones(self)
+                }
+
+                fn compact(&mut self) {
+                    <$tree>::compact(self)
+                }
+
+                fn set_lazy_delete(&mut self, fraction: Option<f64>) where T: Clone {
+                    <$tree>::set_lazy_delete(self, fraction)
+                }
+            }
+        )+
+    };
+}
+
+impl_search_tree! {
+    Tree<T, U, A>, Finger<T, U, A>, [T: Ord, U: TreeBalance, A: Augment<T>];
+    MultiTree<T, U>, MultiFinger, [T: Ord, U: MultiBalance];
+    DynTree<T, A>, DynFinger, [T: Ord + 'static, A: Augment<T> + 'static];
+}
@@ -0,0 +1,134 @@
+use core::fmt::Debug;
+
+use crate::tree::TreePath;
+use crate::tree::inspect::*;
+
+/// Implementation of a balance for a binary tree
+/// that effectively converts a [Tree] into a Splay Tree
+///
+/// Accessed, inserted and deleted nodes are splayed to the root of the tree.
+/// Use [Tree::search_mut] for searches to splay the found node.
+pub struct SplayBalance();
+impl TreeBalance for SplayBalance {
+    type State = ();
+    type Cache = ();
+
+    const ON_ACCESS: bool = true;
+
+    fn rebalance_insert<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
+        // All the work is done when the inserted node is accessed
+        node.into_position(NodeOffset::Root)
+    }
+
+    fn new<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+        SplayBalance()
+    }
+
+    fn new_root<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+        SplayBalance()
+    }
+
+    fn new_rebuilt(_: usize, _: Option<usize>) -> Option<Self> {
+        Some(SplayBalance())
+    }
+
+    fn rebalance_delete<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: TreePath, _: &Self) -> TreePosition<T, Self, A> {
+        // All the work is done when the parent of the deleted node is accessed
+        node.into_position(NodeOffset::Root)
+    }
+
+    fn on_access<T: Ord, A: Augment<T>>(mut node: NodeInspector<T, Self, A>, path: &[TreePath]) -> TreePosition<T, Self, A> {
+        match *path {
+            // Zig, the accessed node is a child of the root
+            [ppath] if node.inspect_is_root() => {
+                node.rotate((ppath, ppath)).into_position(NodeOffset::Root)
+            },
+            // Zig-zig, rotate the parent up and then the accessed node
+            [ppath, xpath] if ppath == xpath => {
+                node = node.rotate((ppath, ppath)).rotate((xpath, xpath));
+                Self::splay_next(node)
+            },
+            // Zig-zag, rotate the accessed node up twice
+            [ppath, xpath] => {
+                node = node.rotate((ppath, xpath));
+                Self::splay_next(node)
+            },
+            // Travel up until we are at the grandparent of the accessed node (or the root)
+            _ => Self::splay_next(node)
+        }
+    }
+
+    fn state<T: Ord, A: Augment<T>>(_: &NodeInspector<T, Self, A>) -> Self::State {}
+
+    fn adjust_root(&mut self) {
+        // Do nothing
+    }
+}
+
+impl SplayBalance {
+    /// Returns the position to continue splaying from
+    fn splay_next<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>) -> TreePosition<T, Self, A> {
+        if node.inspect_is_root() {
+            node.into_position(NodeOffset::Root)
+        } else {
+            node.into_position(NodeOffset::Parent)
+        }
+    }
+}
+
+impl Debug for SplayBalance {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        write!(f, "Splay")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SplayBalance;
+    use crate::Tree;
+
+    #[test]
+    fn insert_splays() {
+        let mut tree: Tree<u32, SplayBalance> = Tree::new();
+
+        tree.insert(40);
+        tree.insert(50);
+        tree.insert(60);
+        tree.insert(70);
+        // Ascending inserts leave a path down the left side
+        assert_eq!(tree.height(), 4);
+        assert_eq!(format!("{}", tree), "Tree: { 40, 50, 60, 70, }");
+    }
+
+    #[test]
+    fn search_splays() {
+        let mut tree: Tree<u32, SplayBalance> = Tree::new();
+
+        for key in 0..32 {
+            tree.insert(key);
+        }
+        assert_eq!(tree.height(), 32);
+
+        // Splaying the deepest node roughly halves the depth of the path
+        assert!(tree.search_mut(&0));
+        assert_eq!(tree.height(), 18);
+        assert!(!tree.search_mut(&32));
+        assert_eq!(tree.len(), 32);
+    }
+
+    #[test]
+    fn delete_splays() {
+        let mut tree: Tree<u32, SplayBalance> = Tree::new();
+
+        for key in 0..32 {
+            tree.insert(key);
+        }
+        assert_eq!(tree.delete(&0), Some(0));
+        assert_eq!(tree.delete(&0), None);
+        assert_eq!(tree.height(), 17);
+        for key in 1..32 {
+            assert!(tree.search_mut(&key));
+        }
+        assert_eq!(format!("{}", tree).matches(',').count(), 31);
+    }
+}
@@ -0,0 +1,165 @@
+use core::fmt::Debug;
+
+use crate::tree::TreePath;
+use crate::tree::inspect::*;
+
+/// Implementation of a balance for a binary tree
+/// that effectively converts a [Tree] into a Treap
+///
+/// Each node is given a random priority from the tree's generator when it is created,
+/// and nodes are rotated so that every node has a higher priority than its children.
+/// The shape of the tree only depends on the seed of the tree (see [Tree::with_seed])
+/// and the operations applied to it.
+pub struct TreapBalance {
+    priority: u64
+}
+
+impl TreeBalance for TreapBalance {
+    type State = u64;
+    type Cache = ();
+
+    fn rebalance_insert<T: Ord, A: Augment<T>>(mut node: NodeInspector<T, Self, A>, path: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
+        let (ppath, xpath) = path;
+        if let Some(xpath) = xpath {
+            // Rotate the inserted node above its parent, making it a child of this node
+            let (priority, ppriority) = {
+                let pnode = node.inspect_child(ppath).unwrap();
+                let xpriority = pnode.inspect_child(xpath).unwrap().inspect_balance(|b| b.priority);
+                (xpriority, pnode.inspect_balance(|b| b.priority))
+            };
+            if priority <= ppriority {
+                return node.into_position(NodeOffset::Root);
+            }
+            node = node.rotate_at(&[ppath], xpath);
+        }
+
+        // Rotate the inserted node above this node, continuing from its new parent
+        let priority = node.inspect_child(ppath).unwrap().inspect_balance(|b| b.priority);
+        if priority <= node.inspect_balance(|b| b.priority) {
+            node.into_position(NodeOffset::Root)
+        } else {
+            node.rotate((ppath, ppath)).into_position(NodeOffset::Parent)
+        }
+    }
+
+    fn new<T: Ord>(context: &mut BalanceContext<T>) -> Self {
+        TreapBalance { priority: context.random() }
+    }
+
+    fn new_root<T: Ord>(context: &mut BalanceContext<T>) -> Self {
+        Self::new(context)
+    }
+
+    fn rebalance_delete<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: TreePath, _: &Self) -> TreePosition<T, Self, A> {
+        // The deleted node was rotated down until it had at most one child,
+        // which takes its place without breaking the heap order
+        node.into_position(NodeOffset::Root)
+    }
+
+    fn before_descend<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: (TreePath, Option<TreePath>), op: Descent) -> NodeInspector<T, Self, A> {
+        if op != Descent::Successor {
+            return node;
+        }
+        // Rotate the node being deleted below the child with the higher priority, so it is removed
+        // once it has at most one child rather than swapping the key of its successor into its place
+        // (the search for the successor never moves past the node, so this is always the node being deleted)
+        let priority = |path| node.inspect_child(path).unwrap().inspect_balance(|b| b.priority);
+        let hpath = if priority(TreePath::Left) > priority(TreePath::Right) {
+            TreePath::Left
+        } else {
+            TreePath::Right
+        };
+        node.rotate((hpath, hpath))
+    }
+
+    fn verify<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Result<(), ValidationError> {
+        let priority = node.inspect_balance(|b| b.priority);
+        let higher = |path| node.inspect_child(path).is_some_and(|n| n.inspect_balance(|b| b.priority) > priority);
+        if higher(TreePath::Left) || higher(TreePath::Right) {
+            Err(ValidationError::Balance("Treap child has a higher priority than its parent"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn state<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Self::State {
+        node.inspect_balance(|b| b.priority)
+    }
+
+    fn adjust_root(&mut self) {
+        // Do nothing
+    }
+}
+
+impl Debug for TreapBalance {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        write!(f, "{:016x}", self.priority)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TreapBalance;
+    use crate::Tree;
+
+    #[test]
+    fn insert_ascending() {
+        let mut tree: Tree<u32, TreapBalance> = Tree::new();
+
+        for key in 0..1000 {
+            tree.insert(key);
+        }
+        // The expected height is about 3 log2 of 1000
+        assert!(tree.height() <= 40);
+        assert!((0..1000).all(|key| tree.search(&key)));
+    }
+
+    #[test]
+    fn seeded_shape() {
+        let build = |seed| {
+            let mut tree: Tree<u32, TreapBalance> = Tree::with_seed(seed);
+            for key in [40, 50, 60, 70, 90, 80, 100, 110] {
+                tree.insert(key);
+            }
+            tree.delete(&60);
+            format!("{:?}", tree)
+        };
+        assert_eq!(build(1), build(1));
+        assert_ne!(build(1), build(2));
+    }
+
+    #[test]
+    fn delete_keeps_order() {
+        let mut tree: Tree<u32, TreapBalance> = Tree::with_seed(3);
+
+        for key in 0..200 {
+            tree.insert(key);
+        }
+        for key in (0..200).step_by(2) {
+            assert_eq!(tree.delete(&key), Some(key));
+        }
+        assert_eq!(tree.len(), 100);
+        assert!((0..200).all(|key| tree.search(&key) == (key % 2 == 1)));
+        assert!(tree.height() <= 25);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn delete_keeps_priorities() {
+        let mut tree: Tree<u32, TreapBalance> = Tree::with_seed(5);
+
+        for key in 0..100 {
+            tree.insert(key);
+            assert_eq!(tree.validate(), Ok(()));
+        }
+        let priorities: Vec<_> = (0..100).map(|key| tree.node_info(&key).unwrap().state).collect();
+        // Deleting the root always removes a node with two children
+        for _ in 0..50 {
+            let root = (0..100).find(|key| tree.node_info(key).is_some_and(|info| info.depth == 1)).unwrap();
+            tree.delete(&root);
+            assert_eq!(tree.validate(), Ok(()));
+        }
+        // The deleted nodes are rotated down and removed, so every other key keeps its priority
+        assert!((0..100).all(|key| tree.node_info(&key).is_none_or(|info| info.state == priorities[key as usize])));
+    }
+}
@@ -1,14 +1,20 @@
-use std::rc::{Rc, Weak};
-use std::fmt::Display;
-use std::fmt::Debug;
-use std::cell::RefCell;
+use alloc::rc::{Rc, Weak};
+use alloc::vec::Vec;
+use core::fmt::Display;
+use core::fmt::Debug;
+use core::cell::RefCell;
+use core::ops::RangeBounds;
 
 mod ops;
+pub mod augment;
+pub mod cache;
 pub mod inspect;
 mod node;
 
-use node::TreeNode;
-use inspect::TreeBalance;
+use augment::Augment;
+use cache::NodeCache;
+use node::{DebugNode, Shape, TreeNode};
+use inspect::{BalanceContext, InspectError, NodeInspector, TreeBalance, ValidationError};
 
 /// Enum for describing the path from one node to its child
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -44,8 +50,56 @@ impl TreePath {
 /// Instead it relies on the balancing instructions provided by the [TreeBalance].
 /// 
 /// Using a [TreeBalance] that doesn't do any balancing will result in just an ordinary binary tree
-pub struct Tree<T: Ord, U: TreeBalance>(Option<TreeBranch<T, U>>);
-impl <T: Ord, U: TreeBalance> Tree<T, U> {
+pub struct Tree<T: Ord, U: TreeBalance, A: Augment<T> = ()> {
+    /// Root of the tree
+    root: Subtree<T, U, A>,
+    /// Number of keys stored in the tree
+    len: usize,
+    /// Number of lazily deleted nodes still in the tree
+    tombstones: usize,
+    /// Lazy deletion settings, if enabled
+    lazy: Option<LazyDelete<T>>,
+    /// Factor of the minimal height the tree may grow to before it is rebuilt, if enabled
+    auto_rebuild: Option<f64>,
+    /// Random number generator used when creating balances
+    rng: TreeRng,
+    /// Number of rotations performed by the balance
+    rotations: usize
+}
+
+/// Seeded random number generator (xorshift64*) owned by a tree
+pub(crate) struct TreeRng(u64);
+
+impl TreeRng {
+    /// Seed used by trees that are not given one
+    pub(crate) const DEFAULT_SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+    /// Creates a generator from the given seed
+    pub(crate) fn new(seed: u64) -> Self {
+        // The state must never be zero
+        TreeRng (if seed == 0 { Self::DEFAULT_SEED } else { seed })
+    }
+
+    /// Returns the next random number
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+/// Settings for lazy deletion
+/// 
+/// See [Tree::with_lazy_delete]
+pub(crate) struct LazyDelete<T> {
+    /// Fraction of nodes that can be tombstones before the tree is compacted
+    pub(crate) fraction: f64,
+    /// Function used to return an owned key while leaving the node in the tree
+    pub(crate) clone: fn(&T) -> T
+}
+
+impl <T: Ord, U: TreeBalance, A: Augment<T>> Tree<T, U, A> {
 
     /// Creates a new empty tree
     /// 
@@ -56,16 +110,45 @@ impl <T: Ord, U: TreeBalance> Tree<T, U> {
     /// assert!(tree.is_empty());
     /// ```
     pub fn new() -> Self {
-        Tree::<T, U>(None)
+        Tree {
+            root: Subtree::new(),
+            len: 0,
+            tombstones: 0,
+            lazy: None,
+            auto_rebuild: None,
+            rng: TreeRng::new(TreeRng::DEFAULT_SEED),
+            rotations: 0
+        }
     }
 
-    /// Creates a new tree, wrapping the given [TreeBranch]
-    fn new_with(branch: TreeBranch<T, U>) -> Self {
-        Tree(Some(branch))
+    /// Creates a new empty tree using the given seed for random numbers
+    /// 
+    /// Balances can draw random numbers when they are created for a new node (see [inspect::BalanceContext]).
+    /// Trees created with the same seed and given the same operations will always have the same shape.
+    /// Trees created with [Tree::new] use a fixed seed.
+    /// 
+    /// ```
+    /// use project2::tree::Tree;
+    /// use project2::treap::TreapBalance;
+    /// let mut first = Tree::<usize, TreapBalance>::with_seed(7);
+    /// let mut second = Tree::<usize, TreapBalance>::with_seed(7);
+    /// for key in 0..100 {
+    ///     first.insert(key);
+    ///     second.insert(key);
+    /// }
+    /// assert_eq!(format!("{:?}", first), format!("{:?}", second));
+    /// ```
+    pub fn with_seed(seed: u64) -> Self {
+        Tree {
+            rng: TreeRng::new(seed),
+            ..Tree::new()
+        }
     }
 
     /// Returns true if the tree has no contents
     /// 
+    /// Lazily deleted keys are not counted, so a tree holding only tombstones is empty.
+    /// 
     /// ```
     /// use project2::tree::Tree;
     /// use project2::avl::AVLBalance;
@@ -79,7 +162,27 @@ impl <T: Ord, U: TreeBalance> Tree<T, U> {
     /// assert!(tree.is_empty());
     /// ```
     pub fn is_empty(&self) -> bool {
-        self.0.is_none()
+        self.len == 0
+    }
+
+    /// Returns the number of keys in the tree
+    /// 
+    /// ```
+    /// use project2::tree::Tree;
+    /// use project2::avl::AVLBalance;
+    /// let mut tree = Tree::<usize, AVLBalance>::new();
+    /// assert_eq!(tree.len(), 0);
+    /// 
+    /// tree.insert(2);
+    /// tree.insert(3);
+    /// tree.insert(3);
+    /// assert_eq!(tree.len(), 2);
+    /// 
+    /// tree.delete(&2);
+    /// assert_eq!(tree.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.len
     }
 
     /// Inserts an element into the tree
@@ -95,8 +198,34 @@ impl <T: Ord, U: TreeBalance> Tree<T, U> {
     /// tree.insert(2);
     /// assert!(tree.search(&2));
     /// ```
+    /// 
+    /// # Panics
+    /// 
+    /// This function panics if the balance fails while rebalancing (see [Tree::try_insert])
     pub fn insert(&mut self, key: T) {
-        *self = ops::bst_insert(std::mem::replace(self, Tree::new()), key)
+        if let Err(error) = self.try_insert(key) {
+            panic!("{}", error)
+        }
+    }
+
+    /// Inserts an element into the tree, returning an error if the balance fails
+    /// 
+    /// Behaves like [Tree::insert] but if the associated [TreeBalance] fails while rebalancing
+    /// (e.g. it rotates around a missing node or returns an offset to an empty node)
+    /// the error is returned instead of panicking.  The key is still inserted
+    /// but the tree may be left unbalanced.
+    /// 
+    /// ```
+    /// use project2::tree::Tree;
+    /// use project2::avl::AVLBalance;
+    /// let mut tree = Tree::<usize, AVLBalance>::new();
+    /// 
+    /// assert!(tree.try_insert(2).is_ok());
+    /// assert!(tree.search(&2));
+    /// ```
+    pub fn try_insert(&mut self, key: T) -> Result<(), InspectError> {
+        let start = self.root.branch().map(Rc::clone);
+        self.insert_from(start, key).1
     }
 
     /// Finds if an element exists in the tree
@@ -117,7 +246,108 @@ impl <T: Ord, U: TreeBalance> Tree<T, U> {
     /// assert!(!tree.search(&2));
     /// ```
     pub fn search(&self, key: &T) -> bool {
-        ops::bst_search(&self, key)
+        ops::bst_search(&self.root, key).is_some_and(|n| !n.borrow().is_tombstone())
+    }
+
+    /// Finds if an element exists in the tree, letting the balance restructure the tree
+    /// 
+    /// Behaves like [Tree::search] but the last node visited by the search is passed to
+    /// [TreeBalance::on_access], allowing self-adjusting balances to move it (e.g. splay trees).
+    /// 
+    /// ```
+    /// # use project2::SplayTree;
+    /// let mut tree = SplayTree::<usize>::new();
+    /// 
+    /// for key in 0..10 {
+    ///     tree.insert(key);
+    /// }
+    /// 
+    /// assert!(tree.search_mut(&3));
+    /// assert!(!tree.search_mut(&10));
+    /// ```
+    pub fn search_mut(&mut self, key: &T) -> bool {
+        match self.root.branch().map(Rc::clone) {
+            Some(root) => {
+                let (node, found) = ops::bst_search_from(root, key);
+                let found = found && !node.borrow().is_tombstone();
+                let (root, checked) = ops::bst_access(core::mem::take(&mut self.root), node, &mut self.rotations);
+                self.root = root;
+                if let Err(error) = checked {
+                    panic!("{}", error)
+                }
+                self.check_invariants();
+                found
+            },
+            None => false
+        }
+    }
+
+    /// Inserts an element into the tree, starting the search at a finger
+    /// 
+    /// Behaves like [Tree::insert] but instead of searching from the root, the search walks up
+    /// from the node the [Finger] points at to the lowest ancestor that can contain the key and
+    /// then back down. Inserting keys close to the previously touched key costs O(log d)
+    /// where d is the distance (in rank) between the keys.
+    /// The finger is moved to the node holding the key afterwards.
+    /// 
+    /// The finger should only be used with the tree it was last used on,
+    /// using it with a different tree will give incorrect results.
+    /// 
+    /// ```
+    /// use project2::tree::{Tree, Finger};
+    /// use project2::avl::AVLBalance;
+    /// let mut tree = Tree::<usize, AVLBalance>::new();
+    /// let mut finger = Finger::new();
+    /// 
+    /// // Insert time ordered keys
+    /// for key in 0..100 {
+    ///     tree.insert_near(&mut finger, key);
+    /// }
+    /// assert!(tree.search(&50));
+    /// assert_eq!(tree.height(), 7);
+    /// ```
+    pub fn insert_near(&mut self, finger: &mut Finger<T, U, A>, key: T) {
+        let start = if self.root.is_empty() {
+            None
+        } else {
+            ops::bst_finger(&self.root, &finger.0, &key)
+        };
+        let (node, checked) = self.insert_from(start, key);
+        finger.0 = Rc::downgrade(&node);
+        if let Err(error) = checked {
+            panic!("{}", error)
+        }
+    }
+
+    /// Finds if an element exists in the tree, starting the search at a finger
+    /// 
+    /// Behaves like [Tree::search] but starts the search from the node the [Finger] points at
+    /// (see [Tree::insert_near]).  The finger is moved to the last node touched by the search.
+    /// 
+    /// ```
+    /// # use project2::tree::{Tree, Finger};
+    /// # use project2::avl::AVLBalance;
+    /// let mut tree = Tree::<usize, AVLBalance>::new();
+    /// let mut finger = Finger::new();
+    /// 
+    /// for key in 0..100 {
+    ///     tree.insert(key * 2);
+    /// }
+    /// 
+    /// assert!(tree.search_near(&mut finger, &50));
+    /// assert!(tree.search_near(&mut finger, &52));
+    /// assert!(!tree.search_near(&mut finger, &53));
+    /// ```
+    pub fn search_near(&self, finger: &mut Finger<T, U, A>, key: &T) -> bool {
+        match ops::bst_finger(&self.root, &finger.0, key) {
+            Some(start) => {
+                let (node, found) = ops::bst_search_from(start, key);
+                let found = found && !node.borrow().is_tombstone();
+                finger.0 = Rc::downgrade(&node);
+                found
+            },
+            None => false
+        }
     }
 
     /// Removes an element from the tree if it exists
@@ -142,12 +372,221 @@ impl <T: Ord, U: TreeBalance> Tree<T, U> {
     /// tree.delete(&2);
     /// assert!(!tree.search(&2) && tree.is_empty());
     /// ```
+    /// 
+    /// # Panics
+    /// 
+    /// This function panics if the balance fails while rebalancing (see [Tree::try_delete])
     pub fn delete(&mut self, key: &T) -> Option<T> {
-        let (root, key) = ops::bst_delete(std::mem::replace(self, Tree::new()), &key);
-        *self = root;
+        self.try_delete(key).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Removes an element from the tree if it exists, returning an error if the balance fails
+    /// 
+    /// Behaves like [Tree::delete] but if the associated [TreeBalance] fails while rebalancing
+    /// (e.g. it rotates around a missing node or returns an offset to an empty node)
+    /// the error is returned instead of panicking.  The key is still removed
+    /// but the tree may be left unbalanced.
+    /// 
+    /// ```
+    /// use project2::tree::Tree;
+    /// use project2::avl::AVLBalance;
+    /// let mut tree = Tree::<usize, AVLBalance>::new();
+    /// tree.insert(2);
+    /// 
+    /// assert_eq!(tree.try_delete(&2), Ok(Some(2)));
+    /// assert_eq!(tree.try_delete(&2), Ok(None));
+    /// ```
+    pub fn try_delete(&mut self, key: &T) -> Result<Option<T>, InspectError> {
+        let start = self.root.branch().map(Rc::clone);
+        let (key, _, checked) = self.delete_from(start, key);
+        checked.map(|_| key)
+    }
+
+    /// Removes an element from the tree if it exists, starting the search at a finger
+    /// 
+    /// Behaves like [Tree::delete] but starts the search from the node the [Finger] points at
+    /// (see [Tree::insert_near]).  The finger is moved next to where the deletion took place.
+    /// 
+    /// ```
+    /// # use project2::tree::{Tree, Finger};
+    /// # use project2::avl::AVLBalance;
+    /// let mut tree = Tree::<usize, AVLBalance>::new();
+    /// let mut finger = Finger::new();
+    /// 
+    /// for key in 0..100 {
+    ///     tree.insert_near(&mut finger, key);
+    /// }
+    /// 
+    /// assert_eq!(tree.delete_near(&mut finger, &99), Some(99));
+    /// assert_eq!(tree.delete_near(&mut finger, &98), Some(98));
+    /// assert_eq!(tree.delete_near(&mut finger, &98), None);
+    /// ```
+    pub fn delete_near(&mut self, finger: &mut Finger<T, U, A>, key: &T) -> Option<T> {
+        let start = ops::bst_finger(&self.root, &finger.0, key);
+        let (key, near, checked) = self.delete_from(start, key);
+        finger.0 = near.map_or_else(Weak::new, |n| Rc::downgrade(&n));
+        if let Err(error) = checked {
+            panic!("{}", error)
+        }
         key
     }
 
+    /// Inserts the key searching from the given start node, returning the node holding the key
+    /// and the first error the balance failed with
+    /// 
+    /// Revives the node if the key was lazily deleted
+    fn insert_from(&mut self, start: Option<TreeBranch<T, U, A>>, key: T) -> (TreeBranch<T, U, A>, Result<(), InspectError>) {
+        let (root, node, inserted, checked) = ops::bst_insert_from(core::mem::take(&mut self.root), start, key, &mut self.rng, &mut self.rotations);
+        let (root, accessed) = ops::bst_access(root, Rc::clone(&node), &mut self.rotations);
+        self.root = root;
+        if inserted {
+            self.len += 1;
+        } else if node.borrow().is_tombstone() {
+            ops::bst_set_tombstone(&node, false);
+            self.tombstones -= 1;
+            self.len += 1;
+        }
+        let checked = checked.and(accessed);
+        if checked.is_ok() {
+            self.check_invariants();
+            if inserted {
+                self.rebuild_if_needed(&node);
+            }
+        }
+        (node, checked)
+    }
+
+    /// Deletes the key searching from the given start node
+    /// 
+    /// Returns the key, the node closest to where the deletion took place and the first error the balance failed with.
+    /// If lazy deletion is enabled the node is marked as a tombstone instead of being removed.
+    fn delete_from(&mut self, start: Option<TreeBranch<T, U, A>>, key: &T) -> DeleteOutcome<T, U, A> {
+        if let Some(clone) = self.lazy.as_ref().map(|lazy| lazy.clone) {
+            let node = match start {
+                Some(start) => ops::bst_search_from(start, key),
+                None => return (None, None, Ok(()))
+            };
+            let key = match &node {
+                (node, true) if !node.borrow().is_tombstone() => {
+                    ops::bst_set_tombstone(node, true);
+                    Some(clone(node.borrow().get_key()))
+                },
+                _ => None
+            };
+            let (root, checked) = ops::bst_access(core::mem::take(&mut self.root), node.0, &mut self.rotations);
+            self.root = root;
+            if key.is_some() {
+                self.len -= 1;
+                self.tombstones += 1;
+                self.compact_if_needed();
+            }
+            if checked.is_ok() {
+                self.check_invariants();
+            }
+            // Use the root in case we compacted
+            (key, self.root.branch().map(Rc::clone), checked)
+        } else {
+            let (root, key, near, checked) = ops::bst_delete_from(core::mem::take(&mut self.root), start, key, &mut self.rotations);
+            let (root, accessed) = match &near {
+                Some(near) => ops::bst_access(root, Rc::clone(near), &mut self.rotations),
+                None => (root, Ok(()))
+            };
+            self.root = root;
+            if key.is_some() {
+                self.len -= 1;
+            }
+            let checked = checked.and(accessed);
+            if checked.is_ok() {
+                self.check_invariants();
+            }
+            (key, near, checked)
+        }
+    }
+
+    /// Applies a batch of insert and delete operations to the tree
+    /// 
+    /// The batch is sorted by key (keeping the order of operations on the same key) and applied in a single
+    /// descent from the root, splitting the batch around the key of each node so only the paths down to the
+    /// keys of the batch are visited, each once.  Inserted keys are linked in where they fall off the bottom
+    /// of the tree and deleted nodes are removed where they are found.  The balance rebalances the subtree
+    /// around each change, and the nodes above it are updated once as the descent moves back up past them.
+    /// A batch at least as large as the tree touches every part of it, so it is instead merged with the tree
+    /// in a single in order pass and the tree is rebuilt in O(n + m)
+    /// (see [Tree::rebuild], balances that cannot be rebuilt have the nodes appended again as in [Tree::compact]).
+    /// 
+    /// If lazy deletion is enabled deleted keys are marked as tombstones (see [Tree::with_lazy_delete]),
+    /// but merging the batch drops every tombstone.
+    /// 
+    /// Returns the outcome of each operation in the order the operations were given.
+    /// 
+    /// ```
+    /// use project2::tree::{Tree, Op, OpOutcome};
+    /// use project2::avl::AVLBalance;
+    /// let mut tree = Tree::<usize, AVLBalance>::new();
+    /// tree.insert(2);
+    /// 
+    /// let outcomes = tree.apply_batch(vec![
+    ///     Op::Insert(3),
+    ///     Op::Delete(2),
+    ///     Op::Insert(1),
+    ///     Op::Insert(3),
+    ///     Op::Delete(4)
+    /// ]);
+    /// 
+    /// assert_eq!(outcomes, vec![
+    ///     OpOutcome::Inserted,
+    ///     OpOutcome::Deleted(2),
+    ///     OpOutcome::Inserted,
+    ///     OpOutcome::Exists,
+    ///     OpOutcome::Missing
+    /// ]);
+    /// assert!(tree.search(&1) && !tree.search(&2) && tree.search(&3));
+    /// ```
+    /// 
+    /// # Panics
+    /// 
+    /// This function panics if the balance fails while rebalancing (see [Tree::try_insert] and [Tree::try_delete])
+    pub fn apply_batch<I: IntoIterator<Item = Op<T>>>(&mut self, batch: I) -> Vec<OpOutcome<T>> {
+        let mut batch: Vec<(usize, Op<T>)> = batch.into_iter().enumerate().collect();
+        // Stable sort so operations on the same key happen in the given order
+        batch.sort_by(|a, b| a.1.key().cmp(b.1.key()));
+
+        // A batch at least as large as the tree touches every part of it,
+        // so it is cheaper to merge the batch into the tree and rebuild it in O(n + m)
+        if batch.len() >= self.len {
+            let (root, outcomes) = ops::bst_merge_batch(core::mem::take(&mut self.root), batch, &mut self.rng);
+            self.root = root;
+            // Merging drops every tombstone
+            self.tombstones = 0;
+            return self.finish_batch(outcomes, Ok(()))
+        }
+
+        let lazy = self.lazy.as_ref().map(|lazy| lazy.clone);
+        let (root, outcomes, checked) = ops::bst_apply_batch(core::mem::take(&mut self.root), batch, lazy, &mut self.rng, &mut self.tombstones, &mut self.rotations);
+        self.root = root;
+        self.finish_batch(outcomes, checked)
+    }
+
+    /// Counts the keys inserted and deleted by a batch applied by [Tree::apply_batch],
+    /// returning the outcomes in the order the operations were given
+    fn finish_batch(&mut self, mut outcomes: Vec<(usize, OpOutcome<T>)>, checked: Result<(), InspectError>) -> Vec<OpOutcome<T>> {
+        for (_, outcome) in outcomes.iter() {
+            match outcome {
+                OpOutcome::Inserted => self.len += 1,
+                OpOutcome::Deleted(_) => self.len -= 1,
+                _ => ()
+            }
+        }
+        if let Err(error) = checked {
+            panic!("{}", error)
+        }
+        self.compact_if_needed();
+        self.check_invariants();
+
+        outcomes.sort_by_key(|(i, _)| *i);
+        outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+    }
+
     /// Get the height of the tree
     /// 
     /// Returns the length of the longest path from the root node to any leaf node
@@ -168,7 +607,7 @@ impl <T: Ord, U: TreeBalance> Tree<T, U> {
     /// assert_eq!(tree.height(), 2);
     /// ```
     pub fn height(&self) -> usize {
-        self.0.as_ref().map_or(0, |node| node.borrow().get_height())
+        self.root.branch().map_or(0, |node| node.borrow().get_height())
     }
 
     /// Get the number of leaves of the tree
@@ -195,7 +634,176 @@ impl <T: Ord, U: TreeBalance> Tree<T, U> {
     /// assert_eq!(tree.leaves(), 2);
     /// ```
     pub fn leaves(&self) -> usize {
-        self.0.as_ref().map_or(0, |node| node.borrow().get_leaves())
+        self.root.branch().map_or(0, |node| node.borrow().get_leaves())
+    }
+
+    /// Get the aggregate of the keys within the given range
+    ///
+    /// Combines the keys in the range using the associated [Augment] in O(log n) for a balanced tree,
+    /// returning [Augment::empty] if there are none.  Lazily deleted keys are left out
+    /// (see [Tree::with_lazy_delete]).
+    ///
+    /// ```
+    /// use project2::tree::Tree;
+    /// use project2::tree::augment::Sum;
+    /// use project2::avl::AVLBalance;
+    /// let mut tree = Tree::<u32, AVLBalance, Sum>::new();
+    /// for key in 1..=10 {
+    ///     tree.insert(key);
+    /// }
+    ///
+    /// assert_eq!(tree.aggregate(3..6), 3 + 4 + 5);
+    /// assert_eq!(tree.aggregate(8..), 8 + 9 + 10);
+    ///
+    /// // Deleted keys are no longer included
+    /// tree.delete(&4);
+    /// assert_eq!(tree.aggregate(3..6), 3 + 5);
+    /// assert_eq!(tree.aggregate(20..30), 0);
+    /// ```
+    pub fn aggregate<R: RangeBounds<T>>(&self, range: R) -> A::Value {
+        ops::bst_aggregate(&self.root, &range)
+    }
+
+    /// Describes the node holding the given key
+    /// 
+    /// Returns the depth of the node, the height and leaf count of the tree it roots, and the
+    /// state the associated [TreeBalance] keeps for it (see [TreeBalance::state]), or None if the key
+    /// is not in the tree.  The height and leaf count take O(n) if the balance does not cache them.
+    /// 
+    /// ```
+    /// use project2::RedBlackTree;
+    /// use project2::redblack::RBColor;
+    /// let mut tree = RedBlackTree::new();
+    /// for key in 1..=3 {
+    ///     tree.insert(key);
+    /// }
+    /// 
+    /// let info = tree.node_info(&1).unwrap();
+    /// assert_eq!((info.depth, info.height, info.leaves), (2, 1, 1));
+    /// assert_eq!(info.state, RBColor::Red);
+    /// assert_eq!(tree.node_info(&2).unwrap().state, RBColor::Black);
+    /// assert!(tree.node_info(&4).is_none());
+    /// ```
+    pub fn node_info(&self, key: &T) -> Option<NodeInfo<U::State>> {
+        let node = ops::bst_search(&self.root, key).filter(|n| !n.borrow().is_tombstone())?;
+        let (height, leaves) = {
+            let n = node.borrow();
+            (n.get_height(), n.get_leaves())
+        };
+        Some(NodeInfo {
+            depth: ops::bst_depth(&node),
+            height,
+            leaves,
+            state: U::state(&NodeInspector::open(node))
+        })
+    }
+
+    /// Calls a function to inspect the root of the tree, if there is one
+    /// 
+    /// Used by balance specific queries, the function must not change the tree
+    pub(crate) fn inspect_root<F, R>(&self, inspect: F) -> Option<R> where F: FnOnce(&NodeInspector<T, U, A>) -> R {
+        self.root.branch().map(|root| inspect(&NodeInspector::open(Rc::clone(root))))
+    }
+
+    /// Returns the number of rotations the associated [TreeBalance] has performed on the tree
+    /// 
+    /// Counts the rotations done while rebalancing after insertions and deletions and while restructuring
+    /// on the way down or after an access (a double rotation counts as two).  Rebuilding the tree
+    /// (e.g. when compacting or converting it) is not counted.
+    /// 
+    /// ```
+    /// use project2::tree::Tree;
+    /// use project2::avl::AVLBalance;
+    /// let mut tree = Tree::<usize, AVLBalance>::new();
+    /// 
+    /// tree.insert(1);
+    /// tree.insert(2);
+    /// assert_eq!(tree.rotations(), 0);
+    /// 
+    /// tree.insert(3);
+    /// assert_eq!(tree.rotations(), 1);
+    /// ```
+    pub fn rotations(&self) -> usize {
+        self.rotations
+    }
+
+    /// Checks that the tree is still correct
+    /// 
+    /// Checks that the keys are in order, that the nodes link back to their parents, that the cached
+    /// heights, leaf counts and sizes are up to date and that [Tree::len] and [Tree::tombstones] match the keys
+    /// in the tree.  Then checks the invariants of the associated [TreeBalance] (see [TreeBalance::verify]).
+    /// Runs in O(n) plus the cost of the balance's checks.
+    /// 
+    /// With the `debug-invariants` feature enabled the tree is validated after every operation that changes it.
+    /// 
+    /// ```
+    /// use project2::tree::Tree;
+    /// use project2::redblack::RedBlackBalance;
+    /// let mut tree = Tree::<usize, RedBlackBalance>::new();
+    /// 
+    /// for key in 0..100 {
+    ///     tree.insert(key);
+    /// }
+    /// assert_eq!(tree.validate(), Ok(()));
+    /// ```
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let (keys, tombstones) = ops::bst_validate(&self.root)?;
+        if keys != self.len || tombstones != self.tombstones {
+            return Err(ValidationError::Length)
+        }
+        Ok(())
+    }
+
+    /// Converts the tree to use a different balance
+    /// 
+    /// The keys are moved into new nodes (one allocation per key, as the type of the nodes changes) linked in
+    /// the same shape as the tree, without being compared and keeping lazily deleted keys.  Each node is given a
+    /// balance from its height and its parent's height by [TreeBalance::new_rebuilt] in a single O(n) pass, and
+    /// the result is checked with [TreeBalance::verify].  Converting between AVL and red black trees (in either
+    /// direction after a conversion the other way) always keeps the shape.  If the shape is not valid for the new
+    /// balance the tree is rebuilt into a minimal height tree instead, and if the new balance cannot describe a tree
+    /// from its shape (e.g. a treap) the nodes are appended in order, rebalancing after each as if it was inserted,
+    /// which is amortized O(n) for the balances of this crate.  Lazy deletion settings and the seed of the tree are carried over.
+    /// 
+    /// ```
+    /// use project2::{AVLTree, RedBlackTree};
+    /// let mut tree = AVLTree::new();
+    /// for key in 0..100 {
+    ///     tree.insert(key);
+    /// }
+    /// let height = tree.height();
+    /// 
+    /// let tree: RedBlackTree<usize> = tree.rebalance_as();
+    /// assert_eq!(tree.height(), height);
+    /// assert_eq!(tree.len(), 100);
+    /// assert_eq!(tree.validate(), Ok(()));
+    /// ```
+    pub fn rebalance_as<B: TreeBalance>(mut self) -> Tree<T, B, A> {
+        let (root, tombstones) = ops::bst_convert(self.root, &mut self.rng);
+        let tree = Tree {
+            root,
+            len: self.len,
+            tombstones,
+            lazy: self.lazy,
+            auto_rebuild: self.auto_rebuild,
+            rng: self.rng,
+            rotations: self.rotations
+        };
+        tree.check_invariants();
+        tree
+    }
+
+    /// Validates the tree if the `debug-invariants` feature is enabled
+    /// 
+    /// # Panics
+    /// 
+    /// This function panics if the tree is not valid
+    fn check_invariants(&self) {
+        if cfg!(feature = "debug-invariants") {
+            if let Err(error) = self.validate() {
+                panic!("{}", error)
+            }
+        }
     }
 
     /// Clears the contents of the tree
@@ -216,40 +824,354 @@ impl <T: Ord, U: TreeBalance> Tree<T, U> {
     /// assert_eq!(tree.height(), 0);
     /// ```
     pub fn clear(&mut self) {
-        self.0 = None;
+        self.root = Subtree::new();
+        self.len = 0;
+        self.tombstones = 0;
+    }
+
+    /// Returns the number of lazily deleted nodes still in the tree
+    /// 
+    /// See [Tree::with_lazy_delete]
+    pub fn tombstones(&self) -> usize {
+        self.tombstones
+    }
+
+    /// Removes all lazily deleted nodes from the tree
+    /// 
+    /// The remaining nodes are reused and rebuilt into a minimal height tree in O(n)
+    /// if the associated [TreeBalance] supports it (see [TreeBalance::new_rebuilt]),
+    /// otherwise they are appended in order, rebalancing after each as if it was inserted
+    /// (amortized O(n) for the balances of this crate).
+    /// 
+    /// ```
+    /// use project2::tree::Tree;
+    /// use project2::redblack::RedBlackBalance;
+    /// let mut tree = Tree::<usize, RedBlackBalance>::with_lazy_delete(1.0);
+    /// 
+    /// for key in 0..100 {
+    ///     tree.insert(key);
+    /// }
+    /// for key in 0..90 {
+    ///     tree.delete(&key);
+    /// }
+    /// assert_eq!(tree.tombstones(), 90);
+    /// 
+    /// tree.compact();
+    /// assert_eq!(tree.tombstones(), 0);
+    /// assert_eq!(tree.height(), 4);
+    /// ```
+    pub fn compact(&mut self) {
+        if self.tombstones > 0 {
+            self.root = ops::bst_compact(core::mem::take(&mut self.root), &mut self.rng);
+            self.tombstones = 0;
+            self.check_invariants();
+        }
+    }
+
+    /// Rebuilds the tree into a tree of minimal height
+    /// 
+    /// The nodes are rearranged in place using rotations (the Day–Stout–Warren algorithm) into a complete
+    /// tree, where every level other than the last is full, in O(n) time and O(1) extra space.  Each node is
+    /// then given a new balance from [TreeBalance::new_rebuilt].  Lazily deleted nodes are kept.
+    /// The rotations are not counted by [Tree::rotations].
+    /// 
+    /// Returns false and leaves the tree unchanged if the associated [TreeBalance] does not support being rebuilt.
+    /// 
+    /// ```
+    /// use project2::BinarySearchTree;
+    /// let mut tree = BinarySearchTree::new();
+    /// for key in 0..100 {
+    ///     tree.insert(key);
+    /// }
+    /// assert_eq!(tree.height(), 100);
+    /// 
+    /// assert!(tree.rebuild());
+    /// assert_eq!(tree.height(), 7);
+    /// assert_eq!(tree.len(), 100);
+    /// ```
+    pub fn rebuild(&mut self) -> bool {
+        if U::new_rebuilt(1, None).is_none() {
+            return false
+        }
+        self.root = ops::bst_dsw(core::mem::take(&mut self.root));
+        self.check_invariants();
+        true
+    }
+
+    /// Creates a new empty tree that rebuilds itself when it grows too tall
+    /// 
+    /// After an insertion leaves the new key more than `factor` times the minimal height for the
+    /// number of keys (about log2(n)) below the root, the tree is rebuilt (see [Tree::rebuild]).
+    /// Finding the depth of the key costs as much as the insertion itself.  Meant for trees that do
+    /// not balance themselves (e.g. [BinarySearchTree](crate::BinarySearchTree)), it has no effect
+    /// if the associated [TreeBalance] does not support being rebuilt.
+    /// 
+    /// Sorted insertions still extend a single path, so a rebuild happens every O(log n) insertions,
+    /// which costs O(n / log n) per insertion instead of O(n).
+    /// 
+    /// # Panics
+    /// 
+    /// This function panics if `factor` is less than 1
+    /// 
+    /// ```
+    /// use project2::BinarySearchTree;
+    /// let mut tree = BinarySearchTree::with_auto_rebuild(2.0);
+    /// for key in 0..1000 {
+    ///     tree.insert(key);
+    /// }
+    /// // The minimal height for 1000 keys is 10
+    /// assert!(tree.height() <= 20);
+    /// ```
+    pub fn with_auto_rebuild(factor: f64) -> Self {
+        let mut tree = Tree::new();
+        tree.set_auto_rebuild(Some(factor));
+        tree
+    }
+
+    /// Enables or disables rebuilding the tree when it grows too tall
+    /// 
+    /// See [Tree::with_auto_rebuild].  Enabling it does not rebuild the tree until the next insertion.
+    /// 
+    /// # Panics
+    /// 
+    /// This function panics if `factor` is less than 1
+    pub fn set_auto_rebuild(&mut self, factor: Option<f64>) {
+        if let Some(factor) = factor {
+            assert!(factor >= 1.0, "factor must be at least 1");
+        }
+        self.auto_rebuild = factor;
+    }
+
+    /// Rebuilds the tree if the given newly inserted node is deeper than the configured limit
+    fn rebuild_if_needed(&mut self, node: &TreeBranch<T, U, A>) {
+        if let Some(factor) = self.auto_rebuild {
+            let minimal = (usize::BITS - self.len.leading_zeros()) as f64;
+            if ops::bst_depth(node) as f64 > factor * minimal {
+                self.rebuild();
+            }
+        }
+    }
+
+    /// Compacts the tree if the fraction of tombstones exceeds the configured limit
+    fn compact_if_needed(&mut self) {
+        if let Some(lazy) = &self.lazy {
+            if self.tombstones as f64 > lazy.fraction * (self.tombstones + self.len) as f64 {
+                self.compact();
+            }
+        }
+    }
+
+}
+
+impl <T: Ord + Clone, U: TreeBalance, A: Augment<T>> Tree<T, U, A> {
+
+    /// Creates a new empty tree that deletes lazily
+    /// 
+    /// Instead of removing nodes (and rebalancing the tree) [Tree::delete] marks the node as a tombstone
+    /// which is skipped by searches, printing, and [Tree::len].  Once the tombstones make up more than
+    /// `fraction` of the nodes in the tree, the tree is compacted (see [Tree::compact]).
+    /// Inserting a lazily deleted key brings the node back to life.
+    /// 
+    /// Since the key stays in the tree, deleting returns a clone of the key.
+    /// 
+    /// # Panics
+    /// 
+    /// This function panics if `fraction` is not in the range (0, 1]
+    /// 
+    /// ```
+    /// use project2::tree::Tree;
+    /// use project2::redblack::RedBlackBalance;
+    /// let mut tree = Tree::<usize, RedBlackBalance>::with_lazy_delete(0.5);
+    /// 
+    /// for key in 0..10 {
+    ///     tree.insert(key);
+    /// }
+    /// assert_eq!(tree.delete(&2), Some(2));
+    /// assert!(!tree.search(&2));
+    /// assert_eq!(tree.len(), 9);
+    /// assert_eq!(tree.tombstones(), 1);
+    /// 
+    /// // Deleting more than half of the keys compacts the tree
+    /// for key in 3..8 {
+    ///     tree.delete(&key);
+    /// }
+    /// assert_eq!(tree.tombstones(), 0);
+    /// assert_eq!(format!("{}", tree), "Tree: { 0, 1, 8, 9, }");
+    /// ```
+    pub fn with_lazy_delete(fraction: f64) -> Self {
+        let mut tree = Tree::new();
+        tree.set_lazy_delete(Some(fraction));
+        tree
+    }
+
+    /// Enables or disables lazy deletion
+    /// 
+    /// See [Tree::with_lazy_delete].  Disabling lazy deletion compacts the tree.
+    /// 
+    /// # Panics
+    /// 
+    /// This function panics if `fraction` is not in the range (0, 1]
+    pub fn set_lazy_delete(&mut self, fraction: Option<f64>) {
+        match fraction {
+            Some(fraction) => {
+                assert!(fraction > 0.0 && fraction <= 1.0, "fraction must be in the range (0, 1]");
+                self.lazy = Some(LazyDelete {
+                    fraction,
+                    clone: T::clone
+                });
+                self.compact_if_needed();
+            },
+            None => {
+                self.lazy = None;
+                self.compact();
+            }
+        }
+    }
+
+}
+
+impl <T: Ord, U: TreeBalance, A: Augment<T>> Default for Tree<T, U, A> {
+    fn default() -> Self {
+        Tree::new()
+    }
+}
+
+/// Link to a (possibly empty) subtree
+/// 
+/// Used to point to the root of a [Tree] and
+/// to the children of each [TreeNode]
+struct Subtree<T: Ord, U: TreeBalance, A: Augment<T>>(Option<TreeBranch<T, U, A>>);
+impl <T: Ord, U: TreeBalance, A: Augment<T>> Subtree<T, U, A> {
+
+    /// Creates a new empty subtree
+    fn new() -> Self {
+        Subtree(None)
+    }
+
+    /// Creates a new subtree, wrapping the given [TreeBranch]
+    fn new_with(branch: TreeBranch<T, U, A>) -> Self {
+        Subtree(Some(branch))
+    }
+
+    /// Returns true if the subtree has no contents
+    fn is_empty(&self) -> bool {
+        self.0.is_none()
     }
 
     /// Get a reference to the branch this wraps
     /// 
-    /// Returns a reference to the [TreeBranch] that this tree wraps
+    /// Returns a reference to the [TreeBranch] that this subtree wraps
     /// to make traversal more convenient when performing operations on the tree
-    fn branch(&self) -> Option<&TreeBranch<T, U>> {
+    fn branch(&self) -> Option<&TreeBranch<T, U, A>> {
         self.0.as_ref()
     }
 
     /// Get the branch this wraps
     /// 
-    /// Returns the [TreeBranch] that this [Tree] wraps
-    /// consuming the [Tree] in the process
-    fn into_inner(self) -> Option<TreeBranch<T, U>> {
+    /// Returns the [TreeBranch] that this [Subtree] wraps
+    /// consuming the [Subtree] in the process
+    fn into_inner(self) -> Option<TreeBranch<T, U, A>> {
         self.0
     }
 
 }
 
+impl <T: Ord, U: TreeBalance, A: Augment<T>> Default for Subtree<T, U, A> {
+    fn default() -> Self {
+        Subtree::new()
+    }
+}
+
+/// Operation on a [Tree] that can be applied as part of a batch
+/// 
+/// See [Tree::apply_batch]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op<T> {
+    /// Insert the key into the tree
+    Insert(T),
+    /// Remove the key from the tree
+    Delete(T)
+}
+
+impl <T> Op<T> {
+
+    /// Returns the key the operation acts on
+    pub fn key(&self) -> &T {
+        match self {
+            Op::Insert(key) => key,
+            Op::Delete(key) => key
+        }
+    }
+
+}
+
+/// Outcome of an [Op] applied as part of a batch
+/// 
+/// See [Tree::apply_batch]
+#[derive(Clone, Debug, PartialEq)]
+pub enum OpOutcome<T> {
+    /// The key was inserted into the tree
+    Inserted,
+    /// The key to insert was already in the tree
+    Exists,
+    /// The key was removed from the tree, holds the removed key
+    Deleted(T),
+    /// The key to remove was not in the tree
+    Missing
+}
+
+/// Description of the node holding a key in a [Tree]
+/// 
+/// See [Tree::node_info]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeInfo<S> {
+    /// Depth of the node, the root is at depth one
+    pub depth: usize,
+    /// Height of the tree rooted by the node
+    pub height: usize,
+    /// Number of leaves of the tree rooted by the node
+    pub leaves: usize,
+    /// State the associated [TreeBalance] keeps for the node
+    pub state: S
+}
+
+/// Remembered position in a [Tree] used to speed up nearby operations
+/// 
+/// A finger points at the last node touched by an operation such as [Tree::search_near]
+/// or [Tree::insert_near].  Operations that start at a finger are faster than starting at
+/// the root when consecutive keys are close to each other (i.e. time ordered events).
+/// 
+/// A new finger (or one whose node has been removed from the tree) starts at the root.
+pub struct Finger<T: Ord, U: TreeBalance, A: Augment<T> = ()>(TreeTrunk<T, U, A>);
+impl <T: Ord, U: TreeBalance, A: Augment<T>> Finger<T, U, A> {
+
+    /// Creates a new finger that starts at the root of a tree
+    pub fn new() -> Self {
+        Finger(Weak::new())
+    }
+
+}
+
+impl <T: Ord, U: TreeBalance, A: Augment<T>> Default for Finger<T, U, A> {
+    fn default() -> Self {
+        Finger::new()
+    }
+}
 
 /// Shorthand type for pointer to a shared [TreeNode]
-type TreeBranch<T, U> = Rc<RefCell<TreeNode<T, U>>>;
+type TreeBranch<T, U, A> = Rc<RefCell<TreeNode<T, U, A>>>;
 /// Shorthand type for pointer to a parent [TreeNode]
-type TreeTrunk<T, U> = Weak<RefCell<TreeNode<T, U>>>;
+type TreeTrunk<T, U, A> = Weak<RefCell<TreeNode<T, U, A>>>;
+/// Shorthand type for the result of [Tree::delete_from]
+type DeleteOutcome<T, U, A> = (Option<T>, Option<TreeBranch<T, U, A>>, Result<(), InspectError>);
 
-impl <T, U> Display for Tree<T, U>
+impl <T, U, A: Augment<T>> Display for Tree<T, U, A>
 where
     T: Ord + Display,
     U: TreeBalance
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        match &self.0 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        match self.root.branch() {
             Some(node) => {
                 write!(f, "Tree: {{ {}}}", node.borrow())
             }
@@ -258,15 +1180,17 @@ where
     }
 }
 
-impl <T, U> Debug for Tree<T, U>
+impl <T, U, A: Augment<T>> Debug for Tree<T, U, A>
 where
     T: Ord + Debug,
     U: TreeBalance + Debug
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        match &self.0 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        match self.root.branch() {
             Some(node) => {
-                node.borrow().fmt(f)
+                // Measure the whole tree first so each node's height and leaves are not recomputed
+                let node = node.borrow();
+                DebugNode(&node, &Shape::measure(&node)).fmt(f)
             }
             None => Ok(())
         }
@@ -289,4 +1213,86 @@ trait IntoInner {
 trait Open {
     type Target;
     fn open(target: Self::Target) -> Self;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Op, OpOutcome, Tree, TreeRng};
+    use super::inspect::TreeBalance;
+
+    /// Checks that applying a batch of size `size` gives the same
+    /// result as applying the operations in the batch one at a time
+    fn check_batch<U: TreeBalance>(size: usize) {
+        let mut batched: Tree<u32, U> = Tree::new();
+        let mut repeated: Tree<u32, U> = Tree::new();
+        for key in (0..500).step_by(2) {
+            batched.insert(key);
+            repeated.insert(key);
+        }
+
+        let mut rng = TreeRng::new(7);
+        let batch: Vec<Op<u32>> = (0..size).map(|_| {
+            let random = rng.next_u64();
+            let key = (random >> 8) as u32 % 600;
+            if random & 1 == 0 { Op::Insert(key) } else { Op::Delete(key) }
+        }).collect();
+
+        let outcomes = batched.apply_batch(batch.clone());
+        for (op, outcome) in batch.into_iter().zip(outcomes) {
+            match op {
+                Op::Insert(key) => {
+                    let exists = repeated.search(&key);
+                    repeated.insert(key);
+                    assert_eq!(outcome, if exists { OpOutcome::Exists } else { OpOutcome::Inserted });
+                },
+                Op::Delete(key) => {
+                    assert_eq!(outcome, repeated.delete(&key).map_or(OpOutcome::Missing, OpOutcome::Deleted));
+                }
+            }
+        }
+
+        // Both trees should hold the same keys
+        assert_eq!(format!("{}", batched), format!("{}", repeated));
+        assert_eq!(batched.len(), repeated.len());
+
+        // The trees should keep working after the batch
+        for key in (0..600).step_by(3) {
+            assert_eq!(batched.delete(&key), repeated.delete(&key));
+            batched.insert(key + 1);
+            repeated.insert(key + 1);
+        }
+        assert_eq!(format!("{}", batched), format!("{}", repeated));
+        assert_eq!(batched.validate(), Ok(()));
+        assert_eq!(repeated.validate(), Ok(()));
+    }
+
+    #[test]
+    fn apply_batch_merge() {
+        check_batch::<crate::avl::AVLBalance>(1000);
+        check_batch::<crate::redblack::RedBlackBalance>(1000);
+        check_batch::<crate::redblack::TopDownRedBlackBalance>(1000);
+        check_batch::<crate::redblack::LeftLeaningRedBlackBalance>(1000);
+        check_batch::<crate::splay::SplayBalance>(1000);
+        check_batch::<crate::scapegoat::ScapegoatBalance>(1000);
+        check_batch::<crate::treap::TreapBalance>(1000);
+        check_batch::<crate::aa::AABalance>(1000);
+        check_batch::<crate::wavl::WAVLBalance>(1000);
+        check_batch::<crate::weight::WeightBalance>(1000);
+    }
+
+    #[test]
+    fn apply_batch_descent() {
+        for size in [10, 200] {
+            check_batch::<crate::avl::AVLBalance>(size);
+            check_batch::<crate::redblack::RedBlackBalance>(size);
+            check_batch::<crate::redblack::TopDownRedBlackBalance>(size);
+            check_batch::<crate::redblack::LeftLeaningRedBlackBalance>(size);
+            check_batch::<crate::splay::SplayBalance>(size);
+            check_batch::<crate::scapegoat::ScapegoatBalance>(size);
+            check_batch::<crate::treap::TreapBalance>(size);
+            check_batch::<crate::aa::AABalance>(size);
+            check_batch::<crate::wavl::WAVLBalance>(size);
+            check_batch::<crate::weight::WeightBalance>(size);
+        }
+    }
 }
\ No newline at end of file
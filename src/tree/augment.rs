@@ -0,0 +1,232 @@
+use core::ops::Add;
+
+/// Augmentation of a [Tree](super::Tree) that caches an aggregate of the keys of every subtree
+///
+/// Each node stores the value of [Augment::combine] applied to the aggregates of its children and its own key,
+/// or of [Augment::merge] applied to the aggregates of its children if its key is lazily deleted.
+/// The value is kept up to date through insertions, deletions and rotations the same as the cached heights,
+/// so aggregates over a range of keys can be answered in O(log n) (see [Tree::aggregate](super::Tree::aggregate)).
+///
+/// The aggregates must form a monoid: combining must be associative and [Augment::empty] must be its identity,
+/// otherwise the result of a query depends on the shape of the tree.
+///
+/// ```
+/// use project2::tree::Tree;
+/// use project2::tree::augment::Augment;
+/// use project2::avl::AVLBalance;
+///
+/// // Largest value of a range of (key, value) pairs
+/// struct MaxValue();
+///
+/// impl Augment<(u32, u32)> for MaxValue {
+///     type Value = Option<u32>;
+///
+///     fn empty() -> Option<u32> {
+///         None
+///     }
+///
+///     fn combine(left: &Option<u32>, key: &(u32, u32), right: &Option<u32>) -> Option<u32> {
+///         (*left).max(Some(key.1)).max(*right)
+///     }
+///
+///     fn merge(left: &Option<u32>, right: &Option<u32>) -> Option<u32> {
+///         (*left).max(*right)
+///     }
+/// }
+///
+/// let mut tree: Tree<(u32, u32), AVLBalance, MaxValue> = Tree::new();
+/// for (key, value) in [(1, 40), (2, 10), (3, 30), (4, 20)] {
+///     tree.insert((key, value));
+/// }
+/// assert_eq!(tree.aggregate((2, 0)..(4, 0)), Some(30));
+/// assert_eq!(tree.aggregate(..), Some(40));
+/// assert_eq!(tree.aggregate((5, 0)..), None);
+/// ```
+pub trait Augment<T> {
+    /// Aggregate cached for each subtree
+    type Value: Clone;
+
+    /// Returns the aggregate of an empty tree
+    fn empty() -> Self::Value;
+
+    /// Returns the aggregate of a tree with the given key at its root from the aggregates of its subtrees
+    fn combine(left: &Self::Value, key: &T, right: &Self::Value) -> Self::Value;
+
+    /// Returns the aggregate of a tree whose root's key is lazily deleted from the aggregates of its subtrees
+    ///
+    /// Must be the same as combining with [Augment::empty] in place of the key's own aggregate.
+    fn merge(left: &Self::Value, right: &Self::Value) -> Self::Value;
+}
+
+/// No augmentation, used by trees that do not cache an aggregate
+impl <T> Augment<T> for () {
+    type Value = ();
+
+    fn empty() {}
+
+    fn combine(_: &(), _: &T, _: &()) {}
+
+    fn merge(_: &(), _: &()) {}
+}
+
+/// Augmentation counting the keys of each subtree
+///
+/// ```
+/// use project2::tree::Tree;
+/// use project2::tree::augment::Count;
+/// use project2::redblack::RedBlackBalance;
+/// let mut tree: Tree<u32, RedBlackBalance, Count> = Tree::new();
+/// for key in 0..100 {
+///     tree.insert(key);
+/// }
+/// assert_eq!(tree.aggregate(10..20), 10);
+/// assert_eq!(tree.aggregate(90..=200), 10);
+/// ```
+#[derive(Debug)]
+pub struct Count();
+
+impl <T> Augment<T> for Count {
+    type Value = usize;
+
+    fn empty() -> usize {
+        0
+    }
+
+    fn combine(left: &usize, _: &T, right: &usize) -> usize {
+        left + 1 + right
+    }
+
+    fn merge(left: &usize, right: &usize) -> usize {
+        left + right
+    }
+}
+
+/// Augmentation summing the keys of each subtree
+///
+/// ```
+/// use project2::tree::Tree;
+/// use project2::tree::augment::Sum;
+/// use project2::wavl::WAVLBalance;
+/// let mut tree: Tree<u64, WAVLBalance, Sum> = Tree::new();
+/// for key in 1..=100 {
+///     tree.insert(key);
+/// }
+/// assert_eq!(tree.aggregate(..), 5050);
+/// assert_eq!(tree.aggregate(..=10), 55);
+/// ```
+#[derive(Debug)]
+pub struct Sum();
+
+impl <T: Clone + Default + Add<Output = T>> Augment<T> for Sum {
+    type Value = T;
+
+    fn empty() -> T {
+        T::default()
+    }
+
+    fn combine(left: &T, key: &T, right: &T) -> T {
+        left.clone() + key.clone() + right.clone()
+    }
+
+    fn merge(left: &T, right: &T) -> T {
+        left.clone() + right.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeSet;
+    use super::{Count, Sum};
+    use crate::tree::{Op, Tree, TreeRng};
+    use crate::tree::inspect::TreeBalance;
+
+    /// Checks that the aggregates of a tree match the keys it holds
+    /// while inserting and deleting keys, and after restructuring the tree
+    fn check_aggregate<U: TreeBalance>() {
+        let mut tree: Tree<u64, U, Sum> = Tree::new();
+        let mut keys = BTreeSet::new();
+        let check = |tree: &Tree<u64, U, Sum>, keys: &BTreeSet<u64>| {
+            for (start, end) in [(0, 1000), (100, 200), (150, 151), (499, 700), (999, 1000)] {
+                assert_eq!(tree.aggregate(start..end), keys.range(start..end).sum::<u64>());
+                assert_eq!(tree.aggregate(start..=end), keys.range(start..=end).sum::<u64>());
+                assert_eq!(tree.aggregate(..end), keys.range(..end).sum::<u64>());
+                assert_eq!(tree.aggregate(start..), keys.range(start..).sum::<u64>());
+            }
+            assert_eq!(tree.aggregate(..), keys.iter().sum::<u64>());
+        };
+
+        let mut rng = TreeRng::new(11);
+        for i in 0..2000 {
+            let random = rng.next_u64();
+            let key = (random >> 33) % 1000;
+            if random & 3 == 0 {
+                assert_eq!(tree.delete(&key).is_some(), keys.remove(&key));
+            } else {
+                tree.insert(key);
+                keys.insert(key);
+            }
+            if i % 50 == 0 {
+                check(&tree, &keys);
+            }
+        }
+        check(&tree, &keys);
+
+        tree.search_mut(&keys.iter().next().copied().unwrap());
+        tree.apply_batch((0..100).map(|key| if key % 3 == 0 { Op::Delete(key * 7) } else { Op::Insert(key * 7) }));
+        for key in 0..100 {
+            if key % 3 == 0 {
+                keys.remove(&(key * 7));
+            } else {
+                keys.insert(key * 7);
+            }
+        }
+        check(&tree, &keys);
+        tree.rebuild();
+        check(&tree, &keys);
+
+        // Converting the tree keeps the aggregates
+        let count: Tree<u64, U, Count> = keys.iter().copied().fold(Tree::new(), |mut tree, key| {
+            tree.insert(key);
+            tree
+        });
+        let count: Tree<u64, crate::avl::AVLBalance, Count> = count.rebalance_as();
+        assert_eq!(count.aggregate(100..200), keys.range(100..200).count());
+        assert_eq!(count.aggregate(..), keys.len());
+    }
+
+    #[test]
+    fn aggregate() {
+        check_aggregate::<crate::avl::AVLBalance>();
+        check_aggregate::<crate::avl::RelaxedAVLBalance<2>>();
+        check_aggregate::<crate::redblack::RedBlackBalance>();
+        check_aggregate::<crate::redblack::TopDownRedBlackBalance>();
+        check_aggregate::<crate::redblack::LeftLeaningRedBlackBalance>();
+        check_aggregate::<crate::unbalanced::UnbalancedBalance>();
+        check_aggregate::<crate::splay::SplayBalance>();
+        check_aggregate::<crate::scapegoat::ScapegoatBalance>();
+        check_aggregate::<crate::treap::TreapBalance>();
+        check_aggregate::<crate::aa::AABalance>();
+        check_aggregate::<crate::wavl::WAVLBalance>();
+        check_aggregate::<crate::weight::WeightBalance>();
+    }
+
+    #[test]
+    fn aggregate_lazy_delete() {
+        let mut tree: Tree<u32, crate::avl::AVLBalance, Count> = Tree::with_lazy_delete(0.5);
+        for key in 0..10 {
+            tree.insert(key);
+        }
+
+        // Lazily deleted keys are left out, and counted again once revived
+        tree.delete(&3);
+        assert_eq!(tree.aggregate(0..5), 4);
+        assert_eq!(tree.aggregate(3..=3), 0);
+        assert_eq!(tree.aggregate(..), 9);
+        tree.insert(3);
+        assert_eq!(tree.aggregate(0..5), 5);
+        tree.delete(&3);
+        tree.compact();
+        assert_eq!(tree.aggregate(0..5), 4);
+        assert_eq!(tree.aggregate(..), 9);
+    }
+}
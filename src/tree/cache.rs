@@ -0,0 +1,133 @@
+/// Information about the shape of a [Tree](super::Tree) cached in every node
+///
+/// The [TreeBalance](super::TreeBalance) of a tree chooses what its nodes cache.  Each node stores the
+/// value of [NodeCache::combine] applied to the caches of its children, kept up to date through insertions,
+/// deletions and rotations.  Information that is not cached is computed on demand (in O(n)) when requested
+/// through [Tree::height](super::Tree::height), [Tree::leaves](super::Tree::leaves) or the
+/// [InspectNode](super::inspect::InspectNode) methods.  Caches can be paired to keep both, and balances
+/// that need nothing cached use `()`, which takes no space in the nodes.
+///
+/// ```
+/// use project2::tree::Tree;
+/// use project2::avl::AVLBalance;
+/// use project2::redblack::RedBlackBalance;
+///
+/// // AVL trees cache heights, red black trees compute them when asked
+/// let mut avl: Tree<u32, AVLBalance> = Tree::new();
+/// let mut red_black: Tree<u32, RedBlackBalance> = Tree::new();
+/// for key in 0..100 {
+///     avl.insert(key);
+///     red_black.insert(key);
+/// }
+/// assert_eq!(avl.height(), 7);
+/// assert_eq!(red_black.height(), 11);
+/// ```
+pub trait NodeCache {
+    /// Returns the cache of a node with no children
+    fn leaf() -> Self;
+
+    /// Returns the cache of a node from the caches of its children
+    fn combine(left: Option<&Self>, right: Option<&Self>) -> Self;
+
+    /// Returns the height of the tree rooted by the node, if cached
+    fn height(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the number of leaves of the tree rooted by the node, if cached
+    fn leaves(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the number of nodes in the tree rooted by the node (including lazily deleted ones), if cached
+    fn size(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Nothing cached
+impl NodeCache for () {
+    fn leaf() {}
+
+    fn combine(_: Option<&()>, _: Option<&()>) {}
+}
+
+/// Caches the height of the tree each node roots
+#[derive(Debug)]
+pub struct Height(usize);
+
+impl NodeCache for Height {
+    fn leaf() -> Self {
+        Height(1)
+    }
+
+    fn combine(left: Option<&Self>, right: Option<&Self>) -> Self {
+        Height(left.map_or(0, |c| c.0).max(right.map_or(0, |c| c.0)) + 1)
+    }
+
+    fn height(&self) -> Option<usize> {
+        Some(self.0)
+    }
+}
+
+/// Caches the number of leaves of the tree each node roots
+#[derive(Debug)]
+pub struct Leaves(usize);
+
+impl NodeCache for Leaves {
+    fn leaf() -> Self {
+        Leaves(1)
+    }
+
+    fn combine(left: Option<&Self>, right: Option<&Self>) -> Self {
+        Leaves((left.map_or(0, |c| c.0) + right.map_or(0, |c| c.0)).max(1))
+    }
+
+    fn leaves(&self) -> Option<usize> {
+        Some(self.0)
+    }
+}
+
+/// Caches the number of nodes in the tree each node roots
+#[derive(Debug)]
+pub struct Size(usize);
+
+impl NodeCache for Size {
+    fn leaf() -> Self {
+        Size(1)
+    }
+
+    fn combine(left: Option<&Self>, right: Option<&Self>) -> Self {
+        Size(left.map_or(0, |c| c.0) + right.map_or(0, |c| c.0) + 1)
+    }
+
+    fn size(&self) -> Option<usize> {
+        Some(self.0)
+    }
+}
+
+/// Caches everything either cache of the pair does
+impl <A: NodeCache, B: NodeCache> NodeCache for (A, B) {
+    fn leaf() -> Self {
+        (A::leaf(), B::leaf())
+    }
+
+    fn combine(left: Option<&Self>, right: Option<&Self>) -> Self {
+        (
+            A::combine(left.map(|c| &c.0), right.map(|c| &c.0)),
+            B::combine(left.map(|c| &c.1), right.map(|c| &c.1))
+        )
+    }
+
+    fn height(&self) -> Option<usize> {
+        self.0.height().or_else(|| self.1.height())
+    }
+
+    fn leaves(&self) -> Option<usize> {
+        self.0.leaves().or_else(|| self.1.leaves())
+    }
+
+    fn size(&self) -> Option<usize> {
+        self.0.size().or_else(|| self.1.size())
+    }
+}
@@ -1,19 +1,71 @@
+use core::marker::PhantomData;
+
 use super::*;
-use super::ops::bst_rotate;
+use super::ops::{bst_rebuild, bst_rotate};
+
+// Balances are generic over the augmentation of the tree they balance
+pub use super::augment::Augment;
+// and choose what their nodes cache
+pub use super::cache::NodeCache;
 
 /// Inspector for checking and manipulating a tree's state
 /// 
 /// Handle for manipulating and inspecting a subtree
 /// Exposes methods to check the tree state, check and update balance states
 /// and perform rotation operations around the top node
-pub struct NodeInspector<T: Ord, U: TreeBalance>(TreeBranch<T, U>);
+/// 
+/// Counts the rotations performed through it (see [Tree::rotations]) and notes if it rebuilt a subtree
+pub struct NodeInspector<T: Ord, U: TreeBalance, A: Augment<T>>(TreeBranch<T, U, A>, usize, bool);
+
+/// Result of a checked rotation, holding the unchanged inspector along with the error if the rotation could not take place
+type RotateResult<T, U, A> = Result<NodeInspector<T, U, A>, (NodeInspector<T, U, A>, InspectError)>;
 
 /// Inspector for checking a child node in a subtree that a [NodeInspector] exposes
-pub struct BranchInspector<'a, T: Ord, U: TreeBalance>{
-    parent_ref: std::cell::Ref<'a, TreeNode<T, U>>,
+pub struct BranchInspector<'a, T: Ord, U: TreeBalance, A: Augment<T>>{
+    parent_ref: core::cell::Ref<'a, TreeNode<T, U, A>>,
     path: TreePath
 }
 
+/// Inspector for checking a node related to the node that a [NodeInspector] exposes
+/// (its parent, grandparent or sibling)
+/// 
+/// The [NodeInspector] cannot be used while a related node is being inspected
+pub struct RelativeInspector<'a, T: Ord, U: TreeBalance, A: Augment<T>> {
+    branch: TreeBranch<T, U, A>,
+    _inspector: PhantomData<&'a mut NodeInspector<T, U, A>>
+}
+
+/// Context given to a [TreeBalance] when creating the balance for a new node
+/// 
+/// Exposes the key of the new node and the random number generator of the tree
+pub struct BalanceContext<'a, T> {
+    key: &'a T,
+    rng: &'a mut TreeRng
+}
+
+impl <T> BalanceContext<'_, T> {
+    /// Returns the key of the new node
+    pub fn key(&self) -> &T {
+        self.key
+    }
+
+    /// Returns a random number from the tree's generator
+    /// 
+    /// The generator is seeded when the tree is created (see [Tree::with_seed])
+    pub fn random(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+}
+
+impl <'a, T> Open for BalanceContext<'a, T> {
+    type Target = (&'a T, &'a mut TreeRng);
+
+    /// Constructor for creating a BalanceContext for the given key and generator
+    fn open((key, rng): (&'a T, &'a mut TreeRng)) -> BalanceContext<'a, T> {
+        BalanceContext { key, rng }
+    }
+}
+
 /// Offset descriptor for tree traversal relative to a given node
 pub enum NodeOffset {
     // Traverse to root
@@ -21,39 +73,164 @@ pub enum NodeOffset {
     // Traverse to parent
     Parent,
     // Traverse to child at path
-    Child(TreePath)
+    Child(TreePath),
+    // Traverse up the given number of levels
+    Ancestor(usize),
+    // Traverse down each path in order, at least one path must be given
+    Descendant(alloc::vec::Vec<TreePath>)
+}
+
+/// Operation that a search down the tree is being performed for
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Descent {
+    /// Searching for the position to insert a key
+    Insert,
+    /// Searching for the node to delete
+    Delete,
+    /// Searching for the successor of a node with two children being deleted, which replaces its key
+    Successor
+}
+
+/// Error describing why an inspector operation could not be performed
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum InspectError {
+    /// A node the operation requires has no child on the given path
+    MissingChild(TreePath),
+    /// A node the operation requires is already borrowed by another inspector
+    Borrowed,
+    /// A position returned by a balance points to a node that does not exist
+    InvalidOffset,
+    /// A multiway node the operation requires has no child at the given index (or the child holds no keys)
+    MissingIndex(usize)
+}
+
+impl core::fmt::Display for InspectError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InspectError::MissingChild(path) => write!(f, "Inspected node has no {:?} child", path),
+            InspectError::Borrowed => write!(f, "Inspected node is already borrowed by another inspector"),
+            InspectError::InvalidOffset => write!(f, "Rebalance offset points to an empty node"),
+            InspectError::MissingIndex(index) => write!(f, "Inspected node has no child at {}", index)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InspectError {}
+
+/// Error describing an invariant of a tree that does not hold (see [Tree::validate])
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ValidationError {
+    /// A key is not between the keys of the ancestors it is placed under
+    Unordered,
+    /// A node's parent reference does not point to the node holding it (or the root has a parent)
+    BrokenParent,
+    /// A node's cached height does not match the height of the tree it roots
+    StaleHeight,
+    /// A node's cached leaf count does not match the leaves of the tree it roots
+    StaleLeaves,
+    /// A node's cached size does not match the number of nodes in the tree it roots
+    StaleSize,
+    /// The number of keys or tombstones in the tree does not match the counts the tree keeps
+    Length,
+    /// An invariant of the balance does not hold (see [TreeBalance::verify])
+    Balance(&'static str)
+}
+
+impl core::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ValidationError::Unordered => write!(f, "Key is out of order"),
+            ValidationError::BrokenParent => write!(f, "Node's parent reference does not match its parent"),
+            ValidationError::StaleHeight => write!(f, "Node's cached height is out of date"),
+            ValidationError::StaleLeaves => write!(f, "Node's cached leaf count is out of date"),
+            ValidationError::StaleSize => write!(f, "Node's cached size is out of date"),
+            ValidationError::Length => write!(f, "Tree's length does not match its keys"),
+            ValidationError::Balance(invariant) => write!(f, "Balance invariant does not hold: {}", invariant)
+        }
+    }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for ValidationError {}
+
 /// Position of a node in the tree as described by a given node and a provided offset
-pub struct TreePosition<T: Ord, U: TreeBalance>(TreeBranch<T, U>, NodeOffset);
+/// 
+/// Holds an error instead of an offset if the balance failed (see [NodeInspector::into_error])
+pub struct TreePosition<T: Ord, U: TreeBalance, A: Augment<T>>(TreeBranch<T, U, A>, Result<NodeOffset, InspectError>, usize, bool);
+
+impl <T: Ord, U: TreeBalance, A: Augment<T>> TreePosition<T, U, A> {
+    /// Checks if a subtree was rebuilt on the way to this position (see [NodeInspector::rebuild_balanced])
+    pub(crate) fn rebuilt(&self) -> bool {
+        self.3
+    }
+}
 
 /// Consumes and returns the data that the TreePosition wrapped
-impl <T: Ord, U: TreeBalance> IntoInner for TreePosition<T, U> {
-    type Target = (TreeBranch<T, U>, NodeOffset);
+/// along with the number of rotations performed
+impl <T: Ord, U: TreeBalance, A: Augment<T>> IntoInner for TreePosition<T, U, A> {
+    type Target = (TreeBranch<T, U, A>, Result<NodeOffset, InspectError>, usize);
 
-    fn into_inner(self) -> (TreeBranch<T, U>, NodeOffset) {
-        (self.0, self.1)
+    fn into_inner(self) -> (TreeBranch<T, U, A>, Result<NodeOffset, InspectError>, usize) {
+        (self.0, self.1, self.2)
     }
 }
 /// Trait for performing inspection operations on a given node
-pub trait InspectNode<'a, T: Ord, U: TreeBalance>
-    where Self: std::marker::Sized {
+pub trait InspectNode<'a, T: Ord, U: TreeBalance, A: Augment<T>>
+    where Self: core::marker::Sized {
 
     /// Returns an inspector for the given child is there is one
-    fn inspect_child(&'a self, path: TreePath) -> Option<BranchInspector<'a, T, U>>;
+    /// 
+    /// # Panics
+    /// 
+    /// This function panics if the node is being updated by another inspector
+    fn inspect_child(&'a self, path: TreePath) -> Option<BranchInspector<'a, T, U, A>> {
+        match self.try_inspect_child(path) {
+            Ok(child) => Some(child),
+            Err(InspectError::MissingChild(_)) => None,
+            Err(error) => panic!("{}", error)
+        }
+    }
+
+    /// Returns an inspector for the given child
+    /// 
+    /// Returns an error if there is no child or the node is being updated by another inspector
+    fn try_inspect_child(&'a self, path: TreePath) -> Result<BranchInspector<'a, T, U, A>, InspectError>;
 
     /// Calls a function to inspect the [TreeBalance] for the node
     /// 
     /// Passes the node's [TreeBalance] into a given function and returns the result
-    fn inspect_balance<F, R>(&self, apply: F) -> R where F: FnOnce(&U) -> R;
+    /// 
+    /// # Panics
+    /// 
+    /// This function panics if the node is being updated by another inspector
+    fn inspect_balance<F, R>(&self, apply: F) -> R where F: FnOnce(&U) -> R {
+        self.try_inspect_balance(apply).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Calls a function to inspect the [TreeBalance] for the node
+    /// 
+    /// Returns an error instead of calling the function if the node is being updated by another inspector
+    fn try_inspect_balance<F, R>(&self, apply: F) -> Result<R, InspectError> where F: FnOnce(&U) -> R;
 
     /// Applies an operation on the [TreeBalance] for the node
     /// 
     /// Passes the node's [TreeBalance] as a mutable reference
     /// into a given function and returns the result
-    fn update_balance<F, R>(&mut self, apply: F) -> R where F: FnOnce(&mut U) -> R;
+    /// 
+    /// # Panics
+    /// 
+    /// This function panics if the node is being inspected by another inspector
+    fn update_balance<F, R>(&mut self, apply: F) -> R where F: FnOnce(&mut U) -> R {
+        self.try_update_balance(apply).unwrap_or_else(|error| panic!("{}", error))
+    }
 
-    /**
+    /// Applies an operation on the [TreeBalance] for the node
+    /// 
+    /// Returns an error instead of calling the function if the node is being inspected by another inspector
+    fn try_update_balance<F, R>(&mut self, apply: F) -> Result<R, InspectError> where F: FnOnce(&mut U) -> R;
+
+    /*
      * Design note:
      * 
      * The original plan was to typedef/newtype Ref<U> and RefMut<U> types and return those
@@ -74,71 +251,287 @@ pub trait InspectNode<'a, T: Ord, U: TreeBalance>
     /// Returns the leaf count of the given node
     fn inspect_leaves(&self) -> usize;
 
+    /// Returns the number of nodes in the tree rooted by the given node
+    /// 
+    /// Lazily deleted nodes are counted (see [Tree::with_lazy_delete])
+    fn inspect_size(&self) -> usize;
+
     /// Checks if the given node is a root node
     fn inspect_is_root(&self) -> bool;
 }
 
-impl <'a, T: Ord, U: TreeBalance> BranchInspector<'a, T, U> {
+impl <'a, T: Ord, U: TreeBalance, A: Augment<T>> BranchInspector<'a, T, U, A> {
+
+    /// Constructor for creating a BranchInspector for the child on the given path of a borrowed node
+    /// 
+    /// Returns an error if there is no child on the path
+    fn new(parent_ref: core::cell::Ref<'a, TreeNode<T, U, A>>, path: TreePath) -> Result<BranchInspector<'a, T, U, A>, InspectError> {
+        if parent_ref.get_child(path).is_some() {
+            Ok(BranchInspector { parent_ref, path })
+        } else {
+            Err(InspectError::MissingChild(path))
+        }
+    }
 
     /// Returns a reference to the branch that his node represents
-    fn get_branch(&self) -> &TreeBranch<T, U> {
+    fn get_branch(&self) -> &TreeBranch<T, U, A> {
         self.parent_ref.get_child(self.path).unwrap()
     }
 
 }
 
-impl <T: Ord, U: TreeBalance> NodeInspector<T, U> {
+impl <T: Ord, U: TreeBalance, A: Augment<T>> NodeInspector<T, U, A> {
     /// Performs a rotate around the root of the subtree with the given case
     /// 
     /// Note: paths are flipped.  This is to make it simpler to handle performing a rotation
     /// based off the parent and child paths of a node (i.e. to perform an outer Right rotation, )
-    pub fn rotate(self, case: (TreePath, TreePath)) -> NodeInspector<T, U> {
-        if case.0 != case.1 {
-            let around = std::rc::Rc::clone(self.0.borrow().get_child(case.0).unwrap());
-            bst_rotate(around, case.1);
+    /// 
+    /// # Panics
+    /// 
+    /// This function panics if a node the rotation brings up is missing
+    /// or a node the rotation moves is borrowed (see [NodeInspector::try_rotate])
+    pub fn rotate(self, case: (TreePath, TreePath)) -> NodeInspector<T, U, A> {
+        self.try_rotate(case).unwrap_or_else(|(_, error)| panic!("{}", error))
+    }
+
+    /// Performs a rotate around the root of the subtree with the given case,
+    /// checking that the rotation can take place first
+    /// 
+    /// Behaves like [NodeInspector::rotate] but returns the inspector unchanged with an error
+    /// if a node the rotation brings up is missing or a node the rotation moves is borrowed
+    pub fn try_rotate(self, case: (TreePath, TreePath)) -> RotateResult<T, U, A> {
+        let checked = Self::check_rotation(&self.0, case.0).and_then(|around| {
+            if case.0 != case.1 {
+                // The child of the new top on the path moves across to this node
+                let top = Self::check_rotation(&around, case.1)?;
+                let moved = top.try_borrow().map_err(|_| InspectError::Borrowed)?.get_child(case.1).map(alloc::rc::Rc::clone);
+                Self::check_free(moved.as_ref())?;
+            }
+            Ok(around)
+        });
+
+        match checked {
+            Ok(around) => {
+                let mut rotations = self.1 + 1;
+                if case.0 != case.1 {
+                    bst_rotate(around, case.1);
+                    rotations += 1;
+                }
+                Ok(NodeInspector (bst_rotate(self.0, case.0), rotations, self.2))
+            },
+            Err(error) => Err((self, error))
+        }
+    }
+
+    /// Performs a single rotation around a descendant of the subtree
+    /// 
+    /// The node found by following the given paths from the root of the subtree is rotated,
+    /// bringing up its child in the given direction.  The nodes between the root of the subtree
+    /// and the rotated node are updated.  If no paths are given the root of the subtree is rotated
+    /// and the returned inspector exposes the new root, otherwise the root stays the same.
+    /// 
+    /// # Panics
+    /// 
+    /// This function panics if there is no node along the paths or no child to bring up
+    /// (see [NodeInspector::try_rotate_at])
+    pub fn rotate_at(self, paths: &[TreePath], direction: TreePath) -> NodeInspector<T, U, A> {
+        self.try_rotate_at(paths, direction).unwrap_or_else(|(_, error)| panic!("{}", error))
+    }
+
+    /// Performs a single rotation around a descendant of the subtree,
+    /// checking that the rotation can take place first
+    /// 
+    /// Behaves like [NodeInspector::rotate_at] but returns the inspector unchanged with an error
+    /// if there is no node along the paths, no child to bring up or a node the rotation touches is borrowed
+    pub fn try_rotate_at(self, paths: &[TreePath], direction: TreePath) -> RotateResult<T, U, A> {
+        let checked = paths.iter().try_fold(alloc::rc::Rc::clone(&self.0), |node, &path| {
+            // Every node along the paths is updated afterwards
+            Self::check_free(Some(&node))?;
+            let child = node.try_borrow().map_err(|_| InspectError::Borrowed)?.get_child(path).map(alloc::rc::Rc::clone);
+            child.ok_or(InspectError::MissingChild(path))
+        }).and_then(|node| Self::check_rotation(&node, direction).map(|_| node));
+        let node = match checked {
+            Ok(node) => node,
+            Err(error) => return Err((self, error))
+        };
+
+        if paths.is_empty() {
+            return Ok(NodeInspector (bst_rotate(self.0, direction), self.1 + 1, self.2));
+        }
+        let top = bst_rotate(node, direction);
+
+        // Update everything from the rotated subtree up to the root of this subtree
+        let mut next = top.borrow().get_parent();
+        while let Some(n) = next {
+            n.borrow_mut().update();
+            if alloc::rc::Rc::ptr_eq(&n, &self.0) {
+                break;
+            }
+            next = n.borrow().get_parent();
         }
-        NodeInspector (bst_rotate(self.0, case.0))
+        Ok(NodeInspector (self.0, self.1 + 1, self.2))
+    }
+
+    /// Checks that a rotation bringing up the child of the node on the given path can take place
+    /// 
+    /// Returns the child that would be brought up
+    fn check_rotation(node: &TreeBranch<T, U, A>, direction: TreePath) -> Result<TreeBranch<T, U, A>, InspectError> {
+        let (child, parent) = {
+            let node = node.try_borrow().map_err(|_| InspectError::Borrowed)?;
+            (node.get_child(direction).map(alloc::rc::Rc::clone), node.get_parent())
+        };
+        let child = child.ok_or(InspectError::MissingChild(direction))?;
+        let moved = child.try_borrow().map_err(|_| InspectError::Borrowed)?.get_child(direction.reflect()).map(alloc::rc::Rc::clone);
+        Self::check_free(Some(node))?;
+        Self::check_free(Some(&child))?;
+        Self::check_free(parent.as_ref())?;
+        Self::check_free(moved.as_ref())?;
+        Ok(child)
+    }
+
+    /// Checks that the given node (if any) is not borrowed so it can be changed
+    fn check_free(node: Option<&TreeBranch<T, U, A>>) -> Result<(), InspectError> {
+        match node {
+            Some(node) => node.try_borrow_mut().map(|_| ()).map_err(|_| InspectError::Borrowed),
+            None => Ok(())
+        }
+    }
+
+    /// Rebuilds the subtree into a height balanced shape in O(n)
+    /// 
+    /// The nodes of the subtree are reused and keep their balances, so any state
+    /// the balances hold about the shape of the subtree needs to be fixed afterwards.
+    /// Returns an inspector for the new root of the subtree.
+    pub fn rebuild_balanced(self) -> NodeInspector<T, U, A> {
+        NodeInspector (bst_rebuild(self.0), self.1, true)
+    }
+
+    /// Moves the inspector to a node at the given offset
+    /// 
+    /// [NodeOffset::Root] moves to the root of the full tree.
+    /// Returns the inspector unmoved as an error if there is no node at the offset
+    /// or the offset is a [NodeOffset::Descendant] without paths.
+    pub fn move_to(self, offset: NodeOffset) -> Result<NodeInspector<T, U, A>, NodeInspector<T, U, A>> {
+        let mut branch = alloc::rc::Rc::clone(&self.0);
+        let moved = match offset {
+            NodeOffset::Root => loop {
+                let parent = branch.borrow().get_parent();
+                match parent {
+                    Some(parent) => branch = parent,
+                    None => break true
+                }
+            },
+            NodeOffset::Parent => Self::travel_up(&mut branch, 1),
+            NodeOffset::Ancestor(n) => Self::travel_up(&mut branch, n),
+            NodeOffset::Child(path) => Self::travel_down(&mut branch, &[path]),
+            NodeOffset::Descendant(paths) => !paths.is_empty() && Self::travel_down(&mut branch, &paths)
+        };
+        if moved {
+            Ok(NodeInspector (branch, self.1, self.2))
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Moves the given branch up a number of levels, returning false if the root is passed
+    fn travel_up(branch: &mut TreeBranch<T, U, A>, levels: usize) -> bool {
+        for _ in 0..levels {
+            let parent = branch.borrow().get_parent();
+            match parent {
+                Some(parent) => *branch = parent,
+                None => return false
+            }
+        }
+        true
+    }
+
+    /// Moves the given branch down the paths, returning false if a node is missing
+    fn travel_down(branch: &mut TreeBranch<T, U, A>, paths: &[TreePath]) -> bool {
+        for &path in paths {
+            let child = branch.borrow().get_child(path).map(alloc::rc::Rc::clone);
+            match child {
+                Some(child) => *branch = child,
+                None => return false
+            }
+        }
+        true
+    }
+
+    /// Returns the path from the parent of this node to this node, or None if it is the root
+    pub fn inspect_placement(&self) -> Option<TreePath> {
+        let node = self.0.borrow();
+        node.get_parent().map(|parent| parent.borrow().find_placement(&node))
+    }
+
+    /// Returns an inspector for the parent of this node if there is one
+    pub fn inspect_parent(&mut self) -> Option<RelativeInspector<'_, T, U, A>> {
+        let parent = self.0.borrow().get_parent();
+        parent.map(RelativeInspector::new)
+    }
+
+    /// Returns an inspector for the grandparent of this node if there is one
+    pub fn inspect_grandparent(&mut self) -> Option<RelativeInspector<'_, T, U, A>> {
+        let parent = self.0.borrow().get_parent();
+        parent.and_then(|p| p.borrow().get_parent()).map(RelativeInspector::new)
+    }
+
+    /// Returns an inspector for the sibling of this node if there is one
+    pub fn inspect_sibling(&mut self) -> Option<RelativeInspector<'_, T, U, A>> {
+        let path = self.inspect_placement()?;
+        let parent = self.0.borrow().get_parent()?;
+        let sibling = parent.borrow().get_child(path.reflect()).map(alloc::rc::Rc::clone);
+        sibling.map(RelativeInspector::new)
     }
 
     /// Consumed the inspector returning a position along the full tree
     /// relative to the root of the subtree this inspector exposed
-    pub fn into_position(self, pos: NodeOffset) -> TreePosition<T, U> {
-        TreePosition (self.0, pos)
+    pub fn into_position(self, pos: NodeOffset) -> TreePosition<T, U, A> {
+        TreePosition (self.0, Ok(pos), self.1, self.2)
+    }
+
+    /// Consumes the inspector returning a position that stops rebalancing with the given error
+    /// 
+    /// The error is returned by [Tree::try_insert] and [Tree::try_delete] (the other operations panic with it).
+    /// The tree keeps every key but may be left unbalanced.
+    pub fn into_error(self, error: InspectError) -> TreePosition<T, U, A> {
+        TreePosition (self.0, Err(error), self.1, self.2)
     }
 
 }
 
-impl <T: Ord, U: TreeBalance> Open for NodeInspector<T, U> {
-    type Target = TreeBranch<T, U>;
+/// Consumes and returns the node that the NodeInspector exposed
+/// along with the number of rotations performed
+impl <T: Ord, U: TreeBalance, A: Augment<T>> IntoInner for NodeInspector<T, U, A> {
+    type Target = (TreeBranch<T, U, A>, usize);
+
+    fn into_inner(self) -> (TreeBranch<T, U, A>, usize) {
+        (self.0, self.1)
+    }
+}
+
+impl <T: Ord, U: TreeBalance, A: Augment<T>> Open for NodeInspector<T, U, A> {
+    type Target = TreeBranch<T, U, A>;
 
     /// Constructor for creating a NodeInspector
     /// for a subtree starting at the given node
-    fn open(branch: TreeBranch<T, U>) -> NodeInspector<T, U>{
-        NodeInspector (branch)
+    fn open(branch: TreeBranch<T, U, A>) -> NodeInspector<T, U, A>{
+        NodeInspector (branch, 0, false)
     }
 }
 
 
-impl <'a, T: Ord, U: TreeBalance> InspectNode<'a, T, U> for NodeInspector<T, U> {
+impl <'a, T: Ord, U: TreeBalance, A: Augment<T>> InspectNode<'a, T, U, A> for NodeInspector<T, U, A> {
 
-    fn inspect_child(&'a self, path: TreePath) -> Option<BranchInspector<'a, T, U>> {
-        let pref = self.0.borrow();
-        if pref.get_child(path).is_some() {
-            Some(BranchInspector {
-                parent_ref: pref,
-                path
-            })
-        } else {
-            None
-        }
+    fn try_inspect_child(&'a self, path: TreePath) -> Result<BranchInspector<'a, T, U, A>, InspectError> {
+        BranchInspector::new(self.0.try_borrow().map_err(|_| InspectError::Borrowed)?, path)
     }
 
-    fn inspect_balance<F, R>(&self, apply: F) -> R where F: FnOnce(&U) -> R {
-        apply(&self.0.borrow().get_balance())
+    fn try_inspect_balance<F, R>(&self, apply: F) -> Result<R, InspectError> where F: FnOnce(&U) -> R {
+        Ok(apply(self.0.try_borrow().map_err(|_| InspectError::Borrowed)?.get_balance()))
     }
 
-    fn update_balance<F, R>(&mut self, apply: F) -> R where F: FnOnce(&mut U) -> R {
-        apply(&mut self.0.borrow_mut().get_balance_mut())
+    fn try_update_balance<F, R>(&mut self, apply: F) -> Result<R, InspectError> where F: FnOnce(&mut U) -> R {
+        Ok(apply(self.0.try_borrow_mut().map_err(|_| InspectError::Borrowed)?.get_balance_mut()))
     }
 
     fn inspect_height(&self) -> usize {
@@ -149,31 +542,69 @@ impl <'a, T: Ord, U: TreeBalance> InspectNode<'a, T, U> for NodeInspector<T, U>
         self.0.borrow().get_leaves()
     }
 
+    fn inspect_size(&self) -> usize {
+        self.0.borrow().get_size()
+    }
+
     fn inspect_is_root(&self) -> bool {
         self.0.borrow().get_parent().is_none()
     }
 
 }
 
-impl <'a, T: Ord, U: TreeBalance> InspectNode<'a, T, U> for BranchInspector<'a, T, U> {
-    fn inspect_child(&'a self, path: TreePath) -> Option<BranchInspector<'a, T, U>> {
-        let pref = self.get_branch().borrow();
-        if pref.get_child(path).is_some() {
-            Some(BranchInspector {
-                parent_ref: pref,
-                path
-            })
-        } else {
-            None
+impl <'r, T: Ord, U: TreeBalance, A: Augment<T>> RelativeInspector<'r, T, U, A> {
+    /// Constructor for creating a RelativeInspector for the given node
+    fn new(branch: TreeBranch<T, U, A>) -> RelativeInspector<'r, T, U, A> {
+        RelativeInspector {
+            branch,
+            _inspector: PhantomData
         }
     }
+}
 
-    fn inspect_balance<F, R>(&self, apply: F) -> R where F: FnOnce(&U) -> R {
-        apply(self.get_branch().borrow().get_balance())
+impl <'a, 'r, T: Ord, U: TreeBalance, A: Augment<T>> InspectNode<'a, T, U, A> for RelativeInspector<'r, T, U, A> {
+
+    fn try_inspect_child(&'a self, path: TreePath) -> Result<BranchInspector<'a, T, U, A>, InspectError> {
+        BranchInspector::new(self.branch.try_borrow().map_err(|_| InspectError::Borrowed)?, path)
     }
 
-    fn update_balance<F, R>(&mut self, apply: F) -> R where F: FnOnce(&mut U) -> R {
-        apply(&mut self.get_branch().borrow_mut().get_balance_mut())
+    fn try_inspect_balance<F, R>(&self, apply: F) -> Result<R, InspectError> where F: FnOnce(&U) -> R {
+        Ok(apply(self.branch.try_borrow().map_err(|_| InspectError::Borrowed)?.get_balance()))
+    }
+
+    fn try_update_balance<F, R>(&mut self, apply: F) -> Result<R, InspectError> where F: FnOnce(&mut U) -> R {
+        Ok(apply(self.branch.try_borrow_mut().map_err(|_| InspectError::Borrowed)?.get_balance_mut()))
+    }
+
+    fn inspect_height(&self) -> usize {
+        self.branch.borrow().get_height()
+    }
+
+    fn inspect_leaves(&self) -> usize {
+        self.branch.borrow().get_leaves()
+    }
+
+    fn inspect_size(&self) -> usize {
+        self.branch.borrow().get_size()
+    }
+
+    fn inspect_is_root(&self) -> bool {
+        self.branch.borrow().get_parent().is_none()
+    }
+
+}
+
+impl <'a, T: Ord, U: TreeBalance, A: Augment<T>> InspectNode<'a, T, U, A> for BranchInspector<'a, T, U, A> {
+    fn try_inspect_child(&'a self, path: TreePath) -> Result<BranchInspector<'a, T, U, A>, InspectError> {
+        BranchInspector::new(self.get_branch().try_borrow().map_err(|_| InspectError::Borrowed)?, path)
+    }
+
+    fn try_inspect_balance<F, R>(&self, apply: F) -> Result<R, InspectError> where F: FnOnce(&U) -> R {
+        Ok(apply(self.get_branch().try_borrow().map_err(|_| InspectError::Borrowed)?.get_balance()))
+    }
+
+    fn try_update_balance<F, R>(&mut self, apply: F) -> Result<R, InspectError> where F: FnOnce(&mut U) -> R {
+        Ok(apply(self.get_branch().try_borrow_mut().map_err(|_| InspectError::Borrowed)?.get_balance_mut()))
     }
 
     fn inspect_height(&self) -> usize {
@@ -185,6 +616,10 @@ impl <'a, T: Ord, U: TreeBalance> InspectNode<'a, T, U> for BranchInspector<'a,
         self.get_branch().borrow().get_leaves()
     }
 
+    fn inspect_size(&self) -> usize {
+        self.get_branch().borrow().get_size()
+    }
+
     fn inspect_is_root(&self) -> bool {
         // A child will always not be the root
         false
@@ -197,13 +632,50 @@ impl <'a, T: Ord, U: TreeBalance> InspectNode<'a, T, U> for BranchInspector<'a,
 /// The [TreeBalance] associated with a [Tree] will be used for 
 /// rebalancing the tree after insert and delete operations
 pub trait TreeBalance
-    where Self: std::marker::Sized
+    where Self: core::marker::Sized
 {
+    /// Shape information each node caches for the tree it roots (see [NodeCache])
+    /// 
+    /// Whatever is not cached is computed on demand (in O(n)) when requested through
+    /// [Tree::height], [Tree::leaves] or [InspectNode], balances that need nothing use `()`
+    type Cache: NodeCache;
+
+    /// Whether [TreeBalance::on_access] should be called after each access
+    /// 
+    /// If not set accessing a node does no work beyond finding it, so only
+    /// self-adjusting balances (e.g. splay trees) need to set it
+    const ON_ACCESS: bool = false;
+
+    /// State the balance keeps for a node, as reported by [Tree::node_info]
+    /// 
+    /// Balances that keep no state for their nodes use `()`
+    type State: Clone + PartialEq + core::fmt::Debug;
+
     /// Returns a new balance for a generic node
-    fn new() -> Self;
+    /// 
+    /// The context exposes the key of the node and a random number generator
+    fn new<T: Ord>(context: &mut BalanceContext<T>) -> Self;
 
     /// Returns a new balance for a root node
-    fn new_root() -> Self;
+    /// 
+    /// The context exposes the key of the node and a random number generator
+    fn new_root<T: Ord>(context: &mut BalanceContext<T>) -> Self;
+
+    /// Returns a new balance for a node of a rebuilt tree
+    /// 
+    /// When a tree is rebuilt from scratch into a minimal height tree (either split around the median
+    /// at every node or complete, so the heights of every node's subtrees differ by at most one)
+    /// each node is given a balance from this function, based off the height of the node and the
+    /// height of its parent (None for the root).
+    /// 
+    /// Returns None if the balance cannot describe a tree built this way, in which case the tree
+    /// is never rebuilt in place and its nodes are instead appended in order when the tree is
+    /// compacted or converted.  Balances whose state depends on more than the shape (e.g. treaps)
+    /// or that need a shape other than these (e.g. AA trees and left leaning red black trees, where
+    /// red links lean to one side) return None.  The result must be consistently Some or None.
+    fn new_rebuilt(_height: usize, _parent_height: Option<usize>) -> Option<Self> {
+        None
+    }
 
     /// Perform a rebalance after an insertion operation
     /// 
@@ -211,14 +683,15 @@ pub trait TreeBalance
     /// position that should be inspected for rebalancing.
     /// The tree is inspectable through the passed [NodeInspector] object and
     /// rotations can be performed on the tree used it.
-    /// The path of the operation is provided in format (Parent Insertion Path, Child Insertion Path)
+    /// The path of the operation is provided in format (Parent Insertion Path, Child Insertion Path),
+    /// where the child insertion path is None if the inserted node is the child on the parent insertion path.
+    /// Rebalancing starts at the grandparent of the inserted node, or at its parent if that is the root.
     /// 
     /// Returns the next position to rebalance in relation to the node currently being balanced.
-    /// 
-    /// # Issues
-    /// 
-    /// Will cause a panic if it returns NodeOffset::Child
-    fn rebalance_insert<T: Ord>(inspector: inspect::NodeInspector<T, Self>, path: (TreePath, TreePath)) -> TreePosition<T, Self>;
+    /// The path given for the next position is the path towards the inserted node, if the next
+    /// node is not above the inserted node rebalancing stops.
+    /// Moving past the root (with [NodeOffset::Parent] or [NodeOffset::Ancestor]) also stops rebalancing.
+    fn rebalance_insert<T: Ord, A: Augment<T>>(inspector: inspect::NodeInspector<T, Self, A>, path: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A>;
 
     /// Perform a rebalance after a delete operation
     /// 
@@ -229,8 +702,55 @@ pub trait TreeBalance
     /// The path provided is the path that the child was deleted from in relation to the current node.
     /// The balance from the deleted node is also provided.
     /// 
-    /// Returns the next position to rebalance in relation to the node currently being balanced
-    fn rebalance_delete<T: Ord>(inspector: inspect::NodeInspector<T, Self>, path: TreePath, balance: &Self) -> TreePosition<T, Self>;
+    /// Returns the next position to rebalance in relation to the node currently being balanced.
+    /// When moving up the tree the path given for the next position is the path to the node we came from,
+    /// when moving down it is the last path travelled.
+    fn rebalance_delete<T: Ord, A: Augment<T>>(inspector: inspect::NodeInspector<T, Self, A>, path: TreePath, balance: &Self) -> TreePosition<T, Self, A>;
+
+    /// Perform a rebalance while searching down the tree
+    /// 
+    /// Called by [Tree::insert] and [Tree::delete] at each node on the search path before the search
+    /// moves down to the node's child, allowing top-down algorithms to rotate and recolor on the way down.
+    /// The path provided is in format (Child Path, Grandchild Path), where the grandchild path
+    /// is None if the child holds the key being searched for.
+    /// 
+    /// When the node to delete has two children the search continues down to its successor
+    /// (with [Descent::Successor]), starting at the node to delete, where the grandchild path
+    /// is None if the child is the successor.
+    /// 
+    /// Returns an inspector for the node now at the top of the subtree.  If this is a different node
+    /// (the subtree was rotated) the hook is called again on the new node, otherwise the search moves on to the child.
+    /// By default nothing is done.
+    fn before_descend<T: Ord, A: Augment<T>>(inspector: inspect::NodeInspector<T, Self, A>, _path: (TreePath, Option<TreePath>), _op: Descent) -> NodeInspector<T, Self, A> {
+        inspector
+    }
+
+    /// Perform a restructure after a node is accessed
+    /// 
+    /// Called with the node found by [Tree::search_mut], the node holding the key after [Tree::insert]
+    /// and the node closest to where the deletion took place after [Tree::delete] (once rebalancing is done).
+    /// The paths provided lead from the current node down to the accessed node (empty at the accessed node).
+    /// 
+    /// Returns the next position to inspect in relation to the node currently being inspected,
+    /// stopping once the accessed node cannot be reached from it.  Only called if [TreeBalance::ON_ACCESS]
+    /// is set, by default nothing is done.
+    fn on_access<T: Ord, A: Augment<T>>(inspector: inspect::NodeInspector<T, Self, A>, _path: &[TreePath]) -> TreePosition<T, Self, A> {
+        inspector.into_position(NodeOffset::Root)
+    }
+
+    /// Checks that the invariants of the balance hold at a node
+    /// 
+    /// Called by [Tree::validate] on each node of the tree once the structure of the tree has been checked,
+    /// so cached heights, leaf counts and sizes are correct.  Returns [ValidationError::Balance] describing
+    /// the invariant that does not hold if there is one.  By default nothing is checked.
+    fn verify<T: Ord, A: Augment<T>>(_inspector: &inspect::NodeInspector<T, Self, A>) -> Result<(), ValidationError> {
+        Ok(())
+    }
+
+    /// Returns the state the balance keeps for a node
+    /// 
+    /// Called by [Tree::node_info] to describe the node
+    fn state<T: Ord, A: Augment<T>>(inspector: &inspect::NodeInspector<T, Self, A>) -> Self::State;
 
     /// Called when a new node moves into the root location after a delete operation
     fn adjust_root(&mut self);
@@ -1,43 +1,49 @@
-use std::cmp::{Ordering, max};
+use core::cmp::{Ordering, max};
+use alloc::boxed::Box;
+use alloc::format;
 use super::*;
 
 /// A node in a binary tree structure
 /// 
 /// Has a key of type [T] and is balanced
 /// using the associated [TreeBalance] type
-pub struct TreeNode<T: Ord, U>
-    where U: TreeBalance
+pub struct TreeNode<T: Ord, U, A>
+    where U: TreeBalance, A: Augment<T>
 {
     /// Key for the node
     key: T,
-    /// Height of the tree that is rooted by this node
-    height: usize,
-    /// Number of leaves of the tree that is rooted by this node
-    leaves: usize,
+    /// Shape information cached for the tree that is rooted by this node (see [NodeCache])
+    cache: U::Cache,
+    /// Aggregate of the keys of the tree that is rooted by this node (see [Augment])
+    aggregate: A::Value,
     /// Reference to parent node
-    parent: TreeTrunk<T, U>,
+    parent: TreeTrunk<T, U, A>,
     /// Reference to left child node
-    left: Tree<T, U>,
+    left: Subtree<T, U, A>,
     /// Reference to right child node
-    right: Tree<T, U>,
+    right: Subtree<T, U, A>,
     /// [TreeBalance] type to use for balancing
-    balance: U
+    balance: U,
+    /// Whether the key has been lazily deleted
+    tombstone: bool
 }
 
-impl <T: Ord, U: TreeBalance> TreeNode<T, U> {
+impl <T: Ord, U: TreeBalance, A: Augment<T>> TreeNode<T, U, A> {
     /// Constructs a new tree node with the given key
     /// 
     /// Creates a parentless [TreeNode] that owns the given key
     /// and initializes the associated [TreeBalance] as a root node
-    pub fn new_with(key: T) -> Self {
+    pub fn new_with(key: T, rng: &mut TreeRng) -> Self {
+        let balance = U::new_root(&mut BalanceContext::open((&key, rng)));
         TreeNode {
+            aggregate: Self::leaf_aggregate(&key),
             key,
-            height: 1,
-            leaves: 1,
+            cache: U::Cache::leaf(),
             parent: Weak::new(),
-            left: Tree::new(),
-            right: Tree::new(),
-            balance: U::new_root()
+            left: Subtree::new(),
+            right: Subtree::new(),
+            balance,
+            tombstone: false
         }
     }
 
@@ -45,46 +51,85 @@ impl <T: Ord, U: TreeBalance> TreeNode<T, U> {
     /// 
     /// Creates [TreeNode] that owns the given key and references the given parent
     /// and initializes the associated [TreeBalance] as a non-root node (even if parent reference is None)
-    pub fn new_with_parent(key: T, parent: TreeTrunk<T, U>) -> Self {
+    pub fn new_with_parent(key: T, parent: TreeTrunk<T, U, A>, rng: &mut TreeRng) -> Self {
+        let balance = U::new(&mut BalanceContext::open((&key, rng)));
         TreeNode {
+            aggregate: Self::leaf_aggregate(&key),
             key,
-            height: 1,
-            leaves: 1,
+            cache: U::Cache::leaf(),
             parent,
-            left: Tree::new(),
-            right: Tree::new(),
-            balance: U::new()
+            left: Subtree::new(),
+            right: Subtree::new(),
+            balance,
+            tombstone: false
         }
     }
 
+    /// Constructs a new tree node with the given key and balance
+    /// 
+    /// Creates a parentless [TreeNode] that owns the given key and balance
+    pub fn new_with_balance(key: T, balance: U) -> Self {
+        TreeNode {
+            aggregate: Self::leaf_aggregate(&key),
+            key,
+            cache: U::Cache::leaf(),
+            parent: Weak::new(),
+            left: Subtree::new(),
+            right: Subtree::new(),
+            balance,
+            tombstone: false
+        }
+    }
+
+    /// Returns the aggregate of a tree holding only the given key
+    fn leaf_aggregate(key: &T) -> A::Value {
+        A::combine(&A::empty(), key, &A::empty())
+    }
+
     /// Update the node's knowledge of the tree
     /// 
     /// Updates the node's cached information regarding its' tree
-    /// structure (height and number of leaves)
+    /// structure, only the information requested by the [NodeCache]
+    /// of the associated [TreeBalance] is kept.
+    /// The aggregate of the [Augment] is always kept up to date.
     pub fn update(&mut self) {
-        self.update_leaves();
-        self.update_height();
+        let empty = A::empty();
+        let left = self.left.0.as_ref().map(|node| node.borrow());
+        let right = self.right.0.as_ref().map(|node| node.borrow());
+        self.cache = U::Cache::combine(
+            left.as_ref().map(|node| &node.cache),
+            right.as_ref().map(|node| &node.cache)
+        );
+        self.aggregate = self.combine_aggregate(
+            left.as_ref().map_or(&empty, |node| &node.aggregate),
+            right.as_ref().map_or(&empty, |node| &node.aggregate)
+        );
     }
 
-    /// Updates the node's counter the tree's leaves
-    fn update_leaves(&mut self) {
-        self.leaves = max(
-            1,
-            self.left.0.as_ref().map_or(0, |node| node.borrow().get_leaves())
-                + self.right.0.as_ref().map_or(0, |node| node.borrow().get_leaves())
-        )
+    /// Updates the node's aggregate of the keys in the tree
+    fn update_aggregate(&mut self) {
+        let empty = A::empty();
+        let left = self.left.0.as_ref().map(|node| node.borrow());
+        let right = self.right.0.as_ref().map(|node| node.borrow());
+        self.aggregate = self.combine_aggregate(
+            left.as_ref().map_or(&empty, |node| &node.aggregate),
+            right.as_ref().map_or(&empty, |node| &node.aggregate)
+        );
     }
 
-    /// Updates the node's counter of the height of the tree
-    fn update_height(&mut self) {
-        self.height = max(
-            self.left.0.as_ref().map_or(0, |node| node.borrow().height),
-            self.right.0.as_ref().map_or(0, |node| node.borrow().height)
-        ) + 1;
+    /// Returns the aggregate of the node's key combined between the given aggregates
+    /// 
+    /// A lazily deleted key is left out (see [Augment::merge])
+    pub fn combine_aggregate(&self, left: &A::Value, right: &A::Value) -> A::Value {
+        if self.tombstone {
+            A::merge(left, right)
+        } else {
+            A::combine(left, &self.key, right)
+        }
     }
 
     /// Returns a reference to the [TreeBranch] that is pointed at the given path
-    pub fn get_child(&self, pos: TreePath) -> Option<&TreeBranch<T, U>> {
+    pub fn get_child(&self, pos: TreePath) -> Option<&TreeBranch<T, U, A>> {
         match pos {
             Left => self.left.0.as_ref(),
             Right => self.right.0.as_ref()
@@ -102,31 +147,31 @@ impl <T: Ord, U: TreeBalance> TreeNode<T, U> {
     }
 
     /// Returns a reference to this node's parent [TreeBranch]
-    pub fn get_parent(&self) -> Option<TreeBranch<T, U>> {
+    pub fn get_parent(&self) -> Option<TreeBranch<T, U, A>> {
         Weak::upgrade(&self.parent)
     }
 
-    /// Returns a mutable reference to the [Tree]
+    /// Returns a mutable reference to the [Subtree]
     /// used to point to the child along the given [TreePath]
-    pub fn get_joint(&mut self, pos: TreePath) -> &mut Tree<T, U> {
+    pub fn get_joint(&mut self, pos: TreePath) -> &mut Subtree<T, U, A> {
         match pos {
             Left => &mut self.left,
             Right => &mut self.right
         }
     }
 
-    /// Returns a mutable reference to the [Tree]
+    /// Returns a mutable reference to the [Subtree]
     /// used to point to the parent of this node
-    pub fn get_parent_joint(&mut self) -> &mut TreeTrunk<T, U> {
+    pub fn get_parent_joint(&mut self) -> &mut TreeTrunk<T, U, A> {
         &mut self.parent
     }
 
-    /// Removes the child [Tree] at the given path and returns it
-    pub fn prune(&mut self, pos: TreePath) -> Tree<T, U> {
+    /// Removes the child [Subtree] at the given path and returns it
+    pub fn prune(&mut self, pos: TreePath) -> Subtree<T, U, A> {
         // Perform replacement
         let pruned = match pos {
-            Left => std::mem::replace(&mut self.left, Tree::new()),
-            Right => std::mem::replace(&mut self.right, Tree::new())
+            Left => core::mem::take(&mut self.left),
+            Right => core::mem::take(&mut self.right)
         };
 
         // Remove parent reference from the path we just detached
@@ -148,14 +193,98 @@ impl <T: Ord, U: TreeBalance> TreeNode<T, U> {
         }
     }
 
+    /// Returns a reference to the key of this node
+    pub fn get_key(&self) -> &T {
+        &self.key
+    }
+
+    /// Returns true if the node's key has been lazily deleted
+    pub fn is_tombstone(&self) -> bool {
+        self.tombstone
+    }
+
+    /// Marks (or unmarks) the node's key as lazily deleted
+    /// 
+    /// The node's aggregate is updated but its ancestors' are not
+    pub fn set_tombstone(&mut self, tombstone: bool) {
+        self.tombstone = tombstone;
+        self.update_aggregate();
+    }
+
     /// Returns the height of the tree rooted by this node
+    /// 
+    /// If the height is not cached it is computed by traversing the tree
     pub fn get_height(&self) -> usize {
-        self.height
+        self.cache.height().unwrap_or_else(|| self.compute_height())
     }
 
     /// Returns the number of leaves of the tree rooted by this node
+    /// 
+    /// If the leaf count is not cached it is computed by traversing the tree
     pub fn get_leaves(&self) -> usize {
-        self.leaves
+        self.cache.leaves().unwrap_or_else(|| self.compute_leaves())
+    }
+
+    /// Returns the number of nodes in the tree rooted by this node (including lazily deleted ones)
+    /// 
+    /// If the size is not cached it is computed by traversing the tree
+    pub fn get_size(&self) -> usize {
+        self.cache.size().unwrap_or_else(|| self.compute_size())
+    }
+
+    /// Returns the shape information cached by this node
+    pub fn get_cache(&self) -> &U::Cache {
+        &self.cache
+    }
+
+    /// Returns the aggregate of the keys of the tree rooted by this node (including lazily deleted ones)
+    pub fn get_aggregate(&self) -> &A::Value {
+        &self.aggregate
+    }
+
+    /// Computes the height of the tree rooted by this node in O(n)
+    fn compute_height(&self) -> usize {
+        let mut height = 1;
+        let mut stack: Vec<(TreeBranch<T, U, A>, usize)> = self.children().map(|c| (c, 2)).collect();
+        while let Some((node, depth)) = stack.pop() {
+            height = max(height, depth);
+            stack.extend(node.borrow().children().map(|c| (c, depth + 1)));
+        }
+        height
+    }
+
+    /// Computes the number of leaves of the tree rooted by this node in O(n)
+    fn compute_leaves(&self) -> usize {
+        let mut leaves = 0;
+        let mut stack: Vec<TreeBranch<T, U, A>> = self.children().collect();
+        if stack.is_empty() {
+            return 1;
+        }
+        while let Some(node) = stack.pop() {
+            let n = node.borrow();
+            let len = stack.len();
+            stack.extend(n.children());
+            if stack.len() == len {
+                leaves += 1;
+            }
+        }
+        leaves
+    }
+
+    /// Computes the number of nodes in the tree rooted by this node in O(n)
+    fn compute_size(&self) -> usize {
+        let mut size = 1;
+        let mut stack: Vec<TreeBranch<T, U, A>> = self.children().collect();
+        while let Some(node) = stack.pop() {
+            size += 1;
+            stack.extend(node.borrow().children());
+        }
+        size
+    }
+
+    /// Returns an iterator over the existing children of this node
+    fn children(&self) -> impl Iterator<Item = TreeBranch<T, U, A>> + '_ {
+        self.left.0.iter().chain(self.right.0.iter()).map(Rc::clone)
     }
 
     /// Finds which child path the provided [TreeNode] should be placed on
@@ -168,7 +297,7 @@ impl <T: Ord, U: TreeBalance> TreeNode<T, U> {
     /// # Panics
     /// 
     /// The function panics if the key of the child node and this node match
-    pub fn find_placement(&self, child: &TreeNode<T, U>) -> TreePath {
+    pub fn find_placement(&self, child: &TreeNode<T, U, A>) -> TreePath {
         self.search(&child.key).unwrap()
     }
 
@@ -194,7 +323,8 @@ impl <T: Ord, U: TreeBalance> TreeNode<T, U> {
             assert_ne!(key.cmp(&n.key), Ordering::Greater);
         }
 
-        std::mem::swap(&mut self.key, &mut key);
+        core::mem::swap(&mut self.key, &mut key);
+        self.update_aggregate();
 
         key
     }
@@ -204,43 +334,82 @@ impl <T: Ord, U: TreeBalance> TreeNode<T, U> {
     }
 }
 
-impl <T, U> Display for TreeNode<T, U>
+impl <T, U, A: Augment<T>> Display for TreeNode<T, U, A>
 where
     T: Ord + Display,
     U: TreeBalance
 {
 
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
         match self.left.0 {
-            Some(ref node) => std::fmt::Display::fmt(&node.borrow(), f),
+            Some(ref node) => core::fmt::Display::fmt(&node.borrow(), f),
             None => Ok(())
-        }.and_then(|_| write!(f, "{}, ", self.key)).and_then(|_|
+        }.and_then(|_| if self.tombstone {
+            Ok(())
+        } else {
+            write!(f, "{}, ", self.key)
+        }).and_then(|_|
             match self.right.0 {
-                Some(ref node) => std::fmt::Display::fmt(&node.borrow(), f),
+                Some(ref node) => core::fmt::Display::fmt(&node.borrow(), f),
                 None => Ok(())
             }
         )
     }
 }
 
-impl <T, U> Debug for TreeNode<T, U>
+/// Height and number of leaves of each node in a tree, measured bottom up in a single traversal
+///
+/// Used to print trees whose balance does not cache this information
+/// without traversing the tree rooted by every node
+pub struct Shape {
+    height: usize,
+    leaves: usize,
+    left: Option<Box<Shape>>,
+    right: Option<Box<Shape>>
+}
+
+impl Shape {
+    /// Measures the tree rooted by the given node
+    pub fn measure<T: Ord, U: TreeBalance, A: Augment<T>>(node: &TreeNode<T, U, A>) -> Self {
+        let left = node.left.0.as_ref().map(|n| Box::new(Shape::measure(&n.borrow())));
+        let right = node.right.0.as_ref().map(|n| Box::new(Shape::measure(&n.borrow())));
+        let (height, leaves) = match (&left, &right) {
+            (None, None) => (1, 1),
+            (Some(s), None) | (None, Some(s)) => (s.height + 1, s.leaves),
+            (Some(l), Some(r)) => (max(l.height, r.height) + 1, l.leaves + r.leaves)
+        };
+        Shape { height, leaves, left, right }
+    }
+}
+
+/// Prints a node along with the [Shape] of the tree it roots
+pub struct DebugNode<'a, T: Ord, U: TreeBalance, A: Augment<T>>(pub &'a TreeNode<T, U, A>, pub &'a Shape);
+
+impl <T, U, A: Augment<T>> Debug for DebugNode<'_, T, U, A>
 where
     T: Ord + Debug,
     U: TreeBalance + Debug
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        let mut builder = f.debug_struct(&format!("{:?}", &self.key));
-        builder.field("balance", &self.balance);
-        builder.field("height", &self.height);
-        builder.field("leaves", &self.leaves);
-        match self.right.0 {
-            Some(ref node) => builder.field("right", &node.borrow()),
-            None => builder.field("right", &"None")
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        let DebugNode(node, shape) = self;
+        let mut builder = f.debug_struct(&format!("{:?}", &node.key));
+        builder.field("balance", &node.balance);
+        if node.tombstone {
+            builder.field("tombstone", &node.tombstone);
+        }
+        builder.field("height", &shape.height);
+        builder.field("leaves", &shape.leaves);
+        if let Some(size) = node.cache.size() {
+            builder.field("size", &size);
+        }
+        match (&node.right.0, &shape.right) {
+            (Some(child), Some(measured)) => builder.field("right", &DebugNode(&child.borrow(), measured)),
+            _ => builder.field("right", &"None")
         };
-        match self.left.0 {
-            Some(ref node) => builder.field("left", &node.borrow()),
-            None => builder.field("left", &"None")
+        match (&node.left.0, &shape.left) {
+            (Some(child), Some(measured)) => builder.field("left", &DebugNode(&child.borrow(), measured)),
+            _ => builder.field("left", &"None")
         };
         builder.finish()
     }
-}
\ No newline at end of file
+}
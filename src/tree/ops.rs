@@ -1,26 +1,42 @@
-use std::rc::Rc;
-use std::cell::RefCell;
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::cmp::max;
+use core::ops::{Bound, Range, RangeBounds};
 
 use super::*;
 use super::TreePath::*;
 use super::inspect::*;
 
-/// Perform an insertion using a given key
-/// on a binary tree with the given root
+/// Result of [bst_insert_from], holding the new root, the node that holds the key,
+/// whether the key was inserted and any error the balance failed with
+type InsertResult<T, U, A> = (Subtree<T, U, A>, TreeBranch<T, U, A>, bool, Result<(), InspectError>);
+
+/// Perform an insertion using a given key on a binary tree
+/// with the given root, starting the search at the given node
+/// 
+/// The search for the insertion point begins at `start` instead of the root,
+/// which must be a node of the tree whose subtree contains the position of the key
+/// (see [bst_finger]).  If no start node is given the search begins at the root.
+/// 
+/// Returns the new root, the node that holds the key (either the newly inserted
+/// node or the node that already held it) and true if the key was inserted.
+/// If the balance fails the rest of rebalancing is skipped and the error is returned
+/// alongside, the key is still inserted.  The rotations performed are added to `rotations`.
 /// 
 /// # Panics
 /// 
 /// This function panics if the root node is not actually the root node (has a parent)
-pub fn bst_insert<T: Ord, U: TreeBalance>(root: Tree<T, U>, key: T) -> Tree<T, U> {
+pub fn bst_insert_from<T: Ord, U: TreeBalance, A: Augment<T>>(mut root: Subtree<T, U, A>, start: Option<TreeBranch<T, U, A>>, key: T, rng: &mut TreeRng, rotations: &mut usize) -> InsertResult<T, U, A> {
     // Unwrap the root
-    if let Some(mut p) = root.branch().map(|r| Rc::clone(r)) {
+    if let Some(mut p) = start {
         // Ensure root is the actual root
-        { assert!(p.borrow().get_parent().is_none()) };
+        { assert!(root.branch().unwrap().borrow().get_parent().is_none()) };
         // Find the parent node to insert to and the path to insert on...
         // Or if the tree is empty we insert at root and be done
         let mut xpath;
+        let mut restructured = false;
         loop {
-            // Get the next child node
+            // Get the next child node and the path the search takes from it
             let child = {
                 let pnode = p.borrow();
                 let search_path = pnode.search(&key);
@@ -29,137 +45,295 @@ pub fn bst_insert<T: Ord, U: TreeBalance>(root: Tree<T, U>, key: T) -> Tree<T, U
                     xpath = path;
                 } else {
                     // Found key in tree already, just return root
-                    return root
+                    drop(pnode);
+                    if restructured {
+                        // The root may have moved while searching
+                        let root = bst_update_to_root(Rc::clone(&p));
+                        return (Subtree::new_with(root), p, false, Ok(()))
+                    }
+                    return (root, p, false, Ok(()))
                 }
 
-                pnode.get_child(xpath).map(|node| Rc::clone(node))
+                pnode.get_child(xpath).map(|x| (Rc::clone(x), x.borrow().search(&key)))
             };
 
-            // If child exists on the found path
-            // we set it as our parent and continue traversal
-            if let Some(x) = child {
-                p = x;
+            // If child exists on the found path let the balance work on the
+            // subtree and then set the child as our parent and continue traversal
+            if let Some((x, next_path)) = child {
+                let (top, rotated) = U::before_descend(NodeInspector::open(Rc::clone(&p)), (xpath, next_path), Descent::Insert).into_inner();
+                *rotations += rotated;
+                if Rc::ptr_eq(&top, &p) {
+                    p = x;
+                } else {
+                    // Search again from the new top of the subtree
+                    top.borrow_mut().update();
+                    if top.borrow().get_parent().is_none() {
+                        // Keep the new root alive
+                        root = Subtree::new_with(Rc::clone(&top));
+                    }
+                    restructured = true;
+                    p = top;
+                }
             } else {
                 // Found an empty node, break
                 break;
             }
         }
 
-        // Perform insert and return a reference to the grandparent
-        let grandparent = {
+        // Perform insert and rebalance the tree
+        let x = Rc::new(
+            RefCell::new(
+                TreeNode::new_with_parent(key, Rc::downgrade(&p), rng)
+            )
+        );
+        {
             let mut pnode = p.borrow_mut();
-            // Update parent node
-            *pnode.get_joint(xpath) = Tree::new_with(
-                Rc::new(
-                    RefCell::new(
-                        TreeNode::new_with_parent(key, Rc::downgrade(&p))
-                    )
-                )
-            );
+            *pnode.get_joint(xpath) = Subtree::new_with(Rc::clone(&x));
             pnode.update();
-            // Get grandparent info for rebalancing
-            pnode.get_parent().map(|b| {
-                let placement = b.borrow().find_placement(&pnode);
-                (b, placement)
-            })
-        };
+        }
+        let (root, checked) = bst_rebalance_inserted(root, &x, rotations);
+        (root, x, true, checked)
 
-        // Rebalance Tree
-        if let Some((mut r, mut ppath)) = grandparent {
-            loop {
-                // Perform the rebalance
-                let (current, next_pos) = U::rebalance_insert(NodeInspector::open(r), (ppath, xpath)).into_inner();
-                r = current;
-                // Get the next node based off next_pos
-                let next = {
-                    let mut rnode = r.borrow_mut();
-                    rnode.update();
-                    match next_pos {
-                        NodeOffset::Root => break, // Gets returned if we no-longer need to rebalance
-                        NodeOffset::Parent =>
-                            rnode.get_parent().map(|b| {
-                                let placement = b.borrow().find_placement(&rnode);
-                                (b, placement)
-                        }),
-                        // In our implemented cases (AVL, Red Black, Unbalanced) this shouldn't happen.
-                        // However, to allow for implementing other balancing methods it might be useful to allow this case
-                        // If more time was budgeted this might be worth implementing
-                        NodeOffset::Child(_) => panic!("Should not happen!")
-                    }
-                };
+    } else {
+        // Tree is empty, return fresh new node
+        let x = Rc::new(
+            RefCell::new(
+                TreeNode::new_with(key, rng)
+            )
+        );
+        (Subtree::new_with(Rc::clone(&x)), x, true, Ok(()))
+    }
+}
 
-                // Check if we have another path to traverse
-                // and update locals accordingly otherwise break
-                if let Some((n, path)) = next {
-                    xpath = ppath;
-                    ppath = path;
-                    r = n;
-                } else {
-                    break;
-                }
+/// Rebalances the tree with the given root after the node `x` was linked in as a leaf
+/// 
+/// The parent of `x` must already be updated.  Returns the new root and any error the
+/// balance failed with, in which case the rest of rebalancing is skipped.
+/// The rotations performed are added to `rotations`.
+fn bst_rebalance_inserted<T: Ord, U: TreeBalance, A: Augment<T>>(mut root: Subtree<T, U, A>, x: &TreeBranch<T, U, A>, rotations: &mut usize) -> (Subtree<T, U, A>, Result<(), InspectError>) {
+    // Find the first node to rebalance, starting at the grandparent or the parent if it is the root
+    let (mut r, mut path) = {
+        let p = x.borrow().get_parent().unwrap();
+        let pnode = p.borrow();
+        let xpath = pnode.find_placement(&x.borrow());
+        match pnode.get_parent() {
+            Some(g) => {
+                let placement = g.borrow().find_placement(&pnode);
+                (g, (placement, Some(xpath)))
+            },
+            None => {
+                drop(pnode);
+                (p, (xpath, None))
             }
+        }
+    };
 
-            // If we have anything more to go up the tree, do now
-            // updating each node's understanding of the tree as we do
-            let mut next = { r.borrow().get_parent() };
-            while let Some(n) = next {
-                r = n;
-                let mut rnode = r.borrow_mut();
-                rnode.update();
-                next = rnode.get_parent();
+    // Rebalance Tree
+    let mut checked = Ok(());
+    loop {
+        // Perform the rebalance
+        let (current, next_pos, rotated) = U::rebalance_insert(NodeInspector::open(r), path).into_inner();
+        *rotations += rotated;
+        r = current;
+        r.borrow_mut().update();
+        if r.borrow().get_parent().is_none() {
+            // Keep the new root alive in case we move down the tree
+            root = Subtree::new_with(Rc::clone(&r));
+        }
+        // Get the next node based off next_pos (stopping if the balance failed)
+        let next = match next_pos.and_then(|offset| bst_offset(&r, offset)) {
+            Ok(next) => next,
+            Err(error) => {
+                checked = Err(error);
+                break
             }
+        };
 
-            // Return a tree wrapping root
-            Tree::new_with(r)
+        // Check if we have another node to rebalance with a path
+        // down towards the inserted node and update locals accordingly otherwise break
+        if let Some((n, next_path)) = next.and_then(|(n, _)| insert_route(&n, x).map(|p| (n, p))) {
+            path = next_path;
+            r = n;
         } else {
-            // Return a tree wrapping the parent
-            // since there was no grandparent
-            Tree::new_with(p)
+            break;
         }
+    }
 
-    } else {
-        // Tree is empty, return fresh new node
-        Tree::new_with(
-            Rc::new(
-                RefCell::new(
-                    TreeNode::new_with(key)
-                )
-            )
-        )
+    // If we have anything more to go up the tree, do now
+    // updating each node's understanding of the tree as we do
+    let mut next = { r.borrow().get_parent() };
+    while let Some(n) = next {
+        r = n;
+        let mut rnode = r.borrow_mut();
+        rnode.update();
+        next = rnode.get_parent();
     }
+
+    // Return a tree wrapping root
+    drop(root);
+    (Subtree::new_with(r), checked)
+}
+
+/// Links the given detached node into the tree with the given root after its last node without comparing keys
+/// 
+/// The key of the node must be greater than every key in the tree and `last` must be the last node
+/// of the tree (None if the tree is empty).  The node is given a new balance and the tree is rebalanced.
+/// Returns the new root.
+/// 
+/// # Panics
+/// 
+/// This function panics if the balance fails while rebalancing
+fn bst_append<T: Ord, U: TreeBalance, A: Augment<T>>(root: Subtree<T, U, A>, last: Option<&TreeBranch<T, U, A>>, x: TreeBranch<T, U, A>, rng: &mut TreeRng) -> Subtree<T, U, A> {
+    let p = match last {
+        Some(p) => p,
+        None => {
+            let mut xnode = x.borrow_mut();
+            let balance = U::new_root(&mut BalanceContext::open((xnode.get_key(), rng)));
+            *xnode.get_balance_mut() = balance;
+            xnode.update();
+            drop(xnode);
+            return Subtree::new_with(x)
+        }
+    };
+    {
+        let mut xnode = x.borrow_mut();
+        let balance = U::new(&mut BalanceContext::open((xnode.get_key(), rng)));
+        *xnode.get_balance_mut() = balance;
+        *xnode.get_parent_joint() = Rc::downgrade(p);
+        xnode.update();
+        drop(xnode);
+        let mut pnode = p.borrow_mut();
+        *pnode.get_joint(Right) = Subtree::new_with(Rc::clone(&x));
+        pnode.update();
+    }
+    let (root, checked) = bst_rebalance_inserted(root, &x, &mut 0);
+    if let Err(error) = checked {
+        panic!("{}", error)
+    }
+    root
+}
+
+/// Finds the path from the given node down towards the inserted node `x`
+/// 
+/// Returns the path to the child and grandchild in the direction of `x` (None if the child is `x`),
+/// or None if `x` is not below the node
+fn insert_route<T: Ord, U: TreeBalance, A: Augment<T>>(node: &TreeBranch<T, U, A>, x: &TreeBranch<T, U, A>) -> Option<(TreePath, Option<TreePath>)> {
+    let xnode = x.borrow();
+    let node = node.borrow();
+    let ppath = node.search(xnode.get_key())?;
+    let p = node.get_child(ppath)?;
+    if Rc::ptr_eq(p, x) {
+        return Some((ppath, None));
+    }
+    let xpath = p.borrow().search(xnode.get_key())?;
+    Some((ppath, Some(xpath)))
+}
+
+/// Travels up the given number of levels from a node, updating the nodes passed along the way
+/// 
+/// Returns the ancestor and the path from it to the last node passed,
+/// or None if the root is passed (or no levels are travelled)
+fn bst_ascend<T: Ord, U: TreeBalance, A: Augment<T>>(node: &TreeBranch<T, U, A>, levels: usize) -> Option<(TreeBranch<T, U, A>, TreePath)> {
+    let mut x = Rc::clone(node);
+    let mut path = None;
+    for _ in 0..levels {
+        let parent = x.borrow().get_parent()?;
+        if path.is_some() {
+            x.borrow_mut().update();
+        }
+        path = Some(parent.borrow().find_placement(&x.borrow()));
+        x = parent;
+    }
+    path.map(|path| (x, path))
+}
+
+/// Travels down the given paths from a node, returning the descendant
+/// 
+/// Returns None if there is no node at the end of the paths
+fn bst_descend<T: Ord, U: TreeBalance, A: Augment<T>>(node: &TreeBranch<T, U, A>, paths: &[TreePath]) -> Option<TreeBranch<T, U, A>> {
+    paths.iter().try_fold(Rc::clone(node), |x, &path| {
+        x.borrow().get_child(path).map(Rc::clone)
+    })
 }
 
-// Helper enum, only used for bst_delete
-// Could be replaced with Option type after refactor
-enum DeletePosition<T: Ord, U: TreeBalance> {
-    // Deleted a child node
-    Child(TreeBranch<T, U>, TreePath),
-    // Deleted the root node
-    Root
+/// Result of [bst_offset], holding the node at the offset and the last path travelled
+type OffsetResult<T, U, A> = Result<Option<(TreeBranch<T, U, A>, Option<TreePath>)>, InspectError>;
+
+/// Finds the node at an offset returned by a balance from the given node
+/// 
+/// Returns the node and the last path travelled (when moving up this is the path to the node we came from),
+/// None if the offset stops rebalancing (it is [NodeOffset::Root] or moves past the root)
+/// or an error if the offset points to an empty node or is a [NodeOffset::Descendant] without paths
+fn bst_offset<T: Ord, U: TreeBalance, A: Augment<T>>(node: &TreeBranch<T, U, A>, offset: NodeOffset) -> OffsetResult<T, U, A> {
+    match offset {
+        NodeOffset::Root => Ok(None),
+        NodeOffset::Parent => Ok(bst_ascend(node, 1).map(|(n, path)| (n, Some(path)))),
+        NodeOffset::Ancestor(levels) => Ok(bst_ascend(node, levels).map(|(n, path)| (n, Some(path)))),
+        NodeOffset::Child(path) => bst_descend(node, &[path]).map(|n| Some((n, Some(path)))).ok_or(InspectError::InvalidOffset),
+        NodeOffset::Descendant(paths) if paths.is_empty() => Err(InspectError::InvalidOffset),
+        NodeOffset::Descendant(paths) => bst_descend(node, &paths).map(|n| Some((n, paths.last().copied()))).ok_or(InspectError::InvalidOffset)
+    }
 }
 
-/// Perform a deletion using a given key on a binary
-/// tree with the given root and return the key
+/// Result of [bst_delete_from], holding the new root, the removed key, the node
+/// closest to where the deletion took place and any error the balance failed with
+type DeleteResult<T, U, A> = (Subtree<T, U, A>, Option<T>, Option<TreeBranch<T, U, A>>, Result<(), InspectError>);
+
+/// Perform a deletion using a given key on a binary tree with the given root,
+/// starting the search at the given node, and return the key
+/// 
+/// The search for the key begins at `start` instead of the root, which must be a node
+/// of the tree whose subtree contains the position of the key (see [bst_finger]).
+/// If no start node is given the search begins at the root.
+/// 
+/// Along with the new root and the key, returns the node closest to where
+/// the deletion took place (the parent of the removed node) if there is one.
+/// If the balance fails the rest of rebalancing is skipped and the error is returned
+/// alongside, the key is still removed.  The rotations performed are added to `rotations`.
 /// 
 /// # Panics
 /// 
 /// This function panics if the root node is not actually the root node (has a parent)
-pub fn bst_delete<T: Ord, U: TreeBalance>(root: Tree<T, U>, key: &T) -> (Tree<T, U>, Option<T>) {
+pub fn bst_delete_from<T: Ord, U: TreeBalance, A: Augment<T>>(root: Subtree<T, U, A>, start: Option<TreeBranch<T, U, A>>, key: &T, rotations: &mut usize) -> DeleteResult<T, U, A> {
     // Find the parent node of the node we wish to delete
     // Or if the tree is empty we just return root
-    if let Some(mut p) = root.into_inner() {
+    if let Some(r) = root.into_inner() {
         // Ensure root is the actual root
-        { assert!(p.borrow().get_parent().is_none()) };
+        { assert!(r.borrow().get_parent().is_none()) };
+        let mut p = start.unwrap_or_else(|| Rc::clone(&r));
         // DO NOT LET THIS VARIABLE DIE OTHERWISE THE TREE WILL BEGIN TO DEALLOCATE
-        let mut root_keep_alive = Tree::new_with(Rc::clone(&p));
-        let mut xpath = {
-            p.borrow().search(key)
-        };
-        // If we aren't deleting the root we need to find the
-        // parent node and the path to the child node to delete
-        if let Some(mut path) = xpath {
-        // Could probably be refactored to a while let
-            loop {
+        let mut root_keep_alive = Subtree::new_with(r);
+        let mut xpath;
+        let mut restructured = false;
+        loop {
+            xpath = {
+                p.borrow().search(key)
+            };
+            // If we start on the node to delete we need to step back to
+            // its parent (if there is one) so we can delete relative to it
+            if xpath.is_none() {
+                let parent = p.borrow().get_parent();
+                if let Some(parent) = parent {
+                    xpath = Some(parent.borrow().find_placement(&p.borrow()));
+                    p = parent;
+                }
+            }
+            // If we aren't deleting the root we need to find the
+            // parent node and the path to the child node to delete
+            let mut path = match xpath {
+                Some(path) => path,
+                None => match bst_successor_descend(&p, rotations) {
+                    Some(top) => {
+                        // The root was rotated, search again from the new root
+                        root_keep_alive = Subtree::new_with(Rc::clone(&top));
+                        restructured = true;
+                        p = top;
+                        continue
+                    },
+                    None => break
+                }
+            };
+            let top = loop {
                 // Get the next parent and the next path
                 let next = {
                     let pnode = p.borrow();
@@ -168,11 +342,20 @@ pub fn bst_delete<T: Ord, U: TreeBalance>(root: Tree<T, U>, key: &T) -> (Tree<T,
                     })
                 };
 
+                // Let the balance work on the subtree before moving down
+                if let Some((_, next_path)) = next {
+                    let (top, rotated) = U::before_descend(NodeInspector::open(Rc::clone(&p)), (path, next_path), Descent::Delete).into_inner();
+                    *rotations += rotated;
+                    if !Rc::ptr_eq(&top, &p) {
+                        break Some(top);
+                    }
+                }
+
                 match next {
                     // If we have a parent but no path, that means we found the node
-                    Some((_, None)) => {
+                    Some((x, None)) => {
                         xpath = Some(path);
-                        break
+                        break bst_successor_descend(&x, rotations)
                     },
                     // If we have another path to go down keep up traversal
                     Some((x, Some(next_path))) => {
@@ -180,106 +363,74 @@ pub fn bst_delete<T: Ord, U: TreeBalance>(root: Tree<T, U>, key: &T) -> (Tree<T,
                         p = x;
                     }
                     // We hit the bottom of the tree, return root and no key
-                    None => return (root_keep_alive, None)
-                }
-            }
-        }
-
-        // Perform delete
-        let (key, mut balance, mut p, mut xpath) = {
-            // Attempt the pop on x, re-write parent
-            let (popped, position) = if let Some(path) = xpath {
-                // Pop relative to the parent
-                let popped = bst_pop(p.borrow_mut().get_joint(path));
-                (popped, DeletePosition::Child(p, path))
-            } else {
-                // If there is not xpath that means the node we wish to pop is root
-                drop(root_keep_alive); // Drop here to remove extra Rc pointer that is needed so that the Weak references don't die
-                root_keep_alive = Tree::new_with(p);
-                (bst_pop(&mut root_keep_alive), DeletePosition::Root)
-            };
-
-            // If the pop was successful, return the result...
-            // Otherwise find a node to swap with
-            if let Some((key, balance)) = popped {
-                match position {
-                    DeletePosition::Child(p, path) => (key, balance, p, path),
-                    DeletePosition::Root => {
-                        if let Some(r) = root_keep_alive.branch() {
-                            r.borrow_mut().mark_root()
+                    None => {
+                        if restructured {
+                            // The root may have moved while searching
+                            let root = bst_update_to_root(Rc::clone(&p));
+                            return (Subtree::new_with(root), None, Some(p), Ok(()))
                         }
-                        return (root_keep_alive, Some(key))
+                        return (root_keep_alive, None, Some(p), Ok(()))
                     }
                 }
-            } else {
-                // Store the node we will swap with (original x)
-                let to_swap = match position {
-                    DeletePosition::Child(p, path) => Rc::clone(p.borrow().get_child(path).unwrap()),
-                    DeletePosition::Root => {
-                        Rc::clone(root_keep_alive.branch().unwrap())
-                    }
-                };
-
-                let mut xpath = Right;
-                let mut p = Rc::clone(&to_swap);
-                let key;
-                let balance;
-                loop {
-                    // Check if we should pop
-                    let pop = {
-                        let pnode = p.borrow();
-                        let xnode = pnode.get_child(xpath).unwrap().borrow();
-                        xnode.get_child(Left).is_none()
-                    };
+            };
 
-                    // If yes, pop the child node at xpath. Otherwise continue travelling the tree
-                    if pop {
-                        let (popped_key, popped_balance) = {
-                            let mut pnode = p.borrow_mut();
-                            bst_pop(pnode.get_joint(xpath)).unwrap()
-                        };
-                        balance = popped_balance;
-                        key = to_swap.borrow_mut().replace_key(popped_key);
-                        break;
-                    } else {
-                        let next_p = {
-                            Rc::clone(&p.borrow().get_child(xpath).unwrap())
-                        };
-                        xpath = Left;
-                        p = next_p;
+            // Search again from the new top of the subtree if it was rotated
+            match top {
+                Some(top) => {
+                    top.borrow_mut().update();
+                    if top.borrow().get_parent().is_none() {
+                        // Keep the new root alive
+                        root_keep_alive = Subtree::new_with(Rc::clone(&top));
                     }
-                }
-                
-                (key, balance, p, xpath)
+                    restructured = true;
+                    p = top;
+                },
+                None => break
+            }
+        }
+
+        // Perform delete
+        let placement = match xpath {
+            Some(path) => Some((p, path)),
+            None => {
+                // The node to delete is the root, drop the extra pointer to it so it can be popped
+                drop(p);
+                None
+            }
+        };
+        let (key, balance, mut p, mut xpath) = match bst_remove(&mut root_keep_alive, placement, rotations) {
+            (key, Some((p, xpath, balance))) => (key, balance, p, xpath),
+            (key, None) => {
+                let near = root_keep_alive.branch().map(Rc::clone);
+                return (root_keep_alive, Some(key), near, Ok(()))
             }
         };
 
+        let near = Rc::clone(&p);
+
         // Rebalance Tree
+        let mut checked = Ok(());
         loop {
-            let (current, next_pos) = U::rebalance_delete(NodeInspector::open(p), xpath, &mut balance).into_inner();
+            let (current, next_pos, rotated) = U::rebalance_delete(NodeInspector::open(p), xpath, &balance).into_inner();
+            *rotations += rotated;
     
-            // Based on the instructions from the balancer we either rebalance a child, parent, or stop and just go to root
-            let next = {
-                p = current;
-                let mut pnode = p.borrow_mut();
-                pnode.update();
-                match next_pos {
-                    NodeOffset::Root => break,
-                    NodeOffset::Parent => {
-                        pnode.get_parent().map(|b| {
-                            let placement = b.borrow().find_placement(&pnode);
-                            (b, placement)
-                        })
-                    },
-                    NodeOffset::Child(path) => {
-                        let new = Rc::clone(pnode.get_child(path).unwrap());
-                        Some((new, path))
-                    }
+            // Based on the instructions from the balancer we either rebalance a descendant, an ancestor, or stop and just go to root
+            p = current;
+            p.borrow_mut().update();
+            if p.borrow().get_parent().is_none() {
+                // Keep the new root alive in case we move down the tree
+                root_keep_alive = Subtree::new_with(Rc::clone(&p));
+            }
+            let next = match next_pos.and_then(|offset| bst_offset(&p, offset)) {
+                Ok(next) => next,
+                Err(error) => {
+                    checked = Err(error);
+                    break
                 }
             };
 
             // If there is more to traverse, do it
-            if let Some((n, path)) = next {
+            if let Some((n, Some(path))) = next {
                 xpath = path;
                 p = n;
             } else {
@@ -297,10 +448,115 @@ pub fn bst_delete<T: Ord, U: TreeBalance>(root: Tree<T, U>, key: &T) -> (Tree<T,
         }
 
         // Return the new root and the key
-        (Tree::new_with(p), Some(key))
+        drop(root_keep_alive);
+        (Subtree::new_with(p), Some(key), Some(near), checked)
     } else {
         // Tree is empty return an empty tree and no key
-        (Tree::new(), None)
+        (Subtree::new(), None, None, Ok(()))
+    }
+}
+
+/// Result of [bst_remove], holding the removed key and where to start rebalancing from
+type RemoveResult<T, U, A> = (T, Option<(TreeBranch<T, U, A>, TreePath, U)>);
+
+/// Removes the node at the given placement (the root if None) from the tree with the given root
+/// 
+/// A node with two children is not removed itself, instead its successor is popped and its key is swapped
+/// into the node, letting the balance work on the subtree on the way down (see [Descent::Successor]).
+/// Returns the removed key, and the parent of the popped node with the path it was popped from and
+/// the balance of the popped node to start rebalancing from.  If the root is popped its child (if any)
+/// takes its place and is marked as the root, and there is nothing to rebalance so None is returned.
+/// 
+/// # Panics
+/// 
+/// This function panics if there is no node at the placement, or if a strong pointer to the node to pop exists elsewhere (see [bst_pop])
+fn bst_remove<T: Ord, U: TreeBalance, A: Augment<T>>(root: &mut Subtree<T, U, A>, placement: Option<(TreeBranch<T, U, A>, TreePath)>, rotations: &mut usize) -> RemoveResult<T, U, A> {
+    // Attempt the pop on x, re-write parent
+    let popped = match &placement {
+        // Pop relative to the parent
+        Some((p, path)) => bst_pop(p.borrow_mut().get_joint(*path)),
+        None => bst_pop(root)
+    };
+
+    // If the pop was successful, return the result...
+    // Otherwise find a node to swap with
+    if let Some((key, balance)) = popped {
+        return match placement {
+            Some((p, path)) => (key, Some((p, path, balance))),
+            None => {
+                if let Some(r) = root.branch() {
+                    r.borrow_mut().mark_root()
+                }
+                (key, None)
+            }
+        }
+    }
+
+    // Store the node we will swap with (original x)
+    let to_swap = match placement {
+        Some((p, path)) => Rc::clone(p.borrow().get_child(path).unwrap()),
+        None => Rc::clone(root.branch().unwrap())
+    };
+
+    let mut xpath = Right;
+    let mut p = Rc::clone(&to_swap);
+    let key;
+    let balance;
+    loop {
+        // Check if we should pop
+        let pop = {
+            let pnode = p.borrow();
+            let xnode = pnode.get_child(xpath).unwrap().borrow();
+            xnode.get_child(Left).is_none()
+        };
+
+        // If yes, pop the child node at xpath. Otherwise continue travelling the tree
+        if pop {
+            let (popped_key, popped_balance) = {
+                let mut pnode = p.borrow_mut();
+                bst_pop(pnode.get_joint(xpath)).unwrap()
+            };
+            balance = popped_balance;
+            key = to_swap.borrow_mut().replace_key(popped_key);
+            break;
+        } else {
+            let next_p = {
+                Rc::clone(p.borrow().get_child(xpath).unwrap())
+            };
+            // Let the balance work on the subtree before moving down
+            let next_path = { next_p.borrow().get_child(Left).unwrap().borrow().get_child(Left).map(|_| Left) };
+            let (top, rotated) = U::before_descend(NodeInspector::open(Rc::clone(&next_p)), (Left, next_path), Descent::Successor).into_inner();
+            *rotations += rotated;
+            if Rc::ptr_eq(&top, &next_p) {
+                xpath = Left;
+                p = next_p;
+            } else {
+                top.borrow_mut().update();
+            }
+        }
+    }
+
+    (key, Some((p, xpath, balance)))
+}
+
+/// Lets the balance work on a node to delete before searching for its successor
+/// 
+/// Returns the new top of the node's subtree if the balance rotated it, or None if it did not
+/// (or the node has less than two children, in which case it has no successor to search for)
+fn bst_successor_descend<T: Ord, U: TreeBalance, A: Augment<T>>(x: &TreeBranch<T, U, A>, rotations: &mut usize) -> Option<TreeBranch<T, U, A>> {
+    let next_path = {
+        let xnode = x.borrow();
+        match (xnode.get_child(Left), xnode.get_child(Right)) {
+            (Some(_), Some(r)) => r.borrow().get_child(Left).map(|_| Left),
+            _ => return None
+        }
+    };
+    let (top, rotated) = U::before_descend(NodeInspector::open(Rc::clone(x)), (Right, next_path), Descent::Successor).into_inner();
+    *rotations += rotated;
+    if Rc::ptr_eq(&top, x) {
+        None
+    } else {
+        Some(top)
     }
 }
 
@@ -319,7 +575,7 @@ pub fn bst_delete<T: Ord, U: TreeBalance>(root: Tree<T, U>, key: &T) -> (Tree<T,
 /// pointer to a the "node to pop" exists outside of this function.
 /// 
 /// This function will panic if the node to remove is empty
-pub fn bst_pop<T: Ord, U: TreeBalance>(tree: &mut Tree<T, U>) -> Option<(T, U)> {
+pub fn bst_pop<T: Ord, U: TreeBalance, A: Augment<T>>(tree: &mut Subtree<T, U, A>) -> Option<(T, U)> {
     let successor = {
         let mut x = tree.branch().unwrap().borrow_mut();
         if x.get_child(Right).is_some() && x.get_child(Left).is_some() {
@@ -334,7 +590,7 @@ pub fn bst_pop<T: Ord, U: TreeBalance>(tree: &mut Tree<T, U>) -> Option<(T, U)>
             // Change the successor's parent
             if let Some(s) = suc.branch() {
                 let parent = x.get_parent().map_or(
-                    std::rc::Weak::new(),
+                    alloc::rc::Weak::new(),
                     |b| Rc::downgrade(&b)
                 );
                 *s.borrow_mut().get_parent_joint() = parent;
@@ -343,7 +599,7 @@ pub fn bst_pop<T: Ord, U: TreeBalance>(tree: &mut Tree<T, U>) -> Option<(T, U)>
         }
     };
 
-    let popped = std::mem::replace(tree, successor);
+    let popped = core::mem::replace(tree, successor);
     Some(
         Rc::try_unwrap(popped.into_inner().unwrap()).map_err(|_| ()) // map_err() call to satisfy the Debug trait bound
         .unwrap().into_inner().pop()
@@ -358,10 +614,12 @@ pub fn bst_pop<T: Ord, U: TreeBalance>(tree: &mut Tree<T, U>) -> Option<(T, U)>
 /// reference to where the new root node should come from (i.e. a Right rotation brings up the Left child)
 /// This is to stay consistent with the [NodeInspector.rotate] function's definition
 /// 
+/// Both the old parent and the new root of the subtree are updated, however the ancestors of the subtree are not
+/// 
 /// # Panics
 /// 
 /// This function panics if the child node in the given direction is empty
-pub fn bst_rotate<T: Ord, U: TreeBalance>(p: TreeBranch<T, U>, direction: TreePath) -> TreeBranch<T, U> {
+pub fn bst_rotate<T: Ord, U: TreeBalance, A: Augment<T>>(p: TreeBranch<T, U, A>, direction: TreePath) -> TreeBranch<T, U, A> {
     // Get the grandparent
     let grandparent = {
         p.borrow().get_parent()
@@ -386,36 +644,1233 @@ pub fn bst_rotate<T: Ord, U: TreeBalance>(p: TreeBranch<T, U>, direction: TreePa
     }
 
     // Connect the old child and old parent together
-    { *x.borrow_mut().get_joint(direction.reflect()) = Tree::new_with(Rc::clone(&p)); }
+    { *x.borrow_mut().get_joint(direction.reflect()) = Subtree::new_with(Rc::clone(&p)); }
     { *p.borrow_mut().get_parent_joint() = Rc::downgrade(&x); }
+    { x.borrow_mut().update(); }
 
     // Connect the old child and grandparent together
     if let Some(r) = grandparent {
         let direction = {
             r.borrow().find_placement(&x.borrow())
         };
-        { *r.borrow_mut().get_joint(direction) = Tree::new_with(Rc::clone(&x)) };
-        { *x.borrow_mut().get_parent_joint() = Rc::downgrade(&r) };
+        *r.borrow_mut().get_joint(direction) = Subtree::new_with(Rc::clone(&x));
+        *x.borrow_mut().get_parent_joint() = Rc::downgrade(&r);
     }
 
     x
 }
 
+/// Checks the invariants of a binary tree with the given root
+/// 
+/// Checks that the keys are ordered, that each node's parent reference points to its parent
+/// and that the cached heights, leaf counts and sizes are correct, then calls [TreeBalance::verify] on each node.
+/// Returns the number of keys and the number of tombstones in the tree.
+pub fn bst_validate<T: Ord, U: TreeBalance, A: Augment<T>>(root: &Subtree<T, U, A>) -> Result<(usize, usize), ValidationError> {
+    let root = match root.branch() {
+        Some(root) => Rc::clone(root),
+        None => return Ok((0, 0))
+    };
+    if root.borrow().get_parent().is_some() {
+        return Err(ValidationError::BrokenParent)
+    }
+
+    // Visit the nodes in pre-order, keeping the index of each node's parent and
+    // of the closest ancestors whose keys bound the node's key from below and above
+    let mut visited: Vec<(TreeBranch<T, U, A>, Option<usize>)> = Vec::new();
+    let mut stack = Vec::new();
+    stack.push((root, None, None, None));
+    while let Some((node, parent, lower, upper)) = stack.pop() {
+        let index = visited.len();
+        {
+            let n = node.borrow();
+            let key = |i: usize| visited[i].0.borrow();
+            let above = lower.is_none_or(|i| n.get_key() > key(i).get_key());
+            let below = upper.is_none_or(|i| n.get_key() < key(i).get_key());
+            if !above || !below {
+                return Err(ValidationError::Unordered)
+            }
+            for path in [Left, Right] {
+                if let Some(child) = n.get_child(path) {
+                    if !child.borrow().get_parent().is_some_and(|p| Rc::ptr_eq(&p, &node)) {
+                        return Err(ValidationError::BrokenParent)
+                    }
+                    let (lower, upper) = match path {
+                        Left => (lower, Some(index)),
+                        Right => (Some(index), upper)
+                    };
+                    stack.push((Rc::clone(child), Some(index), lower, upper));
+                }
+            }
+        }
+        visited.push((node, parent));
+    }
+
+    // Children are visited after their parents, so work backwards to find the heights, leaves and sizes
+    let mut heights = alloc::vec![1; visited.len()];
+    let mut leaves = alloc::vec![0; visited.len()];
+    let mut sizes = alloc::vec![1; visited.len()];
+    let (mut keys, mut tombstones) = (0, 0);
+    for (i, (node, parent)) in visited.iter().enumerate().rev() {
+        let n = node.borrow();
+        leaves[i] = max(leaves[i], 1);
+        if n.get_cache().height().is_some_and(|cached| cached != heights[i]) {
+            return Err(ValidationError::StaleHeight)
+        }
+        if n.get_cache().leaves().is_some_and(|cached| cached != leaves[i]) {
+            return Err(ValidationError::StaleLeaves)
+        }
+        if n.get_cache().size().is_some_and(|cached| cached != sizes[i]) {
+            return Err(ValidationError::StaleSize)
+        }
+        if n.is_tombstone() {
+            tombstones += 1;
+        } else {
+            keys += 1;
+        }
+        if let Some(p) = *parent {
+            heights[p] = max(heights[p], heights[i] + 1);
+            leaves[p] += leaves[i];
+            sizes[p] += sizes[i];
+        }
+    }
+
+    // The structure is sound, let the balance check its own invariants
+    for (node, _) in visited {
+        U::verify(&NodeInspector::open(node))?;
+    }
+    Ok((keys, tombstones))
+}
+
 /// Performs a binary search on a tree with the given root
 /// 
-/// Returns true if the node is found
-pub fn bst_search<T: Ord, U: TreeBalance>(root: &Tree<T, U>, key: &T) -> bool {
+/// Returns the node holding the key if it is found
+pub fn bst_search<T: Ord, U: TreeBalance, A: Augment<T>>(root: &Subtree<T, U, A>, key: &T) -> Option<TreeBranch<T, U, A>> {
+    root.branch().and_then(|r| {
+        let (node, found) = bst_search_from(Rc::clone(r), key);
+        if found { Some(node) } else { None }
+    })
+}
+
+/// Performs a binary search starting at the given node
+/// 
+/// Returns the last node visited by the search (the node holding
+/// the key if it was found) and true if the key was found
+pub fn bst_search_from<T: Ord, U: TreeBalance, A: Augment<T>>(start: TreeBranch<T, U, A>, key: &T) -> (TreeBranch<T, U, A>, bool) {
 
     // Traverse the tree looking for the key
-    let mut next = root.branch().map(|b| Rc::clone(b));
+    let mut current = start;
+    loop {
+        let next = {
+            let node = current.borrow();
+            match node.search(key) {
+                None => return (Rc::clone(&current), true), // Key found
+                Some(path) => node.get_child(path).map(Rc::clone) // Key looking
+            }
+        };
+
+        // Hit the end of the tree, therefore it was not found
+        match next {
+            Some(n) => current = n,
+            None => return (current, false)
+        }
+    }
+}
+
+/// Notifies the balance of a tree that the given node was accessed
+/// 
+/// Calls [TreeBalance::on_access] starting at the accessed node and follows the positions
+/// it returns, allowing the balance to restructure the tree (e.g. move the node up).
+/// Returns the new root of the tree and any error the balance failed with.
+/// The rotations performed are added to `rotations`.  Nothing is done if [TreeBalance::ON_ACCESS] is not set.
+/// 
+/// # Panics
+/// 
+/// This function panics if the accessed node is not a node of the tree with the given root
+pub fn bst_access<T: Ord, U: TreeBalance, A: Augment<T>>(root: Subtree<T, U, A>, x: TreeBranch<T, U, A>, rotations: &mut usize) -> (Subtree<T, U, A>, Result<(), InspectError>) {
+    if !U::ON_ACCESS {
+        return (root, Ok(()))
+    }
+    // DO NOT LET THIS VARIABLE DIE UNTIL THE NEW ROOT IS FOUND OTHERWISE THE TREE WILL BEGIN TO DEALLOCATE
+    let mut root_keep_alive = root;
+    let mut r = Rc::clone(&x);
+    let mut path = Vec::new();
+    let mut checked = Ok(());
+    loop {
+        let (current, next_pos, rotated) = U::on_access(NodeInspector::open(r), &path).into_inner();
+        *rotations += rotated;
+        r = current;
+        r.borrow_mut().update();
+        if r.borrow().get_parent().is_none() {
+            // Keep the new root alive in case we move down the tree
+            root_keep_alive = Subtree::new_with(Rc::clone(&r));
+        }
+        let next = match next_pos.and_then(|offset| bst_offset(&r, offset)) {
+            Ok(next) => next,
+            Err(error) => {
+                checked = Err(error);
+                break
+            }
+        };
+
+        // Continue if the accessed node can still be reached from the next node
+        if let Some((n, next_path)) = next.and_then(|(n, _)| access_route(&n, &x).map(|p| (n, p))) {
+            path = next_path;
+            r = n;
+        } else {
+            break;
+        }
+    }
+
+    // Update the rest of the tree on the way back to the root
+    let root = bst_update_to_root(r);
+    drop(root_keep_alive);
+    (Subtree::new_with(root), checked)
+}
+
+/// Travels up to the root from a node, updating each node along the way
+/// 
+/// Returns the root
+fn bst_update_to_root<T: Ord, U: TreeBalance, A: Augment<T>>(node: TreeBranch<T, U, A>) -> TreeBranch<T, U, A> {
+    let mut r = node;
+    let mut next = { r.borrow().get_parent() };
     while let Some(n) = next {
-        let node = n.borrow();
-        next = match node.search(key) {
-            None => return true, // Key found
-            Some(path) => node.get_child(path).map(|b| Rc::clone(b)) // Key looking
+        r = n;
+        let mut rnode = r.borrow_mut();
+        rnode.update();
+        next = rnode.get_parent();
+    }
+    r
+}
+
+/// Marks (or unmarks) the key of the given node as lazily deleted
+/// 
+/// The aggregates of the node and its ancestors are updated to leave out (or include) the key
+pub fn bst_set_tombstone<T: Ord, U: TreeBalance, A: Augment<T>>(node: &TreeBranch<T, U, A>, tombstone: bool) {
+    node.borrow_mut().set_tombstone(tombstone);
+    bst_update_to_root(Rc::clone(node));
+}
+
+/// Finds the paths from the given node down to the accessed node `x`
+/// 
+/// Returns None if `x` cannot be reached from the node
+fn access_route<T: Ord, U: TreeBalance, A: Augment<T>>(node: &TreeBranch<T, U, A>, x: &TreeBranch<T, U, A>) -> Option<Vec<TreePath>> {
+    let xnode = x.borrow();
+    let mut route = Vec::new();
+    let mut current = Rc::clone(node);
+    loop {
+        let next = {
+            let node = current.borrow();
+            match node.search(xnode.get_key()) {
+                None => return Some(route),
+                Some(path) => {
+                    route.push(path);
+                    node.get_child(path).map(Rc::clone)
+                }
+            }
+        };
+        current = next?;
+    }
+}
+
+/// Finds the node to begin a search for the given key from using a finger
+/// 
+/// Walks up from the finger node through its ancestors until it reaches the
+/// lowest ancestor whose subtree must contain the key's position, so that a search
+/// started from the returned node costs O(log d) where d is the distance (in rank)
+/// between the key and the finger.
+/// If the finger no longer points to a node the search begins at the root.
+/// 
+/// The finger must point to a node of the tree with the given root,
+/// otherwise the returned node will not belong to the tree.
+pub fn bst_finger<T: Ord, U: TreeBalance, A: Augment<T>>(root: &Subtree<T, U, A>, finger: &TreeTrunk<T, U, A>, key: &T) -> Option<TreeBranch<T, U, A>> {
+    let mut current = match finger.upgrade() {
+        Some(node) => node,
+        None => return root.branch().map(Rc::clone)
+    };
+
+    loop {
+        let next = {
+            let node = current.borrow();
+            // Stop if we are on the key or the node is the root
+            let path = match node.search(key) {
+                Some(path) => path,
+                None => break
+            };
+            let parent = match node.get_parent() {
+                Some(parent) => parent,
+                None => break
+            };
+            let placement = parent.borrow().find_placement(&node);
+
+            // If the parent lies in the direction of the key we
+            // need to check if the key falls between the node and the parent
+            if placement != path {
+                let pnode = parent.borrow();
+                if pnode.search(key) == Some(placement) {
+                    break;
+                }
+            }
+            parent
         };
+        current = next;
     }
 
-    // Hit the end of the tree, therefore it was not found
-    false
-}
\ No newline at end of file
+    Some(current)
+}
+
+/// Detaches every node of the tree with the given root
+/// 
+/// Returns the nodes of the tree in order, each with no parent or children
+pub fn bst_flatten<T: Ord, U: TreeBalance, A: Augment<T>>(root: Subtree<T, U, A>) -> Vec<TreeBranch<T, U, A>> {
+    let mut nodes = Vec::new();
+    let mut stack = Vec::new();
+    let mut next = root.into_inner();
+    loop {
+        // Travel down the left side of the subtree detaching as we go
+        while let Some(n) = next {
+            next = n.borrow_mut().prune(Left).into_inner();
+            stack.push(n);
+        }
+
+        // Visit the node and continue with its right subtree
+        match stack.pop() {
+            Some(n) => {
+                next = n.borrow_mut().prune(Right).into_inner();
+                nodes.push(n);
+            },
+            None => break
+        }
+    }
+    nodes
+}
+
+/// Links the given detached nodes into a height balanced tree
+/// 
+/// The nodes must be in order and have no parent or children.
+/// Each node is given a new balance from [TreeBalance::new_rebuilt]
+/// and has its cached information updated.
+/// Returns the root of the new tree.
+/// 
+/// # Panics
+/// 
+/// This function panics if [TreeBalance::new_rebuilt] returns None
+pub fn bst_build<T: Ord, U: TreeBalance, A: Augment<T>>(nodes: Vec<TreeBranch<T, U, A>>) -> Subtree<T, U, A> {
+    let (root, height) = bst_build_range(&nodes, true);
+    if let Some(r) = root.branch() {
+        *r.borrow_mut().get_balance_mut() = U::new_rebuilt(height, None).unwrap();
+    }
+    root
+}
+
+/// Rebuilds the subtree rooted at the given node into a height balanced shape
+/// 
+/// The nodes of the subtree are reused, keeping their balances, and the new
+/// root of the subtree takes the old root's place under its parent.
+/// The cached information of the subtree is updated but its ancestors are not.
+/// Returns the new root of the subtree.
+pub fn bst_rebuild<T: Ord, U: TreeBalance, A: Augment<T>>(x: TreeBranch<T, U, A>) -> TreeBranch<T, U, A> {
+    // Detach the subtree from its parent
+    let placement = {
+        let xnode = x.borrow();
+        xnode.get_parent().map(|p| {
+            let path = p.borrow().find_placement(&xnode);
+            (p, path)
+        })
+    };
+    let subtree = match &placement {
+        Some((p, path)) => p.borrow_mut().prune(*path),
+        None => Subtree::new_with(x)
+    };
+
+    // Rebuild and reattach
+    let nodes = bst_flatten(subtree);
+    let (root, _) = bst_build_range(&nodes, false);
+    let top = root.into_inner().unwrap();
+    if let Some((p, path)) = placement {
+        *top.borrow_mut().get_parent_joint() = Rc::downgrade(&p);
+        *p.borrow_mut().get_joint(path) = Subtree::new_with(Rc::clone(&top));
+    }
+    top
+}
+
+/// Recursively links the given slice of nodes returning the root of the subtree and its height
+/// 
+/// If `fresh` is set each child is given a new balance from [TreeBalance::new_rebuilt], the root's
+/// balance is left for the caller to set since it depends on the parent's height
+fn bst_build_range<T: Ord, U: TreeBalance, A: Augment<T>>(nodes: &[TreeBranch<T, U, A>], fresh: bool) -> (Subtree<T, U, A>, usize) {
+    if nodes.is_empty() {
+        return (Subtree::new(), 0)
+    }
+
+    let mid = nodes.len() / 2;
+    let x = &nodes[mid];
+    let (left, lheight) = bst_build_range(&nodes[..mid], fresh);
+    let (right, rheight) = bst_build_range(&nodes[mid + 1..], fresh);
+    let height = max(lheight, rheight) + 1;
+
+    // Attach the children to the node
+    let mut xnode = x.borrow_mut();
+    for (path, child, cheight) in [(Left, left, lheight), (Right, right, rheight)] {
+        if let Some(c) = child.branch() {
+            let mut cnode = c.borrow_mut();
+            *cnode.get_parent_joint() = Rc::downgrade(x);
+            if fresh {
+                *cnode.get_balance_mut() = U::new_rebuilt(cheight, Some(height)).unwrap();
+            }
+        }
+        *xnode.get_joint(path) = child;
+    }
+    xnode.update();
+
+    (Subtree::new_with(Rc::clone(x)), height)
+}
+
+/// Outcomes of a batch of operations, tagged with the index of their operation
+type BatchOutcomes<T> = Vec<(usize, OpOutcome<T>)>;
+
+/// Applies a sorted batch of operations to the tree with the given root by merging
+/// the batch with the tree's nodes and rebuilding the tree
+/// 
+/// The batch must be sorted by key.  The nodes of the tree are reused (dropping lazily deleted nodes)
+/// and the resulting tree is height balanced (see [bst_build]), or assembled by appending the nodes
+/// in order if the balance cannot be rebuilt (see [bst_assemble]).
+/// Returns the new root and the outcome of each operation, tagged with the
+/// same index as the operation it belongs to.
+pub fn bst_merge_batch<T: Ord, U: TreeBalance, A: Augment<T>>(root: Subtree<T, U, A>, batch: Vec<(usize, Op<T>)>, rng: &mut TreeRng) -> (Subtree<T, U, A>, BatchOutcomes<T>) {
+    let mut nodes = bst_flatten(root).into_iter().peekable();
+    let mut merged = Vec::with_capacity(nodes.len() + batch.len());
+    let mut outcomes = Vec::with_capacity(batch.len());
+    // Node holding the key of the current operation (if it is in the tree)
+    let mut current: Option<TreeBranch<T, U, A>> = None;
+
+    for (i, op) in batch {
+        // Move on from the previous key if the operation is on a new key
+        if current.as_ref().is_some_and(|c| c.borrow().search(op.key()).is_some()) {
+            merged.extend(current.take());
+        }
+
+        // Keep every node before the key, stopping at the node with the key if there is one.
+        // Lazily deleted nodes are dropped along the way
+        if current.is_none() {
+            while let Some(path) = nodes.peek().map(|n| n.borrow().search(op.key())) {
+                match path {
+                    Some(Right) => merged.extend(nodes.next().filter(|n| !n.borrow().is_tombstone())),
+                    Some(Left) => break,
+                    None => {
+                        current = nodes.next().filter(|n| !n.borrow().is_tombstone());
+                        break
+                    }
+                }
+            }
+        }
+
+        let outcome = match op {
+            Op::Insert(key) => {
+                if current.is_some() {
+                    OpOutcome::Exists
+                } else {
+                    current = Some(Rc::new(RefCell::new(TreeNode::new_with(key, rng))));
+                    OpOutcome::Inserted
+                }
+            },
+            Op::Delete(_) => match current.take() {
+                Some(n) => {
+                    let (key, _) = Rc::try_unwrap(n).map_err(|_| ()).unwrap().into_inner().pop();
+                    OpOutcome::Deleted(key)
+                },
+                None => OpOutcome::Missing
+            }
+        };
+        outcomes.push((i, outcome));
+    }
+
+    merged.extend(current);
+    merged.extend(nodes.filter(|n| !n.borrow().is_tombstone()));
+    let root = if U::new_rebuilt(1, None).is_some() {
+        bst_build(merged)
+    } else {
+        bst_assemble(merged, rng)
+    };
+    (root, outcomes)
+}
+
+/// Result of [bst_apply_batch], holding the new root, the outcome of each operation
+/// and any error the balance failed with
+type BatchResult<T, U, A> = (Subtree<T, U, A>, BatchOutcomes<T>, Result<(), InspectError>);
+
+/// Applies a sorted batch of operations to the tree with the given root in a single descent
+/// 
+/// The batch must be sorted by key.  Starting at the root the batch is split around the key of each node,
+/// so only the paths down to the keys of the batch are visited.  Where the batch falls on both sides of a node
+/// the smaller side is applied to its subtree first, and the descent carries on down the other side.
+/// Each key is settled where it is found, or linked in as a leaf where it falls off the bottom of the tree,
+/// and deleted nodes are removed as in [bst_delete_from].
+/// 
+/// The tree is rebalanced after each change (see [TreeBalance::rebalance_insert] and [TreeBalance::rebalance_delete]),
+/// but the nodes the descent passed through are only updated once it moves back up past them.  Only if the balance
+/// restructures the tree (rotating or rebuilding a subtree) above where the descent is does it move back up,
+/// to the lowest node left in place.
+/// 
+/// If `lazy` is given deleted keys are marked as tombstones instead, returning a clone of the key made by the
+/// function, and inserting a lazily deleted key revives its node.  The change in the number of tombstones is
+/// counted in `tombstones` and the rotations performed are added to `rotations`.
+/// Returns the new root, the outcome of each operation (tagged with the same index as the operation it
+/// belongs to) and the first error the balance failed with, in which case the rest of that rebalancing is skipped.
+pub fn bst_apply_batch<T: Ord, U: TreeBalance, A: Augment<T>>(root: Subtree<T, U, A>, batch: Vec<(usize, Op<T>)>, lazy: Option<fn(&T) -> T>, rng: &mut TreeRng, tombstones: &mut usize, rotations: &mut usize) -> BatchResult<T, U, A> {
+    let mut descent = BatchDescent {
+        root,
+        outcomes: Vec::with_capacity(batch.len()),
+        ops: batch.into_iter().map(Some).collect(),
+        checked: Ok(()),
+        moved: Vec::new(),
+        cursors: Vec::new(),
+        ranges: Vec::new(),
+        lazy,
+        rng,
+        tombstones,
+        rotations
+    };
+    let whole = Frame { lo: None, hi: None };
+    descent.apply(None, whole, 0..descent.ops.len());
+    (descent.root, descent.outcomes, descent.checked)
+}
+
+/// Key of an operation of [bst_apply_batch] that has not been applied yet
+fn batch_key<T>(op: &Option<(usize, Op<T>)>) -> &T {
+    op.as_ref().unwrap().1.key()
+}
+
+/// Subtree worked on by [BatchDescent::apply], given by the nodes bounding its keys
+struct Frame<'f, T: Ord, U: TreeBalance, A: Augment<T>> {
+    lo: Option<&'f TreeBranch<T, U, A>>,
+    hi: Option<&'f TreeBranch<T, U, A>>
+}
+
+impl <T: Ord, U: TreeBalance, A: Augment<T>> Clone for Frame<'_, T, U, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl <T: Ord, U: TreeBalance, A: Augment<T>> Copy for Frame<'_, T, U, A> {}
+
+impl <T: Ord, U: TreeBalance, A: Augment<T>> Frame<'_, T, U, A> {
+    /// Checks if the node is in the subtree, its key being between the bounding keys
+    fn holds(&self, node: &TreeBranch<T, U, A>) -> bool {
+        let node = node.borrow();
+        self.lo.is_none_or(|lo| lo.borrow().get_key() < node.get_key()) && self.hi.is_none_or(|hi| node.get_key() < hi.borrow().get_key())
+    }
+}
+
+/// Position of [BatchDescent::apply] in the tree, the placement below which the descent continues
+/// (the root if None) and the nodes bounding the keys below it
+type Cursor<T, U, A> = (Option<(TreeBranch<T, U, A>, TreePath)>, Option<TreeBranch<T, U, A>>, Option<TreeBranch<T, U, A>>);
+
+/// State of [bst_apply_batch] while applying the batch
+struct BatchDescent<'a, T: Ord, U: TreeBalance, A: Augment<T>> {
+    /// Root of the tree, kept alive while the tree is restructured
+    root: Subtree<T, U, A>,
+    /// Operations sorted by key, taken once they are applied
+    ops: Vec<Option<(usize, Op<T>)>>,
+    outcomes: BatchOutcomes<T>,
+    /// The first error the balance failed with
+    checked: Result<(), InspectError>,
+    /// Parents of the subtrees the balance restructured since the descent last moved back up
+    moved: Vec<Option<TreeBranch<T, U, A>>>,
+    /// Where the descent has been in each subtree being worked on.  The nodes the descent moved down from
+    /// are updated once it moves back up past them
+    cursors: Vec<Cursor<T, U, A>>,
+    /// Ranges of operations left to apply to each subtree being worked on, applied one at a time
+    ranges: Vec<Range<usize>>,
+    lazy: Option<fn(&T) -> T>,
+    rng: &'a mut TreeRng,
+    tombstones: &'a mut usize,
+    rotations: &'a mut usize
+}
+
+impl <T: Ord, U: TreeBalance, A: Augment<T>> BatchDescent<'_, T, U, A> {
+    /// Applies the given range of operations to the subtree at the placement (the whole tree if None)
+    /// 
+    /// If the balance restructures the tree above the subtree the descent stops, leaving the parents of the
+    /// subtrees restructured in `moved` and the operations left in `ranges`.
+    fn apply(&mut self, placement: Option<(TreeBranch<T, U, A>, TreePath)>, frame: Frame<'_, T, U, A>, range: Range<usize>) {
+        let base = self.cursors.len();
+        let first = self.ranges.len();
+        self.cursors.push((placement, frame.lo.map(Rc::clone), frame.hi.map(Rc::clone)));
+        self.ranges.push(range);
+        while self.ranges.len() > first {
+            let mut range = self.ranges.pop().unwrap();
+            if range.is_empty() {
+                continue
+            }
+            // Move back up until the keys of the range are below the descent
+            loop {
+                let (_, lo, hi) = self.cursors.last().unwrap();
+                let (first, last) = (batch_key(&self.ops[range.start]), batch_key(&self.ops[range.end - 1]));
+                if lo.as_ref().is_none_or(|lo| lo.borrow().get_key() < first) && hi.as_ref().is_none_or(|hi| last < hi.borrow().get_key()) {
+                    break
+                }
+                let (placement, _, _) = self.cursors.pop().unwrap();
+                placement.unwrap().0.borrow_mut().update();
+            }
+
+            while !range.is_empty() {
+                let (cursor, lo, hi) = self.cursors.last().unwrap();
+                let x = match cursor {
+                    Some((p, path)) => p.borrow().get_child(*path).map(Rc::clone),
+                    None => self.root.branch().map(Rc::clone)
+                };
+                let x = match x {
+                    Some(x) => x,
+                    None => {
+                        // Link in the first key, the keys after it are applied below it
+                        let cursor = cursor.clone();
+                        let ops = &self.ops[range.clone()];
+                        let first = batch_key(&ops[0]);
+                        let equal = ops.partition_point(|op| batch_key(op) == first);
+                        range.start += equal;
+                        if let (_, Some(key)) = self.settle(None, range.start - equal..range.start) {
+                            let origin = self.link(cursor.as_ref(), key);
+                            if !self.resume(base, frame, Some(origin)) {
+                                self.leave(base, range);
+                                return
+                            }
+                        }
+                        continue
+                    }
+                };
+
+                // Split the operations around the key of the node
+                let (less, equal) = {
+                    let xnode = x.borrow();
+                    let ops = &self.ops[range.clone()];
+                    let less = ops.partition_point(|op| batch_key(op) < xnode.get_key());
+                    (less, ops[less..].partition_point(|op| batch_key(op) == xnode.get_key()))
+                };
+                let greater = range.len() - less - equal;
+
+                if equal > 0 && (less == 0 || greater == 0) {
+                    // Settle the key of the node, the operations left are all on one side of it
+                    let ops = if less == 0 {
+                        range.start += equal;
+                        range.start - equal..range.start
+                    } else {
+                        range.end -= equal;
+                        range.end..range.end + equal
+                    };
+                    match self.settle(Some(&x), ops) {
+                        (Some(i), Some(key)) => {
+                            let key = x.borrow_mut().replace_key(key);
+                            self.outcomes.push((i, OpOutcome::Deleted(key)));
+                            x.borrow_mut().update();
+                        },
+                        (Some(i), None) => {
+                            let origin = self.remove(x, i);
+                            if !self.resume(base, frame, origin) {
+                                self.leave(base, range);
+                                return
+                            }
+                        },
+                        _ => if self.lazy.is_some() {
+                            x.borrow_mut().update();
+                        }
+                    }
+                } else if less > 0 && greater > 0 {
+                    // Apply the smaller side first, the operations it leaves are applied once the ones left here are
+                    let (lo, hi) = (lo.clone(), hi.clone());
+                    if less <= greater {
+                        range.start += less;
+                        let side = Frame { lo: lo.as_ref(), hi: Some(&x) };
+                        self.apply(Some((Rc::clone(&x), Left)), side, range.start - less..range.start);
+                    } else {
+                        range.end -= greater;
+                        let side = Frame { lo: Some(&x), hi: hi.as_ref() };
+                        self.apply(Some((Rc::clone(&x), Right)), side, range.end..range.end + greater);
+                    }
+                    x.borrow_mut().update();
+                    if !self.moved.is_empty() && !self.resume(base, frame, Some(x)) {
+                        self.leave(base, range);
+                        return
+                    }
+                } else {
+                    // Every operation is on one side of the node, move down to it
+                    let step = if less > 0 {
+                        (Some((Rc::clone(&x), Left)), lo.clone(), Some(x))
+                    } else {
+                        (Some((Rc::clone(&x), Right)), Some(x), hi.clone())
+                    };
+                    self.cursors.push(step);
+                }
+            }
+        }
+        self.leave(base, 0..0);
+    }
+
+    /// Moves the descent of the subtree starting at `base` back up above the subtrees the balance restructured
+    /// (whose parents are in `moved`), then updates the nodes from `origin` up to where the descent continues
+    /// 
+    /// Returns false if the balance restructured the tree above the subtree instead, leaving `moved` to be
+    /// looked at further up.
+    fn resume(&mut self, base: usize, frame: Frame<'_, T, U, A>, origin: Option<TreeBranch<T, U, A>>) -> bool {
+        let placement = self.cursors[base].0.as_ref().map(|(p, _)| p);
+        let above = self.moved.iter().any(|parent| match (parent, placement) {
+            (Some(parent), Some(p)) => !Rc::ptr_eq(parent, p) && !frame.holds(parent),
+            (parent, p) => parent.is_none() && p.is_some()
+        });
+        if above {
+            return false
+        }
+        for parent in self.moved.drain(..) {
+            // Only nodes on the path of the descent can hold the rest of it
+            let step = self.cursors[base..].iter().rposition(|(placement, _, _)| match (placement, &parent) {
+                (Some((p, _)), Some(parent)) => Rc::ptr_eq(p, parent),
+                (placement, parent) => placement.is_none() && parent.is_none()
+            });
+            if let Some(step) = step {
+                self.cursors.truncate(base + step + 1);
+            }
+        }
+
+        // The rebalancing updated the nodes it went through
+        let stop = self.cursors.last().unwrap().0.as_ref().map(|(p, _)| Rc::clone(p));
+        let mut next = origin;
+        while let Some(n) = next {
+            if stop.as_ref().is_some_and(|stop| Rc::ptr_eq(stop, &n)) {
+                break
+            }
+            n.borrow_mut().update();
+            next = n.borrow().get_parent();
+        }
+        true
+    }
+
+    /// Leaves the subtree whose descent starts at `base`, keeping the range of operations left for later
+    /// 
+    /// The nodes the descent moved down from are updated, deepest first.
+    fn leave(&mut self, base: usize, range: Range<usize>) {
+        if !range.is_empty() {
+            self.ranges.push(range);
+        }
+        for (placement, _, _) in self.cursors.drain(base + 1..).rev() {
+            placement.unwrap().0.borrow_mut().update();
+        }
+        self.cursors.pop();
+    }
+
+    /// Applies the given range of operations, which are all on the same key, to the node holding the key (if there is one)
+    /// 
+    /// Keys deleted lazily or revived are marked on the node.  Returns the index of the operation that removed
+    /// the node's key (if it is removed) and the key left to add to the tree (if there is one).
+    fn settle(&mut self, node: Option<&TreeBranch<T, U, A>>, ops: Range<usize>) -> (Option<usize>, Option<T>) {
+        // Whether the node's key is still in the tree
+        let mut live = node.is_some_and(|n| !n.borrow().is_tombstone());
+        let mut removed = None;
+        let mut added = None;
+        for (i, op) in self.ops[ops].iter_mut().map(|op| op.take().unwrap()) {
+            let outcome = match op {
+                Op::Insert(key) => {
+                    if live || added.is_some() {
+                        OpOutcome::Exists
+                    } else if let (Some(n), None) = (node, removed) {
+                        n.borrow_mut().set_tombstone(false);
+                        *self.tombstones -= 1;
+                        live = true;
+                        OpOutcome::Inserted
+                    } else {
+                        added = Some(key);
+                        OpOutcome::Inserted
+                    }
+                },
+                Op::Delete(_) => match (added.take(), node) {
+                    (Some(key), _) => OpOutcome::Deleted(key),
+                    (None, Some(n)) if live => {
+                        live = false;
+                        match self.lazy {
+                            Some(clone) => {
+                                n.borrow_mut().set_tombstone(true);
+                                *self.tombstones += 1;
+                                OpOutcome::Deleted(clone(n.borrow().get_key()))
+                            },
+                            None => {
+                                // The outcome is known once the key is taken out of the node
+                                removed = Some(i);
+                                continue
+                            }
+                        }
+                    },
+                    _ => OpOutcome::Missing
+                }
+            };
+            self.outcomes.push((i, outcome));
+        }
+        (removed, added)
+    }
+
+    /// Links the key into the tree as a leaf at the placement (as the root if None) and rebalances,
+    /// returning the parent of the new node (the new node if it is the root)
+    fn link(&mut self, placement: Option<&(TreeBranch<T, U, A>, TreePath)>, key: T) -> TreeBranch<T, U, A> {
+        let (p, path) = match placement {
+            Some(placement) => placement,
+            None => {
+                let x = Rc::new(RefCell::new(TreeNode::new_with(key, self.rng)));
+                self.root = Subtree::new_with(Rc::clone(&x));
+                return x
+            }
+        };
+        let x = Rc::new(RefCell::new(TreeNode::new_with_parent(key, Rc::downgrade(p), self.rng)));
+        {
+            let mut pnode = p.borrow_mut();
+            *pnode.get_joint(*path) = Subtree::new_with(Rc::clone(&x));
+            pnode.update();
+        }
+        // Rebalancing starts at the grandparent, or the parent if it is the root (see bst_rebalance_inserted)
+        let start = p.borrow().get_parent().unwrap_or_else(|| Rc::clone(p));
+        self.fix(Fixup::Insert(start, Rc::downgrade(&x)));
+        Rc::clone(p)
+    }
+
+    /// Removes the node from the tree, its key having been deleted by the operation with the given index, and rebalances,
+    /// returning the parent of the removed node (None if the tree is empty)
+    fn remove(&mut self, x: TreeBranch<T, U, A>, i: usize) -> Option<TreeBranch<T, U, A>> {
+        // Let the balance work on the subtree first (see bst_delete_from)
+        while let Some(top) = bst_successor_descend(&x, self.rotations) {
+            top.borrow_mut().update();
+            self.keep_root(&top);
+        }
+        let parent = x.borrow().get_parent();
+        let placement = parent.map(|p| {
+            let path = p.borrow().find_placement(&x.borrow());
+            (p, path)
+        });
+        drop(x);
+        let (key, fixup) = bst_remove(&mut self.root, placement, self.rotations);
+        self.outcomes.push((i, OpOutcome::Deleted(key)));
+        let (p, path, balance) = fixup?;
+        self.fix(Fixup::Delete(Rc::clone(&p), path, balance));
+        Some(p)
+    }
+
+    /// Rebalances the tree after a change, keeping the parents of the subtrees the balance restructured in `moved`
+    /// 
+    /// The nodes rebalanced are updated, the nodes between them are updated as the rebalancing moves up.
+    fn fix(&mut self, mut fixup: Fixup<T, U, A>) {
+        loop {
+            let (anchor, position) = match &fixup {
+                Fixup::Insert(r, x) => match x.upgrade().and_then(|x| insert_route(r, &x)) {
+                    Some(path) => (Rc::clone(r), U::rebalance_insert(NodeInspector::open(Rc::clone(r)), path)),
+                    // The inserted node is no longer below the node
+                    None => break
+                },
+                Fixup::Delete(p, path, balance) => (Rc::clone(p), U::rebalance_delete(NodeInspector::open(Rc::clone(p)), *path, balance))
+            };
+            let rebuilt = position.rebuilt();
+            let (current, next_pos, rotated) = position.into_inner();
+            *self.rotations += rotated;
+            current.borrow_mut().update();
+            self.keep_root(&current);
+            if rotated > 0 || rebuilt || !Rc::ptr_eq(&current, &anchor) {
+                self.moved.push(current.borrow().get_parent());
+            }
+
+            // Move on to the next node to rebalance (stopping if the balance failed)
+            let next = match next_pos.and_then(|offset| bst_offset(&current, offset)) {
+                Ok(next) => next,
+                Err(error) => {
+                    if self.checked.is_ok() {
+                        self.checked = Err(error);
+                    }
+                    break
+                }
+            };
+            fixup = match (fixup, next) {
+                (Fixup::Insert(_, x), Some((n, _))) => Fixup::Insert(n, x),
+                (Fixup::Delete(_, _, balance), Some((n, Some(path)))) => Fixup::Delete(n, path, balance),
+                _ => break
+            };
+        }
+    }
+
+    /// Keeps the node alive as the root of the tree if it has no parent
+    fn keep_root(&mut self, node: &TreeBranch<T, U, A>) {
+        if node.borrow().get_parent().is_none() {
+            self.root = Subtree::new_with(Rc::clone(node));
+        }
+    }
+}
+
+/// Rebalancing to do after a change made by [bst_apply_batch]
+enum Fixup<T: Ord, U: TreeBalance, A: Augment<T>> {
+    /// Rebalance from the node after inserting the given node below it
+    Insert(TreeBranch<T, U, A>, TreeTrunk<T, U, A>),
+    /// Rebalance from the node after removing a node from the given path below it, with the balance of the removed node
+    Delete(TreeBranch<T, U, A>, TreePath, U)
+}
+
+/// Converts the tree with the given root to use a different balance without comparing keys
+/// 
+/// The keys are moved into new nodes (the nodes cannot be reused as their type changes) linked in the same
+/// shape as the old nodes, keeping lazily deleted nodes.  If the new balance can describe a tree from its shape
+/// (see [TreeBalance::new_rebuilt]) each node is given a balance from its height and its parent's height in a
+/// single post-order pass, then the tree is checked with [TreeBalance::verify].  Only if the shape is not valid
+/// for the new balance is the tree rebuilt into a height balanced tree (see [bst_build]).  Balances that cannot
+/// describe a tree from its shape have the nodes appended in order instead (see [bst_assemble]).
+/// Returns the new root and the number of lazily deleted nodes kept.
+pub fn bst_convert<T: Ord, U: TreeBalance, B: TreeBalance, A: Augment<T>>(root: Subtree<T, U, A>, rng: &mut TreeRng) -> (Subtree<T, B, A>, usize) {
+    let mut tombstones = 0;
+    if B::new_rebuilt(1, None).is_none() {
+        let nodes = bst_flatten(root).into_iter().map(|n| {
+            tombstones += usize::from(n.borrow().is_tombstone());
+            bst_move_node(n, |key| B::new(&mut BalanceContext::open((key, &mut *rng))))
+        }).collect::<Vec<_>>();
+        return (bst_assemble(nodes, rng), tombstones)
+    }
+
+    // Visit the nodes in post-order, moving each key into a new node once its children have been moved
+    let mut stack = alloc::vec![Convert::Visit(root.into_inner())];
+    let mut moved: Vec<(Subtree<T, B, A>, usize)> = Vec::new();
+    while let Some(step) = stack.pop() {
+        match step {
+            Convert::Visit(None) => moved.push((Subtree::new(), 0)),
+            Convert::Visit(Some(n)) => {
+                let (left, right) = {
+                    let mut node = n.borrow_mut();
+                    (node.prune(Left).into_inner(), node.prune(Right).into_inner())
+                };
+                stack.push(Convert::Link(n));
+                stack.push(Convert::Visit(right));
+                stack.push(Convert::Visit(left));
+            },
+            Convert::Link(n) => {
+                tombstones += usize::from(n.borrow().is_tombstone());
+                let (right, rheight) = moved.pop().unwrap();
+                let (left, lheight) = moved.pop().unwrap();
+                let height = max(lheight, rheight) + 1;
+                // The balance of the node depends on its parent's height, so it is set once the parent is linked
+                let x = bst_move_node(n, |_| B::new_rebuilt(height, None).unwrap());
+                {
+                    let mut xnode = x.borrow_mut();
+                    for (path, child, cheight) in [(Left, left, lheight), (Right, right, rheight)] {
+                        if let Some(c) = child.branch() {
+                            let mut cnode = c.borrow_mut();
+                            *cnode.get_parent_joint() = Rc::downgrade(&x);
+                            *cnode.get_balance_mut() = B::new_rebuilt(cheight, Some(height)).unwrap();
+                        }
+                        *xnode.get_joint(path) = child;
+                    }
+                    xnode.update();
+                }
+                moved.push((Subtree::new_with(x), height));
+            }
+        }
+    }
+
+    let (root, _) = moved.pop().unwrap();
+    if bst_validate(&root).is_ok() {
+        (root, tombstones)
+    } else {
+        (bst_build(bst_flatten(root)), tombstones)
+    }
+}
+
+/// Step of the post-order traversal done by [bst_convert]
+enum Convert<T: Ord, U: TreeBalance, A: Augment<T>> {
+    /// Move the subtree rooted by the node
+    Visit(Option<TreeBranch<T, U, A>>),
+    /// Move the node, linking it to its already moved children
+    Link(TreeBranch<T, U, A>)
+}
+
+/// Moves the key of a detached node into a new node with a balance made from the key
+/// 
+/// The new node is lazily deleted if the old node was
+fn bst_move_node<T: Ord, U: TreeBalance, B: TreeBalance, A: Augment<T>>(x: TreeBranch<T, U, A>, balance: impl FnOnce(&T) -> B) -> TreeBranch<T, B, A> {
+    let tombstone = x.borrow().is_tombstone();
+    let (key, _) = Rc::try_unwrap(x).map_err(|_| ()).unwrap().into_inner().pop();
+    let balance = balance(&key);
+    let mut node = TreeNode::new_with_balance(key, balance);
+    node.set_tombstone(tombstone);
+    Rc::new(RefCell::new(node))
+}
+
+/// Links the given detached nodes into a tree by appending them one after another without comparing keys
+/// 
+/// The nodes must be in order and have no parent or children.  Each node is given a new balance
+/// and the tree is rebalanced after each node as if it was inserted, which takes amortized O(1) time
+/// for balances that stop rebalancing an insert once nothing changes (e.g. treaps, AA trees and
+/// left leaning red black trees) since the last node is always the parent of the next.
+/// Returns the root of the new tree.
+/// 
+/// # Panics
+/// 
+/// This function panics if the balance fails while rebalancing
+pub fn bst_assemble<T: Ord, U: TreeBalance, A: Augment<T>>(nodes: Vec<TreeBranch<T, U, A>>, rng: &mut TreeRng) -> Subtree<T, U, A> {
+    let mut root = Subtree::new();
+    let mut last: Option<TreeBranch<T, U, A>> = None;
+    for x in nodes {
+        root = bst_append(root, last.as_ref(), Rc::clone(&x), rng);
+        last = Some(x);
+    }
+    root
+}
+
+/// Removes every lazily deleted node from the tree with the given root
+/// 
+/// The remaining nodes are reused, being rebuilt into a height balanced tree (see [bst_build])
+/// if the balance supports it, otherwise appended in order (see [bst_assemble]).
+/// Returns the new root.
+pub fn bst_compact<T: Ord, U: TreeBalance, A: Augment<T>>(root: Subtree<T, U, A>, rng: &mut TreeRng) -> Subtree<T, U, A> {
+    let nodes = bst_flatten(root).into_iter().filter(|n| !n.borrow().is_tombstone()).collect();
+    if U::new_rebuilt(1, None).is_some() {
+        bst_build(nodes)
+    } else {
+        bst_assemble(nodes, rng)
+    }
+}
+
+/// Rebuilds the tree with the given root into a minimal height tree in place (Day–Stout–Warren)
+/// 
+/// The tree is first unfolded into a vine (a tree where no node has a left child) by rotating left
+/// children up, then folded back together by rotating every other node of the vine down in rounds.
+/// The resulting tree is complete, every level other than the last is full and the last level is filled
+/// from the left.  Runs in O(n) time using a constant amount of extra space.
+/// 
+/// Each node is given a new balance from [TreeBalance::new_rebuilt] and has its cached information updated.
+/// Returns the new root.
+/// 
+/// # Panics
+/// 
+/// This function panics if [TreeBalance::new_rebuilt] returns None
+pub fn bst_dsw<T: Ord, U: TreeBalance, A: Augment<T>>(root: Subtree<T, U, A>) -> Subtree<T, U, A> {
+    let mut head = match root.into_inner() {
+        Some(r) => r,
+        None => return Subtree::new()
+    };
+
+    // Unfold the tree into a vine, counting the nodes as they join it
+    let mut len = 0usize;
+    let mut next = Some(Rc::clone(&head));
+    while let Some(x) = next {
+        let left = x.borrow().get_child(Left).is_some();
+        if left {
+            let top = bst_rotate(x, Left);
+            if top.borrow().get_parent().is_none() {
+                head = Rc::clone(&top);
+            }
+            next = Some(top);
+        } else {
+            len += 1;
+            next = x.borrow().get_child(Right).map(Rc::clone);
+        }
+    }
+
+    // Fold the nodes of the last level under the first nodes of the vine,
+    // then fold the rest of the vine into a perfect tree
+    let mut full = (1 << (len + 1).ilog2()) - 1;
+    head = dsw_compress(head, len - full);
+    while full > 1 {
+        full /= 2;
+        head = dsw_compress(head, full);
+    }
+
+    // The nodes left on the vine were updated before their right subtrees were finished
+    let mut last = Rc::clone(&head);
+    loop {
+        let right = last.borrow().get_child(Right).map(Rc::clone);
+        match right {
+            Some(r) => last = r,
+            None => break
+        }
+    }
+    last.borrow_mut().update();
+    bst_update_to_root(last);
+
+    // Give each node a new balance from its place in the complete tree
+    let mut next = Some(bst_leftmost(Rc::clone(&head)));
+    let mut index = 0;
+    while let Some(x) = next {
+        let (height, parent_height) = dsw_heights(index, len);
+        *x.borrow_mut().get_balance_mut() = U::new_rebuilt(height, parent_height).unwrap();
+        next = bst_successor(&x);
+        index += 1;
+    }
+    Subtree::new_with(head)
+}
+
+/// Rotates the first `count` nodes at odd positions of the vine with the given head down
+/// to become the left child of the node after them
+/// 
+/// Returns the new head of the vine
+fn dsw_compress<T: Ord, U: TreeBalance, A: Augment<T>>(mut head: TreeBranch<T, U, A>, count: usize) -> TreeBranch<T, U, A> {
+    let mut next = Some(Rc::clone(&head));
+    for i in 0..count {
+        let top = bst_rotate(next.unwrap(), Right);
+        if i == 0 {
+            head = Rc::clone(&top);
+        }
+        next = top.borrow().get_child(Right).map(Rc::clone);
+    }
+    head
+}
+
+/// Returns the height of the node at the given in order index of a complete tree
+/// with `len` nodes and the height of its parent (None for the root)
+/// 
+/// The complete tree is treated as a perfect tree (the skeleton) with the nodes of the
+/// last level hanging below it, which fill the in order positions 0, 2, 4, ... from the left
+fn dsw_heights(index: usize, len: usize) -> (usize, Option<usize>) {
+    let levels = (len + 1).ilog2();
+    let hanging = len - ((1 << levels) - 1);
+
+    // Height of the skeleton node at the given (one based) in order position,
+    // which is one more if the leftmost path below it reaches the last level
+    let skeleton_height = |s: usize| {
+        let level = s.trailing_zeros();
+        level as usize + 1 + usize::from(s - (1 << level) < hanging)
+    };
+    // Position of the skeleton node's parent in the skeleton, None for the root
+    let skeleton_parent = |s: usize| {
+        let level = s.trailing_zeros();
+        if level + 1 == levels {
+            None
+        } else if (s >> (level + 1)) & 1 == 0 {
+            Some(s + (1 << level))
+        } else {
+            Some(s - (1 << level))
+        }
+    };
+
+    if index < 2 * hanging && index.is_multiple_of(2) {
+        // Hangs below a leaf of the skeleton, on the left of the next skeleton node or the right of the previous
+        let s = index / 2 + 1;
+        let parent = if s % 2 == 1 { s } else { s - 1 };
+        (1, Some(skeleton_height(parent)))
+    } else {
+        let s = if index < 2 * hanging { index.div_ceil(2) } else { index - hanging + 1 };
+        (skeleton_height(s), skeleton_parent(s).map(skeleton_height))
+    }
+}
+
+/// Returns the node with the smallest key in the subtree rooted at the given node
+fn bst_leftmost<T: Ord, U: TreeBalance, A: Augment<T>>(node: TreeBranch<T, U, A>) -> TreeBranch<T, U, A> {
+    let mut x = node;
+    loop {
+        let left = x.borrow().get_child(Left).map(Rc::clone);
+        match left {
+            Some(l) => x = l,
+            None => return x
+        }
+    }
+}
+
+/// Returns the node that follows the given node in order, if there is one
+fn bst_successor<T: Ord, U: TreeBalance, A: Augment<T>>(node: &TreeBranch<T, U, A>) -> Option<TreeBranch<T, U, A>> {
+    let right = node.borrow().get_child(Right).map(Rc::clone);
+    if let Some(r) = right {
+        return Some(bst_leftmost(r))
+    }
+
+    // Travel up until we come from a left child
+    let mut x = Rc::clone(node);
+    loop {
+        let parent = x.borrow().get_parent()?;
+        let from_left = parent.borrow().get_child(Left).is_some_and(|l| Rc::ptr_eq(l, &x));
+        if from_left {
+            return Some(parent)
+        }
+        x = parent;
+    }
+}
+
+/// Returns the depth of the given node, counting the root as depth one
+pub fn bst_depth<T: Ord, U: TreeBalance, A: Augment<T>>(node: &TreeBranch<T, U, A>) -> usize {
+    let mut depth = 1;
+    let mut next = node.borrow().get_parent();
+    while let Some(p) = next {
+        depth += 1;
+        next = p.borrow().get_parent();
+    }
+    depth
+}
+
+/// Returns the aggregate of the keys within the range of the tree with the given root
+/// 
+/// Searches down for the highest node whose key is within the range, then follows the two paths
+/// towards the bounds of the range from it (see [aggregate_bound]) in O(log n) for a balanced tree.
+/// Lazily deleted keys are left out.
+pub fn bst_aggregate<T: Ord, U: TreeBalance, A: Augment<T>, R: RangeBounds<T>>(root: &Subtree<T, U, A>, range: &R) -> A::Value {
+    let after_start = |key: &T| match range.start_bound() {
+        Bound::Included(start) => key >= start,
+        Bound::Excluded(start) => key > start,
+        Bound::Unbounded => true
+    };
+    let before_end = |key: &T| match range.end_bound() {
+        Bound::Included(end) => key <= end,
+        Bound::Excluded(end) => key < end,
+        Bound::Unbounded => true
+    };
+
+    // Find the node splitting the range
+    let mut next = root.branch().map(Rc::clone);
+    let split = loop {
+        let node = match next {
+            Some(node) => node,
+            None => return A::empty()
+        };
+        let path = {
+            let n = node.borrow();
+            if !after_start(n.get_key()) {
+                Some(Right)
+            } else if !before_end(n.get_key()) {
+                Some(Left)
+            } else {
+                None
+            }
+        };
+        match path {
+            Some(path) => next = node.borrow().get_child(path).map(Rc::clone),
+            None => break node
+        }
+    };
+
+    let node = split.borrow();
+    let left = match range.start_bound() {
+        Bound::Unbounded => node.get_child(Left).map_or_else(A::empty, |l| l.borrow().get_aggregate().clone()),
+        _ => aggregate_bound(node.get_child(Left), Left, after_start)
+    };
+    let right = match range.end_bound() {
+        Bound::Unbounded => node.get_child(Right).map_or_else(A::empty, |r| r.borrow().get_aggregate().clone()),
+        _ => aggregate_bound(node.get_child(Right), Right, before_end)
+    };
+    node.combine_aggregate(&left, &right)
+}
+
+/// Returns the aggregate of the keys of a tree that pass the check of one bound of a range
+/// 
+/// Every key of the tree must pass the check of the other bound.  Follows the path towards the
+/// bound given by `toward`: a node passing the check is included along with its subtree away
+/// from the bound, otherwise only the subtree away from the bound can hold keys within the range.
+fn aggregate_bound<T: Ord, U: TreeBalance, A: Augment<T>, F: Fn(&T) -> bool>(node: Option<&TreeBranch<T, U, A>>, toward: TreePath, within: F) -> A::Value {
+    // Collect the included nodes on the path, then combine them from the bottom up
+    let mut included = Vec::new();
+    let mut next = node.map(Rc::clone);
+    while let Some(node) = next {
+        let path = if within(node.borrow().get_key()) {
+            toward
+        } else {
+            toward.reflect()
+        };
+        next = node.borrow().get_child(path).map(Rc::clone);
+        if path == toward {
+            included.push(node);
+        }
+    }
+
+    included.iter().rev().fold(A::empty(), |below, node| {
+        let n = node.borrow();
+        let away = n.get_child(toward.reflect()).map_or_else(A::empty, |c| c.borrow().get_aggregate().clone());
+        match toward {
+            Left => n.combine_aggregate(&below, &away),
+            Right => n.combine_aggregate(&away, &below)
+        }
+    })
+}
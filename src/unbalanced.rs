@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use core::fmt::Debug;
 
 use crate::tree::TreePath;
 use crate::tree::inspect::*;
@@ -7,30 +7,38 @@ use crate::tree::inspect::*;
 /// This effectively takes a [Tree] and makes it a regular binary search tree
 pub struct UnbalancedBalance();
 impl TreeBalance for UnbalancedBalance {
+    type State = ();
+    type Cache = ();
 
-    fn rebalance_insert<T: Ord>(node: NodeInspector<T, Self>, _: (TreePath, TreePath)) -> TreePosition<T, Self> {
+    fn rebalance_insert<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
         node.into_position(NodeOffset::Root)
     }
 
-    fn new() -> Self {
+    fn new<T: Ord>(_: &mut BalanceContext<T>) -> Self {
         UnbalancedBalance()
     }
 
-    fn new_root() -> Self {
+    fn new_root<T: Ord>(_: &mut BalanceContext<T>) -> Self {
         UnbalancedBalance()
     }
 
-    fn rebalance_delete<T: Ord>(node: NodeInspector<T, Self>, _: TreePath, _: &Self) -> TreePosition<T, Self> {
+    fn new_rebuilt(_: usize, _: Option<usize>) -> Option<Self> {
+        Some(UnbalancedBalance())
+    }
+
+    fn rebalance_delete<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: TreePath, _: &Self) -> TreePosition<T, Self, A> {
         node.into_position(NodeOffset::Root)
     }
 
+    fn state<T: Ord, A: Augment<T>>(_: &NodeInspector<T, Self, A>) -> Self::State {}
+
     fn adjust_root(&mut self) {
         // Do nothing
     }
 }
 
 impl Debug for UnbalancedBalance {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
         write!(f, "None")
     }
 }
@@ -38,6 +46,8 @@ impl Debug for UnbalancedBalance {
 mod tests {
     use super::UnbalancedBalance;
     use crate::Tree;
+    use crate::tree::TreeRng;
+    use crate::tree::inspect::TreeBalance;
 
     #[test]
     fn insert() {
@@ -60,4 +70,105 @@ mod tests {
         tree.delete(&40);
         assert_eq!(tree.height(), 3)
     }
+
+    #[test]
+    fn debug_shape() {
+        let mut tree: Tree<u32, UnbalancedBalance> = Tree::new();
+
+        for key in [2, 1, 3, 4] {
+            tree.insert(key);
+        }
+        assert_eq!(
+            format!("{:?}", tree),
+            "2 { balance: None, height: 3, leaves: 2, \
+            right: 3 { balance: None, height: 2, leaves: 1, \
+            right: 4 { balance: None, height: 1, leaves: 1, right: \"None\", left: \"None\" }, left: \"None\" }, \
+            left: 1 { balance: None, height: 1, leaves: 1, right: \"None\", left: \"None\" } }"
+        );
+
+        // Printing a path measures each node once
+        for key in 5..500 {
+            tree.insert(key);
+        }
+        assert!(format!("{:?}", tree).starts_with("2 { balance: None, height: 498, leaves: 2,"));
+    }
+
+    #[test]
+    fn rebuild() {
+        let mut tree: Tree<u32, UnbalancedBalance> = Tree::new();
+
+        for key in 0..1000 {
+            tree.insert(key);
+        }
+        assert_eq!(tree.height(), 1000);
+        assert!(tree.rebuild());
+        assert_eq!(tree.height(), 10);
+        assert_eq!(tree.leaves(), 500);
+        assert_eq!(tree.validate(), Ok(()))
+    }
+
+    #[test]
+    fn auto_rebuild() {
+        let mut tree: Tree<u32, UnbalancedBalance> = Tree::with_auto_rebuild(2.0);
+
+        for key in 0..1000 {
+            tree.insert(key);
+            assert!(tree.height() <= 2 * (usize::BITS - tree.len().leading_zeros()) as usize);
+        }
+        for key in (0..1000).rev().step_by(2) {
+            tree.delete(&key);
+        }
+        assert_eq!(tree.len(), 500);
+        assert_eq!(tree.validate(), Ok(()));
+
+        // Without a limit the tree is left as a path
+        tree.set_auto_rebuild(None);
+        for key in 1000..1100 {
+            tree.insert(key);
+        }
+        assert!(tree.height() > 100)
+    }
+    /// Checks that rebuilding trees of every size up to `max` gives a valid tree of minimal height
+    fn check_rebuild<U: TreeBalance>(max: u32) {
+        for size in 0..max {
+            let mut tree: Tree<u32, U> = Tree::new();
+            let mut rng = TreeRng::new(u64::from(size));
+            for _ in 0..size {
+                tree.insert((rng.next_u64() % u64::from(2 * max)) as u32);
+            }
+            let keys = format!("{}", tree);
+
+            assert!(tree.rebuild());
+            assert_eq!(tree.validate(), Ok(()));
+            assert_eq!(tree.height(), (usize::BITS - tree.len().leading_zeros()) as usize);
+            assert_eq!(format!("{}", tree), keys);
+
+            // The tree should keep working after being rebuilt
+            for key in 0..max {
+                tree.delete(&(key * 2));
+                tree.insert(key * 2 + 1);
+            }
+            assert_eq!(tree.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn rebuild_every_size() {
+        check_rebuild::<crate::avl::AVLBalance>(70);
+        check_rebuild::<crate::avl::RelaxedAVLBalance<2>>(70);
+        check_rebuild::<crate::redblack::RedBlackBalance>(70);
+        check_rebuild::<crate::redblack::TopDownRedBlackBalance>(70);
+        check_rebuild::<crate::splay::SplayBalance>(70);
+        check_rebuild::<UnbalancedBalance>(70);
+        check_rebuild::<crate::wavl::WAVLBalance>(70);
+
+        // Balances that cannot be rebuilt are left alone
+        let mut treap: crate::TreapTree<u32> = crate::TreapTree::new();
+        for key in 0..100 {
+            treap.insert(key);
+        }
+        let shape = format!("{:?}", treap);
+        assert!(!treap.rebuild());
+        assert_eq!(format!("{:?}", treap), shape);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,266 @@
+use core::fmt::Debug;
+
+use crate::tree::TreePath;
+use crate::tree::inspect::*;
+
+/// Implementation of a balance for a binary tree
+/// that effectively converts a [Tree] into a WAVL (weak AVL) Tree
+///
+/// Each node stores a rank, and the rank difference between a node and each of its children
+/// (missing children have rank zero) must be one or two, with leaves at rank one.
+/// Insertions are rebalanced the same way as an AVL tree, so a tree that is only inserted into
+/// has the same shape as an [AVLTree](crate::AVLTree).  Deletions allow rank differences of two
+/// to build up instead of restoring heights, which limits them to at most two rotations.
+pub struct WAVLBalance {
+    rank: usize
+}
+
+impl TreeBalance for WAVLBalance {
+    type State = usize;
+    type Cache = ();
+
+    fn rebalance_insert<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, path: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
+        // The child on the path is fixed here since rotating it can bring the inserted node up to be
+        // a child of this node (which would stop rebalancing if it was done from the child itself)
+        let (node, promoted) = match path.1 {
+            Some(xpath) => fix_insert(node, &[path.0], xpath),
+            // The inserted node is the child, a new leaf promoted from rank zero
+            None => (node, true)
+        };
+        if !promoted {
+            node.into_position(NodeOffset::Root)
+        } else if node.inspect_is_root() {
+            fix_insert(node, &[], path.0).0.into_position(NodeOffset::Root)
+        } else {
+            node.into_position(NodeOffset::Parent)
+        }
+    }
+
+    fn new<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+        WAVLBalance { rank: 1 }
+    }
+
+    fn new_root<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+        WAVLBalance { rank: 1 }
+    }
+
+    fn new_rebuilt(height: usize, _: Option<usize>) -> Option<Self> {
+        // Every AVL tree is a WAVL tree with its heights as ranks
+        Some(WAVLBalance { rank: height })
+    }
+
+    fn rebalance_delete<T: Ord, A: Augment<T>>(mut node: NodeInspector<T, Self, A>, xpath: TreePath, _: &Self) -> TreePosition<T, Self, A> {
+        let spath = xpath.reflect();
+        let (rank, xrank, srank) = ranks(&node);
+        let (xrank, srank) = match xpath {
+            TreePath::Left => (xrank, srank),
+            TreePath::Right => (srank, xrank)
+        };
+
+        // A leaf must have rank one
+        if xrank == 0 && srank == 0 {
+            if rank == 1 {
+                return node.into_position(NodeOffset::Root);
+            }
+            node.update_balance(|b| b.rank = 1);
+            return node.into_position(NodeOffset::Parent);
+        }
+        if rank - xrank < 3 {
+            return node.into_position(NodeOffset::Root);
+        }
+
+        // The deleted side is three ranks below this node
+        if rank - srank == 2 {
+            node.update_balance(|b| b.rank -= 1);
+            return node.into_position(NodeOffset::Parent);
+        }
+        let (inner, outer) = {
+            let snode = node.inspect_child(spath).unwrap();
+            let rank = |path| snode.inspect_child(path).map_or(0, |n| n.inspect_balance(|b| b.rank));
+            (rank(xpath), rank(spath))
+        };
+        if srank - inner == 2 && srank - outer == 2 {
+            node.update_balance(|b| b.rank -= 1);
+            node.inspect_child(spath).unwrap().update_balance(|b| b.rank -= 1);
+            return node.into_position(NodeOffset::Parent);
+        }
+
+        if srank - outer == 1 {
+            // Single rotation bringing the sibling up
+            node = node.rotate((spath, spath));
+            node.update_balance(|b| b.rank += 1);
+            let mut xnode = node.inspect_child(xpath).unwrap();
+            let leaf = xnode.inspect_child(TreePath::Left).is_none() && xnode.inspect_child(TreePath::Right).is_none();
+            xnode.update_balance(|b| b.rank -= if leaf { 2 } else { 1 });
+        } else {
+            // Double rotation bringing the sibling's inner child up
+            node = node.rotate((spath, xpath));
+            node.update_balance(|b| b.rank += 2);
+            { node.inspect_child(spath).unwrap().update_balance(|b| b.rank -= 1); }
+            { node.inspect_child(xpath).unwrap().update_balance(|b| b.rank -= 2); }
+        }
+        node.into_position(NodeOffset::Root)
+    }
+
+    fn verify<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Result<(), ValidationError> {
+        let (rank, left, right) = ranks(node);
+        if left == 0 && right == 0 && rank != 1 {
+            Err(ValidationError::Balance("WAVL leaf is not at rank one"))
+        } else if !(1..=2).contains(&(rank - left.min(rank))) || !(1..=2).contains(&(rank - right.min(rank))) {
+            Err(ValidationError::Balance("WAVL rank difference is not one or two"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn state<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Self::State {
+        node.inspect_balance(|b| b.rank)
+    }
+
+    fn adjust_root(&mut self) {
+        // Do nothing
+    }
+}
+
+impl Debug for WAVLBalance {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        write!(f, "{}", self.rank)
+    }
+}
+
+/// Returns the ranks of a node and its left and right children
+///
+/// Missing children have rank zero
+fn ranks<'a, T: Ord + 'a, A: Augment<T> + 'a, I: InspectNode<'a, T, WAVLBalance, A>>(node: &'a I) -> (usize, usize, usize) {
+    let rank = |path| node.inspect_child(path).map_or(0, |n| n.inspect_balance(|b| b.rank));
+    (node.inspect_balance(|b| b.rank), rank(TreePath::Left), rank(TreePath::Right))
+}
+
+/// Returns the ranks of a node, its child on the given path, its other child
+/// and the inner child of the child on the path
+fn insert_ranks<'a, T: Ord + 'a, A: Augment<T> + 'a, I: InspectNode<'a, T, WAVLBalance, A>>(node: &'a I, xpath: TreePath) -> (usize, usize, usize, usize) {
+    let rank = |path| node.inspect_child(path).map_or(0, |n| n.inspect_balance(|b| b.rank));
+    let inner = node.inspect_child(xpath).map_or(0, |x| x.inspect_child(xpath.reflect()).map_or(0, |n| n.inspect_balance(|b| b.rank)));
+    (node.inspect_balance(|b| b.rank), rank(xpath), rank(xpath.reflect()), inner)
+}
+
+/// Fixes the node found by following the paths if its child on the given path has the same rank
+///
+/// The node is promoted if its other child is one rank below it, otherwise it is rotated which
+/// finishes rebalancing.  Returns true if the node was promoted.
+fn fix_insert<T: Ord, A: Augment<T>>(mut node: NodeInspector<T, WAVLBalance, A>, paths: &[TreePath], xpath: TreePath) -> (NodeInspector<T, WAVLBalance, A>, bool) {
+    let ypath = xpath.reflect();
+    let (rank, xrank, yrank, inner) = match *paths {
+        [] => insert_ranks(&node, xpath),
+        [path] => insert_ranks(&node.inspect_child(path).unwrap(), xpath),
+        _ => unreachable!()
+    };
+    if xrank != rank {
+        return (node, false);
+    }
+
+    if rank - yrank == 1 {
+        update_at(&mut node, paths, |b| b.rank += 1);
+        return (node, true);
+    }
+    let xpaths = [paths, &[xpath]].concat();
+    let ypaths = [paths, &[ypath]].concat();
+    if xrank - inner == 1 {
+        // Double rotation bringing the inner child of the child up
+        node = node.rotate_at(&xpaths, ypath).rotate_at(paths, xpath);
+        update_at(&mut node, paths, |b| b.rank += 1);
+        update_at(&mut node, &xpaths, |b| b.rank -= 1);
+    } else {
+        // Single rotation bringing the child up
+        node = node.rotate_at(paths, xpath);
+    }
+    update_at(&mut node, &ypaths, |b| b.rank -= 1);
+    (node, false)
+}
+
+/// Updates the rank of the node found by following the paths
+fn update_at<T: Ord, A: Augment<T>>(node: &mut NodeInspector<T, WAVLBalance, A>, paths: &[TreePath], apply: impl FnOnce(&mut WAVLBalance)) {
+    match *paths {
+        [] => node.update_balance(apply),
+        [path] => node.inspect_child(path).unwrap().update_balance(apply),
+        [path, next] => node.inspect_child(path).unwrap().inspect_child(next).unwrap().update_balance(apply),
+        _ => unreachable!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WAVLBalance;
+    use crate::Tree;
+    use crate::avl::AVLBalance;
+
+    #[test]
+    fn insert() {
+        let mut tree: Tree<u32, WAVLBalance> = Tree::new();
+
+        tree.insert(40);
+        tree.insert(50);
+        tree.insert(60);
+        assert_eq!(tree.height(), 2)
+    }
+
+    /// Removes the balances and cached heights from a printed tree leaving its shape
+    fn shape(mut tree: String) -> String {
+        for field in ["balance: ", "height: "] {
+            while let Some(start) = tree.find(field) {
+                let end = start + tree[start..].find(", ").unwrap() + 2;
+                tree.replace_range(start..end, "");
+            }
+        }
+        tree
+    }
+
+    #[test]
+    fn insert_matches_avl() {
+        let mut wavl: Tree<u32, WAVLBalance> = Tree::new();
+        let mut avl: Tree<u32, AVLBalance> = Tree::new();
+
+        let mut seed = 13u32;
+        for _ in 0..1000 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let key = (seed >> 8) % 2000;
+            wavl.insert(key);
+            avl.insert(key);
+        }
+        assert_eq!(shape(format!("{:?}", wavl)), shape(format!("{:?}", avl)));
+        assert_eq!(wavl.height(), avl.height());
+        assert_eq!(wavl.rotations(), avl.rotations());
+    }
+
+    #[test]
+    fn delete() {
+        let mut tree: Tree<u32, WAVLBalance> = Tree::new();
+
+        tree.insert(40);
+        tree.insert(50);
+        tree.insert(60);
+        tree.insert(70);
+        tree.delete(&40);
+        assert_eq!(tree.height(), 2);
+        assert_eq!(tree.validate(), Ok(()))
+    }
+
+    #[test]
+    fn delete_rotations() {
+        let mut tree: Tree<u32, WAVLBalance> = Tree::new();
+
+        let mut seed = 5u32;
+        for _ in 0..4000 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let key = (seed >> 8) % 1000;
+            if seed & 3 == 0 {
+                let rotations = tree.rotations();
+                tree.delete(&key);
+                assert!(tree.rotations() - rotations <= 2);
+            } else {
+                tree.insert(key);
+            }
+            assert_eq!(tree.validate(), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use core::fmt::Debug;
+
+use crate::tree::TreePath;
+use crate::tree::cache::Size;
+use crate::tree::inspect::*;
+
+/// Implementation of a balance for a binary tree
+/// that effectively converts a [Tree] into a weight balanced (BB[α]) Tree
+///
+/// The weight of a tree is its size plus one.  Each subtree of a node must weigh at least
+/// `ALPHA` percent of the node's weight, a node breaking this is fixed with a single or double
+/// rotation chosen from the weights of the heavier child's subtrees.  `ALPHA` must be between
+/// 19 and 29 (inclusive) for rotations to always restore the balance, higher values keep the
+/// tree closer to balanced at the cost of more rotations.
+pub struct WeightBalance<const ALPHA: usize = 29>();
+
+impl <const ALPHA: usize> WeightBalance<ALPHA> {
+    /// Fails to compile if ALPHA is out of range
+    const VALID_ALPHA: () = assert!(ALPHA >= 19 && ALPHA <= 29, "ALPHA must be between 19 and 29");
+
+    /// Rotates the node found by following the paths if either of its subtrees is too light
+    fn fix<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, paths: &[TreePath]) -> NodeInspector<T, Self, A> {
+        let heavy = {
+            let (left, right) = match *paths {
+                [] => weights(&node),
+                [path] => weights(&node.inspect_child(path).unwrap()),
+                _ => unreachable!()
+            };
+            if left * 100 < ALPHA * (left + right) {
+                Some(TreePath::Right)
+            } else if right * 100 < ALPHA * (left + right) {
+                Some(TreePath::Left)
+            } else {
+                None
+            }
+        };
+        let hpath = match heavy {
+            Some(hpath) => hpath,
+            None => return node
+        };
+
+        let hpaths = [paths, &[hpath]].concat();
+        let (inner, outer) = match *paths {
+            [] => child_weights(&node.inspect_child(hpath).unwrap(), hpath),
+            [path] => child_weights(&node.inspect_child(path).unwrap().inspect_child(hpath).unwrap(), hpath),
+            _ => unreachable!()
+        };
+        if inner * (200 - ALPHA) < 100 * (inner + outer) {
+            // Single rotation bringing the heavy child up
+            node.rotate_at(paths, hpath)
+        } else {
+            // Double rotation bringing the inner child of the heavy child up
+            node.rotate_at(&hpaths, hpath.reflect()).rotate_at(paths, hpath)
+        }
+    }
+}
+
+impl <const ALPHA: usize> TreeBalance for WeightBalance<ALPHA> {
+    type State = ();
+    type Cache = Size;
+
+    fn rebalance_insert<T: Ord, A: Augment<T>>(mut node: NodeInspector<T, Self, A>, path: (TreePath, Option<TreePath>)) -> TreePosition<T, Self, A> {
+        // The child on the path is fixed here since rotating it can bring the inserted node up to be
+        // a child of this node (which would stop rebalancing if it was done from the child itself).
+        // Every ancestor gains weight so this continues to the root, which is fixed by itself.
+        if path.1.is_some() {
+            node = Self::fix(node, &[path.0]);
+        }
+        if node.inspect_is_root() {
+            node = Self::fix(node, &[]);
+        }
+        node.into_position(NodeOffset::Parent)
+    }
+
+    fn new<T: Ord>(_: &mut BalanceContext<T>) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::VALID_ALPHA;
+        WeightBalance()
+    }
+
+    fn new_root<T: Ord>(context: &mut BalanceContext<T>) -> Self {
+        Self::new(context)
+    }
+
+    fn new_rebuilt(_: usize, _: Option<usize>) -> Option<Self> {
+        // Rebuilt trees are split around the median or complete, so every subtree weighs at least a third of its parent
+        Some(WeightBalance())
+    }
+
+    fn rebalance_delete<T: Ord, A: Augment<T>>(node: NodeInspector<T, Self, A>, _: TreePath, _: &Self) -> TreePosition<T, Self, A> {
+        Self::fix(node, &[]).into_position(NodeOffset::Parent)
+    }
+
+    fn verify<T: Ord, A: Augment<T>>(node: &NodeInspector<T, Self, A>) -> Result<(), ValidationError> {
+        let (left, right) = weights(node);
+        if left.min(right) * 100 < ALPHA * (left + right) {
+            Err(ValidationError::Balance("Weight balanced subtree is lighter than alpha of its parent"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn state<T: Ord, A: Augment<T>>(_: &NodeInspector<T, Self, A>) -> Self::State {}
+
+    fn adjust_root(&mut self) {
+        // Do nothing
+    }
+}
+
+impl <const ALPHA: usize> Debug for WeightBalance<ALPHA> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        write!(f, "BB[{}]", ALPHA)
+    }
+}
+
+/// Returns the weights of the left and right subtrees of a node
+///
+/// Missing subtrees weigh one
+fn weights<'a, T: Ord + 'a, U: TreeBalance + 'a, A: Augment<T> + 'a, I: InspectNode<'a, T, U, A>>(node: &'a I) -> (usize, usize) {
+    let weight = |path| node.inspect_child(path).map_or(0, |n| n.inspect_size()) + 1;
+    (weight(TreePath::Left), weight(TreePath::Right))
+}
+
+/// Returns the weights of the inner and outer subtrees of a node that is a child on the given path
+fn child_weights<'a, T: Ord + 'a, U: TreeBalance + 'a, A: Augment<T> + 'a, I: InspectNode<'a, T, U, A>>(node: &'a I, path: TreePath) -> (usize, usize) {
+    let (left, right) = weights(node);
+    match path {
+        TreePath::Left => (right, left),
+        TreePath::Right => (left, right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WeightBalance;
+    use crate::Tree;
+
+    #[test]
+    fn insert() {
+        let mut tree: Tree<u32, WeightBalance> = Tree::new();
+
+        tree.insert(40);
+        tree.insert(50);
+        tree.insert(60);
+        assert_eq!(tree.height(), 2)
+    }
+
+    #[test]
+    fn insert_ascending() {
+        let mut tree: Tree<u32, WeightBalance> = Tree::new();
+
+        for key in 0..1023 {
+            tree.insert(key);
+        }
+        assert_eq!(tree.validate(), Ok(()));
+        // The height of a BB[α] tree is at most log(n + 1) / log(1 / (1 - α))
+        assert!(tree.height() <= 21)
+    }
+
+    #[test]
+    fn delete() {
+        let mut tree: Tree<u32, WeightBalance> = Tree::new();
+
+        tree.insert(40);
+        tree.insert(50);
+        tree.insert(60);
+        tree.insert(70);
+        tree.delete(&40);
+        tree.delete(&50);
+        assert_eq!(tree.height(), 2);
+        assert_eq!(tree.validate(), Ok(()))
+    }
+
+    #[test]
+    fn validate() {
+        let mut loose: Tree<u32, WeightBalance<19>> = Tree::new();
+        let mut tight: Tree<u32, WeightBalance<29>> = Tree::new();
+
+        let mut seed = 11u32;
+        for _ in 0..4000 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let key = (seed >> 8) % 1000;
+            if seed & 3 == 0 {
+                loose.delete(&key);
+                tight.delete(&key);
+            } else {
+                loose.insert(key);
+                tight.insert(key);
+            }
+            assert_eq!(loose.validate(), Ok(()));
+            assert_eq!(tight.validate(), Ok(()));
+        }
+        assert!(tight.rotations() >= loose.rotations())
+    }
+}